@@ -0,0 +1,14 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec3 Color;
+    vec2 Uv;
+} IN;
+
+uniform sampler2D Texture;
+
+out vec4 Color;
+
+void main() {
+    Color = texture(Texture, IN.Uv) * vec4(IN.Color, 1.0f);
+}
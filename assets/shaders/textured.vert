@@ -0,0 +1,22 @@
+#version 330 core
+
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec3 Color;
+layout (location = 2) in vec2 Uv;
+
+// Assigned to binding 0 by TexturedShader.
+layout (std140) uniform Camera {
+    mat4 View;
+    mat4 Projection;
+};
+
+out VS_OUTPUT {
+    vec3 Color;
+    vec2 Uv;
+} OUT;
+
+void main() {
+    gl_Position = Projection * View * vec4(Position, 1.0);
+    OUT.Color = Color;
+    OUT.Uv = Uv;
+}
@@ -3,7 +3,7 @@ use std::{env, fs::File, path::Path};
 
 fn main() {
     let dest = env::var("OUT_DIR").unwrap();
-    let mut file_bindings = File::create(&Path::new(&dest).join("bindings.rs")).unwrap();
+    let mut file_bindings = File::create(Path::new(&dest).join("bindings.rs")).unwrap();
 
     let registry = Registry::new(Api::Gl, (4, 6), Profile::Core, Fallbacks::All, []);
 
@@ -20,4 +20,22 @@ fn main() {
             .write_bindings(StructGenerator, &mut file_bindings)
             .unwrap()
     }
+
+    // EGL is only used to create headless contexts that don't need a window or a display server.
+    // The surfaceless platform is a Mesa extension which works with llvmpipe in CI.
+    let mut file_egl = File::create(Path::new(&dest).join("egl_bindings.rs")).unwrap();
+    Registry::new(
+        Api::Egl,
+        (1, 5),
+        Profile::Core,
+        Fallbacks::All,
+        [
+            "EGL_EXT_platform_base",
+            "EGL_KHR_create_context",
+            "EGL_KHR_surfaceless_context",
+            "EGL_MESA_platform_surfaceless",
+        ],
+    )
+    .write_bindings(StructGenerator, &mut file_egl)
+    .unwrap();
 }
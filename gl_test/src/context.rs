@@ -1,8 +1,12 @@
 mod bindings;
 mod clear;
+#[cfg(unix)]
+pub mod headless;
 pub mod info;
+mod patch;
 mod rect;
 
 pub use bindings::{gl, Gl};
 pub use clear::{Clear, Color};
+pub use patch::Patch;
 pub use rect::{Rect, Size};
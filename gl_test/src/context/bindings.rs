@@ -1,14 +1,24 @@
-use self::gl::types::{GLboolean, GLchar, GLenum, GLsizei, GLuint};
-use super::{Rect, Size};
-use crate::glenums::{ClearKind, DebugSeverity, DebugSource, DebugType, DrawMode, GetString};
-use log::{error, info, warn};
+use self::gl::types::{GLboolean, GLchar, GLenum, GLint, GLsizei, GLuint, GLvoid};
+use super::{info::ContextInfo, Rect, Size};
+use crate::{
+    glenums::{
+        ClearKind, DebugSeverity, DebugSource, DebugType, DrawMode, GetString, MemoryBarrier,
+        ObjectName,
+    },
+    glerror::GlError,
+};
+use image::RgbaImage;
+use log::{debug, error, info, warn, Level};
 use std::{
     borrow::Cow,
+    cell::OnceCell,
     ffi::{c_void, CStr, CString},
     ops::Deref,
+    path::Path,
     rc::Rc,
 };
 
+#[allow(clippy::all)]
 pub mod gl {
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 }
@@ -16,6 +26,8 @@ pub mod gl {
 #[derive(Clone)]
 pub struct Gl {
     context: gl::Gl,
+    // Queried on first use since listing every extension is slow
+    info: OnceCell<ContextInfo>,
 }
 
 impl Gl {
@@ -25,27 +37,18 @@ impl Gl {
         F: FnMut(&'static str) -> *const c_void,
     {
         let context = gl::Gl::load_with(gl_loader);
-        Self { context }.into()
+        Self {
+            context,
+            info: OnceCell::new(),
+        }
+        .into()
     }
 
-    /// Insert compiled shaders
-    /*pub fn insert_shader(&mut self, program: ShaderProgram) -> &ShaderProgram {
-        let label = program.label();
-        self.shaders.insert(label.clone(), program);
-
-        self.get_shader(&label).unwrap_or_else(|| {
-            panic!(
-                "Impossible: Shader doesn't exist despite just being added. Label: {}",
-                &label
-            )
-        })
+    /// Version and extensions of the context, which are only queried once.
+    pub fn info(self: &Rc<Self>) -> &ContextInfo {
+        self.info.get_or_init(|| ContextInfo::new(self))
     }
 
-    /// Retrieve shader program by name
-    pub fn get_shader(&self, name: &str) -> Option<&ShaderProgram> {
-        self.shaders.get(name)
-    }*/
-
     pub fn get_string<'gl>(
         &'gl self,
         what: GetString,
@@ -69,7 +72,7 @@ impl Gl {
         };
 
         // GetString may return a null pointer on error
-        if get_str == std::ptr::null() {
+        if get_str.is_null() {
             None
         } else {
             let get_str = unsafe { CStr::from_ptr(get_str as _) };
@@ -104,17 +107,158 @@ impl Gl {
         }
     }
 
+    /// Currently set viewport
+    pub fn current_viewport(&self) -> Rect {
+        let mut data = [0; 4];
+        unsafe {
+            // Writes x, y, width, and height in that order
+            self.GetIntegerv(gl::VIEWPORT, data.as_mut_ptr());
+        }
+
+        Rect {
+            x: data[0] as _,
+            y: data[1] as _,
+            size: Size {
+                width: data[2] as _,
+                height: data[3] as _,
+            },
+        }
+    }
+
+    /// Read RGBA pixels from the current read framebuffer.
+    ///
+    /// OpenGL's origin is the bottom left corner, so the rows are flipped to match the top left
+    /// origin used by images.
+    pub fn read_pixels(&self, rect: Rect) -> RgbaImage {
+        let (width, height) = (rect.size.width, rect.size.height);
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+
+        unsafe {
+            // Pixels are written into client memory only if a pixel pack buffer isn't bound.
+            let mut pack_buffer: GLint = 0;
+            self.GetIntegerv(gl::PIXEL_PACK_BUFFER_BINDING, &mut pack_buffer);
+            self.BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+
+            // RGBA8 rows are always four byte aligned, but the pack alignment is global state
+            // that may have been changed elsewhere.
+            let mut alignment: GLint = 4;
+            self.GetIntegerv(gl::PACK_ALIGNMENT, &mut alignment);
+            self.PixelStorei(gl::PACK_ALIGNMENT, 1);
+
+            self.ReadPixels(
+                rect.x as _,
+                rect.y as _,
+                width as _,
+                height as _,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut GLvoid,
+            );
+
+            // Restore previous state
+            self.PixelStorei(gl::PACK_ALIGNMENT, alignment);
+            self.BindBuffer(gl::PIXEL_PACK_BUFFER, pack_buffer as _);
+        }
+
+        let image = RgbaImage::from_raw(width, height, pixels)
+            .expect("Pixel buffer is allocated with the size of the image");
+        image::imageops::flip_vertical(&image)
+    }
+
+    /// Save the current viewport of the read framebuffer to a PNG.
+    pub fn screenshot<P: AsRef<Path>>(&self, path: P) -> Result<(), GlError> {
+        let path = path.as_ref();
+        info!("Saving screenshot to {}", path.to_string_lossy());
+
+        self.read_pixels(self.current_viewport())
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|e| GlError::Image(format!("{e}\nPath: {}", path.to_string_lossy())))
+    }
+
     /// Clear current buffer(s) with values set by `super::Clear`
     pub fn clear(&self, clear: ClearKind) {
         unsafe { self.Clear(clear.bits()) }
     }
 
+    /// Order incoherent shader writes, such as storage block or image stores, before later
+    /// operations that read the data in the ways given by `barriers`.
+    pub fn memory_barrier(&self, barriers: MemoryBarrier) {
+        unsafe { self.MemoryBarrier(barriers.bits()) }
+    }
+
     pub fn draw_elements(&self, mode: DrawMode, count: u32, offset: u32) {
         unsafe {
             self.DrawElements(mode.bits(), count as _, gl::UNSIGNED_INT, offset as _);
         }
     }
 
+    /// Name an object with [glObjectLabel](https://docs.gl/gl4/glObjectLabel) so that debuggers
+    /// such as RenderDoc and debug messages show the label instead of the id.
+    ///
+    /// `name` must be an existing object of type `identifier`. Objects reserved with `glGen*`
+    /// only exist once they're bound. Does nothing without OpenGL 4.3 or KHR_debug. Labels are
+    /// truncated to the implementation's maximum length.
+    pub fn object_label(&self, identifier: ObjectName, name: GLuint, label: &str) {
+        if !self.ObjectLabel.is_loaded() {
+            return;
+        }
+
+        let mut max_len: GLint = 0;
+        unsafe { self.GetIntegerv(gl::MAX_LABEL_LENGTH, &mut max_len) }
+        // The maximum includes the null terminator. Truncate on a char boundary.
+        let max_len = (max_len.max(1) - 1) as usize;
+        let mut len = label.len().min(max_len);
+        while !label.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        unsafe {
+            self.ObjectLabel(
+                identifier.bits(),
+                name,
+                len as GLsizei,
+                label.as_ptr() as *const GLchar,
+            )
+        }
+    }
+
+    /// Read an object's label back with
+    /// [glGetObjectLabel](https://docs.gl/gl4/glGetObjectLabel), e.g. for logging.
+    ///
+    /// Unlabelled objects have an empty label. Returns None without OpenGL 4.3 or KHR_debug.
+    pub fn get_object_label(&self, identifier: ObjectName, name: GLuint) -> Option<String> {
+        if !self.GetObjectLabel.is_loaded() {
+            return None;
+        }
+
+        // Query the length first. It excludes the null terminator.
+        let mut len: GLsizei = 0;
+        unsafe { self.GetObjectLabel(identifier.bits(), name, 0, &mut len, std::ptr::null_mut()) }
+        let mut label = vec![0u8; len.max(0) as usize + 1];
+        unsafe {
+            self.GetObjectLabel(
+                identifier.bits(),
+                name,
+                label.len() as GLsizei,
+                &mut len,
+                label.as_mut_ptr() as *mut GLchar,
+            )
+        }
+        label.truncate(len.max(0) as usize);
+        Some(String::from_utf8_lossy(&label).into_owned())
+    }
+
+    /// Log that object `name` is about to be deleted along with the label the driver has for it,
+    /// which also shows whether labelling worked. Only queried in debug builds with debug logging
+    /// enabled.
+    pub fn log_deletion(&self, identifier: ObjectName, name: GLuint) {
+        if cfg!(debug_assertions) && log::log_enabled!(Level::Debug) {
+            if let Some(label) = self.get_object_label(identifier, name) {
+                debug!("Deleting {identifier} {name} '{label}'");
+            }
+        }
+    }
+
     /// Creates a CString consisting of all whitespace with size len + 1
     pub fn create_whitespace_cstring(len: usize) -> CString {
         let buffer = vec![b' '; len + 1];
@@ -186,3 +330,78 @@ impl Deref for Gl {
         &self.context
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::context::headless::HeadlessContext;
+
+    #[test]
+    fn pixels_are_read_top_down() {
+        // Rows of an odd width aren't four byte aligned once the alignment isn't 1.
+        let size = Size {
+            width: 5,
+            height: 4,
+        };
+        let context = HeadlessContext::new(size).unwrap();
+        let gl = context.gl();
+        context.bind_framebuffer();
+
+        // OpenGL's origin is the bottom left, so the scissored clear paints the top half.
+        unsafe {
+            gl.ClearColor(0.0, 0.0, 1.0, 1.0);
+            gl.Clear(gl::COLOR_BUFFER_BIT);
+            gl.Enable(gl::SCISSOR_TEST);
+            gl.Scissor(0, 2, 5, 2);
+            gl.ClearColor(1.0, 0.0, 0.0, 1.0);
+            gl.Clear(gl::COLOR_BUFFER_BIT);
+            gl.Disable(gl::SCISSOR_TEST);
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 8);
+        }
+
+        let image = gl.read_pixels(Rect { x: 0, y: 0, size });
+        assert_eq!(image.dimensions(), (5, 4));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let expected = if y < 2 {
+                [255, 0, 0, 255]
+            } else {
+                [0, 0, 255, 255]
+            };
+            assert_eq!(pixel.0, expected, "({x}, {y})");
+        }
+
+        let mut alignment: GLint = 0;
+        unsafe { gl.GetIntegerv(gl::PACK_ALIGNMENT, &mut alignment) }
+        assert_eq!(alignment, 8);
+    }
+
+    #[test]
+    fn object_labels_round_trip() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let mut ids = [0; 2];
+        unsafe { gl.CreateBuffers(2, ids.as_mut_ptr()) }
+        let [labelled, unlabelled] = ids;
+
+        gl.object_label(ObjectName::Buffer, labelled, "Vertices");
+        let label = gl.get_object_label(ObjectName::Buffer, labelled);
+        assert_eq!(label.as_deref(), Some("Vertices"));
+        let label = gl.get_object_label(ObjectName::Buffer, unlabelled);
+        assert_eq!(label.as_deref(), Some(""));
+
+        // Long labels are truncated on a char boundary to leave room for the null terminator.
+        let mut max_len: GLint = 0;
+        unsafe { gl.GetIntegerv(gl::MAX_LABEL_LENGTH, &mut max_len) }
+        let max_len = max_len as usize;
+        let long = format!("{}é{}", "a".repeat(max_len - 2), "b".repeat(8));
+        gl.object_label(ObjectName::Buffer, labelled, &long);
+        let label = gl.get_object_label(ObjectName::Buffer, labelled).unwrap();
+        assert_eq!(label, "a".repeat(max_len - 2));
+
+        gl.object_label(ObjectName::Buffer, labelled, &"c".repeat(max_len));
+        let label = gl.get_object_label(ObjectName::Buffer, labelled).unwrap();
+        assert_eq!(label, "c".repeat(max_len - 1));
+
+        unsafe { gl.DeleteBuffers(2, ids.as_ptr()) }
+    }
+}
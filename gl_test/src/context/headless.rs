@@ -0,0 +1,316 @@
+//! Headless OpenGL contexts that render without a window or display server.
+//!
+//! The context is created with [EGL_MESA_platform_surfaceless](https://registry.khronos.org/EGL/extensions/MESA/EGL_MESA_platform_surfaceless.txt)
+//! which is supported by Mesa's software rasterizers (llvmpipe) as well as most Mesa hardware
+//! drivers. Surfaceless contexts don't have a default framebuffer so [HeadlessContext] creates
+//! one out of renderbuffers instead.
+
+use self::egl::types::{EGLConfig, EGLContext, EGLDisplay, EGLint};
+use super::{Gl, Size};
+use crate::{
+    glenums::{FramebufferAttachment, FramebufferTarget, RenderbufferFormat},
+    glerror::GlError,
+    memory::stateful::{Attachment, Framebuffer, Renderbuffer},
+};
+use libloading::Library;
+use log::{error, info};
+use std::{
+    ffi::{c_void, CString},
+    mem::ManuallyDrop,
+    os::raw,
+    rc::Rc,
+};
+
+#[allow(clippy::all, non_camel_case_types, dead_code)]
+pub mod egl {
+    pub type khronos_utime_nanoseconds_t = khronos_uint64_t;
+    pub type khronos_uint64_t = u64;
+    pub type khronos_ssize_t = std::os::raw::c_long;
+    pub type EGLint = i32;
+    pub type EGLNativeDisplayType = *const std::os::raw::c_void;
+    pub type EGLNativePixmapType = *const std::os::raw::c_void;
+    pub type EGLNativeWindowType = *const std::os::raw::c_void;
+    pub type NativeDisplayType = EGLNativeDisplayType;
+    pub type NativePixmapType = EGLNativePixmapType;
+    pub type NativeWindowType = EGLNativeWindowType;
+
+    include!(concat!(env!("OUT_DIR"), "/egl_bindings.rs"));
+}
+
+/// Shared object names to try when loading EGL.
+const EGL_LIBRARIES: [&str; 2] = ["libEGL.so.1", "libEGL.so"];
+
+/// OpenGL versions to request from newest to oldest.
+const GL_VERSIONS: [(EGLint, EGLint); 3] = [(4, 6), (4, 5), (3, 3)];
+
+/// OpenGL context backed by EGL with an offscreen default framebuffer.
+pub struct HeadlessContext {
+    gl: Rc<Gl>,
+    egl: egl::Egl,
+    display: EGLDisplay,
+    context: EGLContext,
+    // Deleted manually before the context is destroyed
+    framebuffer: ManuallyDrop<Framebuffer>,
+    // The library must outlive every function pointer loaded from it so it's dropped last.
+    _library: Library,
+}
+
+impl HeadlessContext {
+    /// Create a surfaceless context and an offscreen framebuffer of `size` pixels.
+    ///
+    /// The context is made current on the calling thread.
+    pub fn new(size: Size) -> Result<Self, GlError> {
+        if size.width == 0 || size.height == 0 {
+            return Err(GlError::Context(format!(
+                "Headless framebuffer must not be empty: {}x{}",
+                size.width, size.height
+            )));
+        }
+
+        let library = EGL_LIBRARIES
+            .iter()
+            .find_map(|name| unsafe { Library::new(name).ok() })
+            .ok_or_else(|| GlError::Context("Failed to load libEGL".into()))?;
+
+        // Core functions are exported by libEGL while extensions have to be queried.
+        let egl = egl::Egl::load_with(|name| load_symbol(&library, name));
+        let get_proc = egl.GetProcAddress.is_loaded();
+        let egl = if get_proc {
+            egl::Egl::load_with(|name| {
+                let symbol = load_symbol(&library, name);
+                if symbol.is_null() {
+                    let name = CString::new(name).expect("EGL function names are valid C strings");
+                    unsafe { egl.GetProcAddress(name.as_ptr()) as _ }
+                } else {
+                    symbol
+                }
+            })
+        } else {
+            egl
+        };
+
+        let display = unsafe {
+            if egl.GetPlatformDisplay.is_loaded() {
+                egl.GetPlatformDisplay(
+                    egl::PLATFORM_SURFACELESS_MESA,
+                    egl::DEFAULT_DISPLAY as _,
+                    std::ptr::null(),
+                )
+            } else if egl.GetPlatformDisplayEXT.is_loaded() {
+                egl.GetPlatformDisplayEXT(
+                    egl::PLATFORM_SURFACELESS_MESA,
+                    egl::DEFAULT_DISPLAY as _,
+                    std::ptr::null(),
+                )
+            } else {
+                egl::NO_DISPLAY
+            }
+        };
+        if display == egl::NO_DISPLAY {
+            error!("EGL doesn't support the surfaceless platform.");
+            return Err(GlError::Context(
+                "Failed to retrieve a surfaceless EGL display".into(),
+            ));
+        }
+
+        let (mut major, mut minor) = (0, 0);
+        if unsafe { egl.Initialize(display, &mut major, &mut minor) } == egl::FALSE {
+            return Err(egl_error(&egl, "eglInitialize"));
+        }
+        info!("EGL v{major}.{minor}");
+
+        // Everything after Initialize must Terminate the display on failure.
+        let context = unsafe { Self::create_context(&egl, display) }.inspect_err(|_| {
+            unsafe { egl.Terminate(display) };
+        })?;
+
+        let gl = Gl::load_gl(|name| {
+            let name = CString::new(name).expect("OpenGL function names are valid C strings");
+            unsafe { egl.GetProcAddress(name.as_ptr()) as _ }
+        });
+
+        let framebuffer = match Self::create_framebuffer(&gl, size) {
+            Ok(framebuffer) => framebuffer,
+            Err(e) => {
+                unsafe { destroy(&egl, display, context) };
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            gl,
+            egl,
+            display,
+            context,
+            framebuffer: ManuallyDrop::new(framebuffer),
+            _library: library,
+        })
+    }
+
+    /// Choose a config and create a core profile context with the newest supported version.
+    unsafe fn create_context(egl: &egl::Egl, display: EGLDisplay) -> Result<EGLContext, GlError> {
+        if egl.BindAPI(egl::OPENGL_API) == egl::FALSE {
+            return Err(egl_error(egl, "eglBindAPI"));
+        }
+
+        // Surfaceless contexts never render to an EGL surface, but the default surface type is
+        // WINDOW_BIT which the surfaceless platform doesn't support.
+        let config_attributes = [
+            egl::RENDERABLE_TYPE as EGLint,
+            egl::OPENGL_BIT as _,
+            egl::SURFACE_TYPE as _,
+            egl::PBUFFER_BIT as _,
+            egl::NONE as _,
+        ];
+        let mut config: EGLConfig = std::ptr::null();
+        let mut num_configs = 0;
+        if egl.ChooseConfig(
+            display,
+            config_attributes.as_ptr(),
+            &mut config,
+            1,
+            &mut num_configs,
+        ) == egl::FALSE
+            || num_configs == 0
+        {
+            return Err(egl_error(egl, "eglChooseConfig"));
+        }
+
+        let context_flags = if cfg!(feature = "debug") {
+            egl::CONTEXT_OPENGL_DEBUG_BIT_KHR as EGLint
+        } else {
+            0
+        };
+
+        for (major, minor) in GL_VERSIONS {
+            let context_attributes = [
+                egl::CONTEXT_MAJOR_VERSION as EGLint,
+                major,
+                egl::CONTEXT_MINOR_VERSION as _,
+                minor,
+                egl::CONTEXT_OPENGL_PROFILE_MASK as _,
+                egl::CONTEXT_OPENGL_CORE_PROFILE_BIT as _,
+                egl::CONTEXT_FLAGS_KHR as _,
+                context_flags,
+                egl::NONE as _,
+            ];
+            let context = egl.CreateContext(
+                display,
+                config,
+                egl::NO_CONTEXT,
+                context_attributes.as_ptr(),
+            );
+
+            if context != egl::NO_CONTEXT {
+                info!("Created headless OpenGL v{major}.{minor} context");
+                if egl.MakeCurrent(display, egl::NO_SURFACE, egl::NO_SURFACE, context) == egl::FALSE
+                {
+                    egl.DestroyContext(display, context);
+                    return Err(egl_error(egl, "eglMakeCurrent"));
+                }
+                return Ok(context);
+            }
+        }
+
+        Err(egl_error(egl, "eglCreateContext"))
+    }
+
+    /// Allocate color and depth/stencil renderbuffers and bind them for drawing and reading.
+    fn create_framebuffer(gl: &Rc<Gl>, size: Size) -> Result<Framebuffer, GlError> {
+        let color =
+            Renderbuffer::new(gl.clone(), size, RenderbufferFormat::Rgba8, "HeadlessColor")?;
+        let depth_stencil = Renderbuffer::new(
+            gl.clone(),
+            size,
+            RenderbufferFormat::Depth24Stencil8,
+            "HeadlessDepthStencil",
+        )?;
+        let framebuffer = Framebuffer::new(
+            gl.clone(),
+            [
+                (
+                    FramebufferAttachment::Color0,
+                    Attachment::Renderbuffer(color),
+                ),
+                (
+                    FramebufferAttachment::DepthStencil,
+                    Attachment::Renderbuffer(depth_stencil),
+                ),
+            ],
+            "HeadlessFramebuffer",
+        )?;
+
+        framebuffer.bind(FramebufferTarget::Framebuffer);
+        gl.viewport(super::Rect {
+            size,
+            ..Default::default()
+        });
+        Ok(framebuffer)
+    }
+
+    /// Loaded OpenGL functions for this context.
+    pub fn gl(&self) -> Rc<Gl> {
+        self.gl.clone()
+    }
+
+    /// Size of the offscreen framebuffer.
+    pub fn size(&self) -> Size {
+        self.framebuffer.size()
+    }
+
+    /// Offscreen framebuffer that stands in for the default framebuffer.
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// Bind the offscreen framebuffer for both drawing and reading.
+    pub fn bind_framebuffer(&self) {
+        self.framebuffer.bind(FramebufferTarget::Framebuffer)
+    }
+
+    /// Small context for unit tests that need OpenGL objects.
+    ///
+    /// Declare it before the objects so that they're dropped while it's still current.
+    #[cfg(test)]
+    pub(crate) fn for_tests() -> Self {
+        Self::new(Size {
+            width: 16,
+            height: 16,
+        })
+        .expect("Headless context")
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        unsafe {
+            // Objects must be deleted while the context is still current.
+            ManuallyDrop::drop(&mut self.framebuffer);
+            destroy(&self.egl, self.display, self.context);
+        }
+    }
+}
+
+/// Release the context and the display connection.
+unsafe fn destroy(egl: &egl::Egl, display: EGLDisplay, context: EGLContext) {
+    egl.MakeCurrent(display, egl::NO_SURFACE, egl::NO_SURFACE, egl::NO_CONTEXT);
+    egl.DestroyContext(display, context);
+    egl.Terminate(display);
+}
+
+/// Look up an exported function in libEGL; returns null if it's missing.
+fn load_symbol(library: &Library, name: &str) -> *const c_void {
+    unsafe {
+        library
+            .get::<*const raw::c_void>(name.as_bytes())
+            .map(|symbol| *symbol)
+            .unwrap_or(std::ptr::null())
+    }
+}
+
+/// Wrap the last EGL error into a [GlError].
+fn egl_error(egl: &egl::Egl, function: &str) -> GlError {
+    let code = unsafe { egl.GetError() };
+    error!("{function} failed with EGL error {code:#x}");
+    GlError::Context(format!("{function} failed with EGL error {code:#x}"))
+}
@@ -4,7 +4,7 @@ use crate::{
 };
 use std::{borrow::Cow, fmt, rc::Rc};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ApiVersion {
     pub major: u32,
     pub minor: u32,
@@ -80,6 +80,35 @@ impl ContextInfo {
             glsl,
         }
     }
+
+    /// Check if the context supports an extension such as `GL_ARB_direct_state_access`.
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|extension| extension == name)
+    }
+
+    /// Direct state access is core since OpenGL 4.5.
+    pub fn supports_dsa(&self) -> bool {
+        self.version >= ApiVersion { major: 4, minor: 5 }
+            || self.has_extension("GL_ARB_direct_state_access")
+    }
+
+    /// Program interface queries, used to reflect on shader programs, are core since OpenGL 4.3.
+    pub fn supports_program_interface_query(&self) -> bool {
+        self.version >= ApiVersion { major: 4, minor: 3 }
+            || self.has_extension("GL_ARB_program_interface_query")
+    }
+
+    /// Anisotropic filtering is core since OpenGL 4.6.
+    pub fn supports_anisotropic_filtering(&self) -> bool {
+        self.version >= ApiVersion { major: 4, minor: 6 }
+            || self.has_extension("GL_ARB_texture_filter_anisotropic")
+            || self.has_extension("GL_EXT_texture_filter_anisotropic")
+    }
+
+    /// SPIR-V shader modules are core since OpenGL 4.6.
+    pub fn supports_spirv(&self) -> bool {
+        self.version >= ApiVersion { major: 4, minor: 6 } || self.has_extension("GL_ARB_gl_spirv")
+    }
 }
 
 // Iterator for extensions supported by this context
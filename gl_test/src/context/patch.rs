@@ -0,0 +1,61 @@
+use std::rc::Rc;
+
+use crate::{
+    context::{gl, Gl},
+    glerror::GlError,
+};
+
+/// Patch state for [DrawMode::Patches](crate::glenums::DrawMode::Patches) set with
+/// [glPatchParameter](https://docs.gl/gl4/glPatchParameter).
+///
+/// The default levels are only used by programs without a tessellation control shader, in which
+/// case they decide how finely the evaluation shader subdivides every patch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Patch {
+    /// Number of vertices that make up each patch
+    pub vertices: u32,
+    /// Subdivisions of each outer edge. Triangles use the first three.
+    pub outer_levels: [f32; 4],
+    /// Subdivisions of the inside. Triangles use the first one.
+    pub inner_levels: [f32; 2],
+}
+
+impl Default for Patch {
+    /// OpenGL's initial state of three vertex patches that aren't subdivided.
+    fn default() -> Self {
+        Self {
+            vertices: 3,
+            outer_levels: [1.0; 4],
+            inner_levels: [1.0; 2],
+        }
+    }
+}
+
+impl Patch {
+    /// Set this struct as the current patch state.
+    ///
+    /// Fails if the vertex count is zero or larger than the implementation supports.
+    pub fn set(&self, gl: &Rc<Gl>) -> Result<(), GlError> {
+        let max = Self::max_vertices(gl);
+        if self.vertices == 0 || self.vertices > max {
+            return Err(GlError::Limit(format!(
+                "Patches of {} vertices aren't supported, the maximum is {max}",
+                self.vertices
+            )));
+        }
+
+        unsafe {
+            gl.PatchParameteri(gl::PATCH_VERTICES, self.vertices as _);
+            gl.PatchParameterfv(gl::PATCH_DEFAULT_OUTER_LEVEL, self.outer_levels.as_ptr());
+            gl.PatchParameterfv(gl::PATCH_DEFAULT_INNER_LEVEL, self.inner_levels.as_ptr());
+        }
+        Ok(())
+    }
+
+    /// Maximum number of vertices per patch which is at least 32.
+    pub fn max_vertices(gl: &Rc<Gl>) -> u32 {
+        let mut max = 0;
+        unsafe { gl.GetIntegerv(gl::MAX_PATCH_VERTICES, &mut max) }
+        max.max(0) as u32
+    }
+}
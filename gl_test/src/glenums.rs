@@ -3,6 +3,7 @@
 //! This module contains various OpenGL enumerations, such as buffer targets, wrapped up into type
 //! safe structs. The new types implement bitwise operators as well as convenience functions.
 
+mod barrier;
 mod buffers;
 mod clearkind;
 mod contextflags;
@@ -10,17 +11,30 @@ mod contextprofile;
 mod debug;
 mod drawmode;
 mod enable;
+mod framebuffers;
 mod geterror;
 mod getstring;
+mod glsltype;
 mod objects;
+mod samplers;
+mod stages;
+mod textures;
 
-pub use buffers::{BufferTarget, BufferUsage};
+pub use barrier::MemoryBarrier;
+pub use buffers::{BufferStorageFlags, BufferTarget, BufferUsage, MapAccess};
 pub use clearkind::ClearKind;
 pub use contextflags::ContextFlags;
 pub use contextprofile::ContextProfile;
 pub use debug::{DebugSeverity, DebugSource, DebugType};
 pub use drawmode::DrawMode;
 pub use enable::Enable;
+pub use framebuffers::{
+    FramebufferAttachment, FramebufferStatus, FramebufferTarget, RenderbufferFormat,
+};
 pub use geterror::GetError;
 pub use getstring::GetString;
+pub use glsltype::GlslType;
 pub use objects::ObjectName;
+pub use samplers::{CompareFunc, CompareMode, MagFilter, MinFilter, SamplerParameter, WrapMode};
+pub use stages::ShaderStages;
+pub use textures::TextureFormat;
@@ -0,0 +1,48 @@
+//! Enumeration for [glMemoryBarrier](https://docs.gl/gl4/glMemoryBarrier)
+
+#![allow(non_upper_case_globals)]
+
+use crate::context::gl::{self, types::GLenum};
+use bitflags::bitflags;
+
+bitflags! {
+    /// Operations that must see the results of earlier incoherent shader writes, e.g. from
+    /// shader storage blocks or image stores.
+    ///
+    /// Each flag names how the data is read *after* the barrier, not how it was written.
+    #[repr(C)]
+    pub struct MemoryBarrier: GLenum {
+        /// Vertex attributes sourced from buffers
+        const VertexAttribArray = gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT;
+        /// Indices sourced from element array buffers
+        const ElementArray = gl::ELEMENT_ARRAY_BARRIER_BIT;
+        /// Uniform blocks
+        const Uniform = gl::UNIFORM_BARRIER_BIT;
+        /// Texture sampling
+        const TextureFetch = gl::TEXTURE_FETCH_BARRIER_BIT;
+        /// Image loads, stores and atomics
+        const ShaderImageAccess = gl::SHADER_IMAGE_ACCESS_BARRIER_BIT;
+        /// Draw and dispatch indirect commands
+        const Command = gl::COMMAND_BARRIER_BIT;
+        /// Pixel pack and unpack buffers
+        const PixelBuffer = gl::PIXEL_BUFFER_BARRIER_BIT;
+        /// Texture uploads and downloads
+        const TextureUpdate = gl::TEXTURE_UPDATE_BARRIER_BIT;
+        /// Buffer reads, writes, copies and mappings
+        const BufferUpdate = gl::BUFFER_UPDATE_BARRIER_BIT;
+        /// Persistently mapped buffers read by the client
+        const ClientMappedBuffer = gl::CLIENT_MAPPED_BUFFER_BARRIER_BIT;
+        /// Framebuffer reads and writes
+        const Framebuffer = gl::FRAMEBUFFER_BARRIER_BIT;
+        /// Transform feedback buffers
+        const TransformFeedback = gl::TRANSFORM_FEEDBACK_BARRIER_BIT;
+        /// Atomic counter buffers
+        const AtomicCounter = gl::ATOMIC_COUNTER_BARRIER_BIT;
+        /// Shader storage blocks
+        const ShaderStorage = gl::SHADER_STORAGE_BARRIER_BIT;
+        /// Query result buffers
+        const QueryBuffer = gl::QUERY_BUFFER_BARRIER_BIT;
+        /// Every kind of access
+        const All = gl::ALL_BARRIER_BITS;
+    }
+}
@@ -68,3 +68,47 @@ bitflags! {
         const DynamicCopy = gl::DYNAMIC_COPY;
     }
 }
+
+bitflags! {
+    /// Flags for immutable storage allocated with
+    /// [glBufferStorage](https://docs.gl/gl4/glBufferStorage).
+    ///
+    /// Unlike [BufferUsage] these aren't hints. Operations that weren't requested are errors, e.g.
+    /// mapping a buffer for writing without [BufferStorageFlags::MapWrite].
+    #[repr(C)]
+    pub struct BufferStorageFlags: GLenum {
+        /// Contents may be updated with glBufferSubData
+        const DynamicStorage = gl::DYNAMIC_STORAGE_BIT;
+        /// Buffer may be mapped for reading
+        const MapRead = gl::MAP_READ_BIT;
+        /// Buffer may be mapped for writing
+        const MapWrite = gl::MAP_WRITE_BIT;
+        /// Buffer may stay mapped while it's used by the GPU
+        const MapPersistent = gl::MAP_PERSISTENT_BIT;
+        /// Writes to a persistent mapping are visible to the GPU without flushing
+        const MapCoherent = gl::MAP_COHERENT_BIT;
+        /// Prefer storage in client memory
+        const ClientStorage = gl::CLIENT_STORAGE_BIT;
+    }
+}
+
+bitflags! {
+    /// Access flags for [glMapBufferRange](https://docs.gl/gl4/glMapBufferRange).
+    #[repr(C)]
+    pub struct MapAccess: GLenum {
+        const Read = gl::MAP_READ_BIT;
+        const Write = gl::MAP_WRITE_BIT;
+        /// Keep the mapping while the buffer is used by the GPU
+        const Persistent = gl::MAP_PERSISTENT_BIT;
+        /// Writes are visible to the GPU without flushing
+        const Coherent = gl::MAP_COHERENT_BIT;
+        /// Previous contents of the range may be discarded
+        const InvalidateRange = gl::MAP_INVALIDATE_RANGE_BIT;
+        /// Previous contents of the whole buffer may be discarded
+        const InvalidateBuffer = gl::MAP_INVALIDATE_BUFFER_BIT;
+        /// Modified ranges are flushed manually
+        const FlushExplicit = gl::MAP_FLUSH_EXPLICIT_BIT;
+        /// Don't wait for pending operations on the buffer
+        const Unsynchronized = gl::MAP_UNSYNCHRONIZED_BIT;
+    }
+}
@@ -2,7 +2,7 @@
 
 #![allow(non_upper_case_globals)]
 
-use crate::context::gl::{self, types::GLenum};
+use crate::context::gl::types::GLenum;
 use bitflags::bitflags;
 
 bitflags! {
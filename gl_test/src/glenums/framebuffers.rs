@@ -0,0 +1,98 @@
+//! Enumerations for [glBindFramebuffer](https://docs.gl/gl4/glBindFramebuffer) and related
+//! functions.
+
+#![allow(non_upper_case_globals)]
+
+use crate::context::gl::{self, types::GLenum};
+use bitflags::bitflags;
+use std::fmt::{self, Display, Formatter};
+
+bitflags! {
+    /// Framebuffer binding points.
+    #[repr(C)]
+    pub struct FramebufferTarget: GLenum {
+        /// Both the draw and read targets
+        const Framebuffer = gl::FRAMEBUFFER;
+        /// Destination of rendering, clearing, and blitting
+        const Draw = gl::DRAW_FRAMEBUFFER;
+        /// Source of pixel reads and blitting
+        const Read = gl::READ_FRAMEBUFFER;
+    }
+
+    /// Attachment points of a framebuffer.
+    #[repr(C)]
+    pub struct FramebufferAttachment: GLenum {
+        const Color0 = gl::COLOR_ATTACHMENT0;
+        const Color1 = gl::COLOR_ATTACHMENT1;
+        const Color2 = gl::COLOR_ATTACHMENT2;
+        const Color3 = gl::COLOR_ATTACHMENT3;
+        const Depth = gl::DEPTH_ATTACHMENT;
+        const Stencil = gl::STENCIL_ATTACHMENT;
+        const DepthStencil = gl::DEPTH_STENCIL_ATTACHMENT;
+    }
+
+    /// Result of [glCheckFramebufferStatus](https://docs.gl/gl4/glCheckFramebufferStatus).
+    #[repr(C)]
+    pub struct FramebufferStatus: GLenum {
+        const Complete = gl::FRAMEBUFFER_COMPLETE;
+        /// The default framebuffer doesn't exist
+        const Undefined = gl::FRAMEBUFFER_UNDEFINED;
+        /// An attachment is missing storage or has a size of zero
+        const IncompleteAttachment = gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT;
+        /// Nothing is attached
+        const MissingAttachment = gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT;
+        /// A draw buffer refers to an empty attachment point
+        const IncompleteDrawBuffer = gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER;
+        /// The read buffer refers to an empty attachment point
+        const IncompleteReadBuffer = gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER;
+        /// The combination of internal formats isn't supported by the implementation
+        const Unsupported = gl::FRAMEBUFFER_UNSUPPORTED;
+        /// Attachments have different sample counts
+        const IncompleteMultisample = gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE;
+        /// Layered and non-layered attachments are mixed
+        const IncompleteLayerTargets = gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS;
+    }
+
+    /// Sized internal formats for renderbuffer storage.
+    #[repr(C)]
+    pub struct RenderbufferFormat: GLenum {
+        const Rgba8 = gl::RGBA8;
+        const Srgb8Alpha8 = gl::SRGB8_ALPHA8;
+        const Rgba16f = gl::RGBA16F;
+        const Rgba32f = gl::RGBA32F;
+        const Depth24 = gl::DEPTH_COMPONENT24;
+        const Depth32f = gl::DEPTH_COMPONENT32F;
+        const Depth24Stencil8 = gl::DEPTH24_STENCIL8;
+        const Depth32fStencil8 = gl::DEPTH32F_STENCIL8;
+        const Stencil8 = gl::STENCIL_INDEX8;
+    }
+}
+
+impl FramebufferAttachment {
+    /// Whether this is a color attachment point.
+    pub fn is_color(self) -> bool {
+        !matches!(
+            self,
+            FramebufferAttachment::Depth
+                | FramebufferAttachment::Stencil
+                | FramebufferAttachment::DepthStencil
+        )
+    }
+}
+
+impl Display for FramebufferStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            FramebufferStatus::Complete => write!(f, "Complete"),
+            FramebufferStatus::Undefined => write!(f, "Undefined default framebuffer"),
+            FramebufferStatus::IncompleteAttachment => write!(f, "Incomplete attachment"),
+            FramebufferStatus::MissingAttachment => write!(f, "Missing attachment"),
+            FramebufferStatus::IncompleteDrawBuffer => write!(f, "Incomplete draw buffer"),
+            FramebufferStatus::IncompleteReadBuffer => write!(f, "Incomplete read buffer"),
+            FramebufferStatus::Unsupported => write!(f, "Unsupported format combination"),
+            FramebufferStatus::IncompleteMultisample => write!(f, "Incomplete multisample"),
+            FramebufferStatus::IncompleteLayerTargets => write!(f, "Incomplete layer targets"),
+            _ => write!(f, "Unknown status {:#x}", self.bits()),
+        }
+    }
+}
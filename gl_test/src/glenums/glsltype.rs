@@ -0,0 +1,202 @@
+//! GLSL types reported by [glGetProgramResource](https://docs.gl/gl4/glGetProgramResource).
+
+use crate::context::gl::{self, types::GLenum};
+use std::fmt::{self, Display, Formatter};
+
+/// Type of an active shader variable.
+///
+/// Types are values rather than flags so unlike most enumerations in this module this isn't a
+/// bitflags struct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum GlslType {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Double,
+    DVec2,
+    DVec3,
+    DVec4,
+    Int,
+    IVec2,
+    IVec3,
+    IVec4,
+    UInt,
+    UVec2,
+    UVec3,
+    UVec4,
+    Bool,
+    BVec2,
+    BVec3,
+    BVec4,
+    Mat2,
+    Mat3,
+    Mat4,
+    Mat2x3,
+    Mat2x4,
+    Mat3x2,
+    Mat3x4,
+    Mat4x2,
+    Mat4x3,
+    Sampler1D,
+    Sampler2D,
+    Sampler3D,
+    SamplerCube,
+    Sampler2DShadow,
+    Sampler2DArray,
+    Sampler2DMultisample,
+    SamplerBuffer,
+    ISampler2D,
+    USampler2D,
+    Image2D,
+    Image3D,
+    IImage2D,
+    UImage2D,
+    AtomicUInt,
+    /// Type that isn't listed above
+    Unknown(GLenum),
+}
+
+impl From<GLenum> for GlslType {
+    fn from(kind: GLenum) -> Self {
+        match kind {
+            gl::FLOAT => GlslType::Float,
+            gl::FLOAT_VEC2 => GlslType::Vec2,
+            gl::FLOAT_VEC3 => GlslType::Vec3,
+            gl::FLOAT_VEC4 => GlslType::Vec4,
+            gl::DOUBLE => GlslType::Double,
+            gl::DOUBLE_VEC2 => GlslType::DVec2,
+            gl::DOUBLE_VEC3 => GlslType::DVec3,
+            gl::DOUBLE_VEC4 => GlslType::DVec4,
+            gl::INT => GlslType::Int,
+            gl::INT_VEC2 => GlslType::IVec2,
+            gl::INT_VEC3 => GlslType::IVec3,
+            gl::INT_VEC4 => GlslType::IVec4,
+            gl::UNSIGNED_INT => GlslType::UInt,
+            gl::UNSIGNED_INT_VEC2 => GlslType::UVec2,
+            gl::UNSIGNED_INT_VEC3 => GlslType::UVec3,
+            gl::UNSIGNED_INT_VEC4 => GlslType::UVec4,
+            gl::BOOL => GlslType::Bool,
+            gl::BOOL_VEC2 => GlslType::BVec2,
+            gl::BOOL_VEC3 => GlslType::BVec3,
+            gl::BOOL_VEC4 => GlslType::BVec4,
+            gl::FLOAT_MAT2 => GlslType::Mat2,
+            gl::FLOAT_MAT3 => GlslType::Mat3,
+            gl::FLOAT_MAT4 => GlslType::Mat4,
+            gl::FLOAT_MAT2x3 => GlslType::Mat2x3,
+            gl::FLOAT_MAT2x4 => GlslType::Mat2x4,
+            gl::FLOAT_MAT3x2 => GlslType::Mat3x2,
+            gl::FLOAT_MAT3x4 => GlslType::Mat3x4,
+            gl::FLOAT_MAT4x2 => GlslType::Mat4x2,
+            gl::FLOAT_MAT4x3 => GlslType::Mat4x3,
+            gl::SAMPLER_1D => GlslType::Sampler1D,
+            gl::SAMPLER_2D => GlslType::Sampler2D,
+            gl::SAMPLER_3D => GlslType::Sampler3D,
+            gl::SAMPLER_CUBE => GlslType::SamplerCube,
+            gl::SAMPLER_2D_SHADOW => GlslType::Sampler2DShadow,
+            gl::SAMPLER_2D_ARRAY => GlslType::Sampler2DArray,
+            gl::SAMPLER_2D_MULTISAMPLE => GlslType::Sampler2DMultisample,
+            gl::SAMPLER_BUFFER => GlslType::SamplerBuffer,
+            gl::INT_SAMPLER_2D => GlslType::ISampler2D,
+            gl::UNSIGNED_INT_SAMPLER_2D => GlslType::USampler2D,
+            gl::IMAGE_2D => GlslType::Image2D,
+            gl::IMAGE_3D => GlslType::Image3D,
+            gl::INT_IMAGE_2D => GlslType::IImage2D,
+            gl::UNSIGNED_INT_IMAGE_2D => GlslType::UImage2D,
+            gl::UNSIGNED_INT_ATOMIC_COUNTER => GlslType::AtomicUInt,
+            _ => GlslType::Unknown(kind),
+        }
+    }
+}
+
+impl GlslType {
+    /// Number of components of a scalar or vector type.
+    ///
+    /// Matrices, samplers and other types that don't fit into a single vertex attribute return
+    /// None.
+    pub fn components(self) -> Option<usize> {
+        use GlslType::*;
+        match self {
+            Float | Double | Int | UInt | Bool => Some(1),
+            Vec2 | DVec2 | IVec2 | UVec2 | BVec2 => Some(2),
+            Vec3 | DVec3 | IVec3 | UVec3 | BVec3 => Some(3),
+            Vec4 | DVec4 | IVec4 | UVec4 | BVec4 => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Whether the type is made of single precision floats, e.g. `vec3` or `mat4`.
+    pub fn is_float(self) -> bool {
+        use GlslType::*;
+        matches!(
+            self,
+            Float
+                | Vec2
+                | Vec3
+                | Vec4
+                | Mat2
+                | Mat3
+                | Mat4
+                | Mat2x3
+                | Mat2x4
+                | Mat3x2
+                | Mat3x4
+                | Mat4x2
+                | Mat4x3
+        )
+    }
+}
+
+impl Display for GlslType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            GlslType::Float => "float",
+            GlslType::Vec2 => "vec2",
+            GlslType::Vec3 => "vec3",
+            GlslType::Vec4 => "vec4",
+            GlslType::Double => "double",
+            GlslType::DVec2 => "dvec2",
+            GlslType::DVec3 => "dvec3",
+            GlslType::DVec4 => "dvec4",
+            GlslType::Int => "int",
+            GlslType::IVec2 => "ivec2",
+            GlslType::IVec3 => "ivec3",
+            GlslType::IVec4 => "ivec4",
+            GlslType::UInt => "uint",
+            GlslType::UVec2 => "uvec2",
+            GlslType::UVec3 => "uvec3",
+            GlslType::UVec4 => "uvec4",
+            GlslType::Bool => "bool",
+            GlslType::BVec2 => "bvec2",
+            GlslType::BVec3 => "bvec3",
+            GlslType::BVec4 => "bvec4",
+            GlslType::Mat2 => "mat2",
+            GlslType::Mat3 => "mat3",
+            GlslType::Mat4 => "mat4",
+            GlslType::Mat2x3 => "mat2x3",
+            GlslType::Mat2x4 => "mat2x4",
+            GlslType::Mat3x2 => "mat3x2",
+            GlslType::Mat3x4 => "mat3x4",
+            GlslType::Mat4x2 => "mat4x2",
+            GlslType::Mat4x3 => "mat4x3",
+            GlslType::Sampler1D => "sampler1D",
+            GlslType::Sampler2D => "sampler2D",
+            GlslType::Sampler3D => "sampler3D",
+            GlslType::SamplerCube => "samplerCube",
+            GlslType::Sampler2DShadow => "sampler2DShadow",
+            GlslType::Sampler2DArray => "sampler2DArray",
+            GlslType::Sampler2DMultisample => "sampler2DMS",
+            GlslType::SamplerBuffer => "samplerBuffer",
+            GlslType::ISampler2D => "isampler2D",
+            GlslType::USampler2D => "usampler2D",
+            GlslType::Image2D => "image2D",
+            GlslType::Image3D => "image3D",
+            GlslType::IImage2D => "iimage2D",
+            GlslType::UImage2D => "uimage2D",
+            GlslType::AtomicUInt => "atomic_uint",
+            GlslType::Unknown(kind) => return write!(f, "Unknown type {kind:#x}"),
+        };
+        write!(f, "{name}")
+    }
+}
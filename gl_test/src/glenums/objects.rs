@@ -4,6 +4,7 @@
 
 use crate::context::gl::{self, types::GLenum};
 use bitflags::bitflags;
+use std::fmt::{self, Display, Formatter};
 
 bitflags! {
     #[repr(C)]
@@ -21,3 +22,23 @@ bitflags! {
         const Framebuffer = gl::FRAMEBUFFER;
     }
 }
+
+impl Display for ObjectName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            ObjectName::Buffer => "buffer",
+            ObjectName::Shader => "shader",
+            ObjectName::Program => "program",
+            ObjectName::VertexArray => "vertex array",
+            ObjectName::Query => "query",
+            ObjectName::ProgramPipeline => "program pipeline",
+            ObjectName::TransformFeedback => "transform feedback",
+            ObjectName::Sampler => "sampler",
+            ObjectName::Texture => "texture",
+            ObjectName::Renderbuffer => "renderbuffer",
+            ObjectName::Framebuffer => "framebuffer",
+            _ => return write!(f, "object type {:#x}", self.bits()),
+        };
+        write!(f, "{name}")
+    }
+}
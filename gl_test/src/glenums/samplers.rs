@@ -0,0 +1,109 @@
+//! Enumerations for [glSamplerParameter](https://docs.gl/gl4/glSamplerParameter).
+
+#![allow(non_upper_case_globals)]
+
+use crate::context::gl::{self, types::GLenum};
+use bitflags::bitflags;
+
+bitflags! {
+    /// Sampler state that may be set with glSamplerParameter.
+    #[repr(C)]
+    pub struct SamplerParameter: GLenum {
+        const MinFilter = gl::TEXTURE_MIN_FILTER;
+        const MagFilter = gl::TEXTURE_MAG_FILTER;
+        const WrapS = gl::TEXTURE_WRAP_S;
+        const WrapT = gl::TEXTURE_WRAP_T;
+        const WrapR = gl::TEXTURE_WRAP_R;
+        /// Maximum anisotropic filtering samples as a float. Core in OpenGL 4.6.
+        const MaxAnisotropy = gl::TEXTURE_MAX_ANISOTROPY;
+        /// Bias added to the computed mipmap level as a float
+        const LodBias = gl::TEXTURE_LOD_BIAS;
+        const CompareMode = gl::TEXTURE_COMPARE_MODE;
+        const CompareFunc = gl::TEXTURE_COMPARE_FUNC;
+    }
+
+    /// Filter used when a texture is minified.
+    #[repr(C)]
+    pub struct MinFilter: GLenum {
+        const Nearest = gl::NEAREST;
+        const Linear = gl::LINEAR;
+        /// Nearest texel of the nearest mipmap
+        const NearestMipmapNearest = gl::NEAREST_MIPMAP_NEAREST;
+        /// Weighted average of the nearest mipmap
+        const LinearMipmapNearest = gl::LINEAR_MIPMAP_NEAREST;
+        /// Nearest texel of the two nearest mipmaps blended together
+        const NearestMipmapLinear = gl::NEAREST_MIPMAP_LINEAR;
+        /// Trilinear filtering
+        const LinearMipmapLinear = gl::LINEAR_MIPMAP_LINEAR;
+    }
+
+    /// Filter used when a texture is magnified.
+    #[repr(C)]
+    pub struct MagFilter: GLenum {
+        const Nearest = gl::NEAREST;
+        const Linear = gl::LINEAR;
+    }
+
+    /// Handling of texture coordinates outside of [0, 1].
+    #[repr(C)]
+    pub struct WrapMode: GLenum {
+        const Repeat = gl::REPEAT;
+        const MirroredRepeat = gl::MIRRORED_REPEAT;
+        const ClampToEdge = gl::CLAMP_TO_EDGE;
+        const ClampToBorder = gl::CLAMP_TO_BORDER;
+        const MirrorClampToEdge = gl::MIRROR_CLAMP_TO_EDGE;
+    }
+
+    /// Whether depth textures return depth values or comparison results.
+    #[repr(C)]
+    pub struct CompareMode: GLenum {
+        const None = gl::NONE;
+        /// Compare the texture coordinate's reference value to the depth texture
+        const RefToTexture = gl::COMPARE_REF_TO_TEXTURE;
+    }
+
+    /// Comparison used if the compare mode is [CompareMode::RefToTexture].
+    #[repr(C)]
+    pub struct CompareFunc: GLenum {
+        const Never = gl::NEVER;
+        const Less = gl::LESS;
+        const Equal = gl::EQUAL;
+        const LessEqual = gl::LEQUAL;
+        const Greater = gl::GREATER;
+        const NotEqual = gl::NOTEQUAL;
+        const GreaterEqual = gl::GEQUAL;
+        const Always = gl::ALWAYS;
+    }
+}
+
+// Defaults match OpenGL's initial sampler state.
+
+impl Default for MinFilter {
+    fn default() -> Self {
+        MinFilter::NearestMipmapLinear
+    }
+}
+
+impl Default for MagFilter {
+    fn default() -> Self {
+        MagFilter::Linear
+    }
+}
+
+impl Default for WrapMode {
+    fn default() -> Self {
+        WrapMode::Repeat
+    }
+}
+
+impl Default for CompareMode {
+    fn default() -> Self {
+        CompareMode::None
+    }
+}
+
+impl Default for CompareFunc {
+    fn default() -> Self {
+        CompareFunc::LessEqual
+    }
+}
@@ -0,0 +1,36 @@
+//! Enumeration for [glUseProgramStages](https://docs.gl/gl4/glUseProgramStages)
+
+#![allow(non_upper_case_globals)]
+
+use crate::{
+    context::gl::{self, types::GLbitfield},
+    shaders::ShaderKind,
+};
+use bitflags::bitflags;
+
+bitflags! {
+    /// Stages of a [ProgramPipeline](crate::shaders::ProgramPipeline) taken from a separable
+    /// program.
+    #[repr(C)]
+    pub struct ShaderStages: GLbitfield {
+        const Vertex = gl::VERTEX_SHADER_BIT;
+        const TessControl = gl::TESS_CONTROL_SHADER_BIT;
+        const TessEvaluation = gl::TESS_EVALUATION_SHADER_BIT;
+        const Geometry = gl::GEOMETRY_SHADER_BIT;
+        const Fragment = gl::FRAGMENT_SHADER_BIT;
+        const Compute = gl::COMPUTE_SHADER_BIT;
+    }
+}
+
+impl From<ShaderKind> for ShaderStages {
+    fn from(kind: ShaderKind) -> Self {
+        match kind {
+            ShaderKind::Vertex => ShaderStages::Vertex,
+            ShaderKind::TessControl => ShaderStages::TessControl,
+            ShaderKind::TessEvaluation => ShaderStages::TessEvaluation,
+            ShaderKind::Geometry => ShaderStages::Geometry,
+            ShaderKind::Fragment => ShaderStages::Fragment,
+            ShaderKind::Compute => ShaderStages::Compute,
+        }
+    }
+}
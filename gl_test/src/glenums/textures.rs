@@ -0,0 +1,62 @@
+//! Internal formats for [glTexStorage2D](https://docs.gl/gl4/glTexStorage2D).
+
+#![allow(non_upper_case_globals)]
+
+use crate::context::gl::{self, types::GLenum};
+use bitflags::bitflags;
+
+bitflags! {
+    /// Sized internal formats that textures are stored as on the GPU.
+    #[repr(C)]
+    pub struct TextureFormat: GLenum {
+        /// Single 8 bit normalized channel
+        const R8 = gl::R8;
+        /// Two 8 bit normalized channels
+        const Rg8 = gl::RG8;
+        /// Four 8 bit normalized channels
+        const Rgba8 = gl::RGBA8;
+        /// Four 8 bit normalized channels with sRGB encoded color
+        const Srgb8Alpha8 = gl::SRGB8_ALPHA8;
+        /// Single 16 bit float channel
+        const R16f = gl::R16F;
+        /// Four 32 bit float channels
+        const Rgba32f = gl::RGBA32F;
+        /// 24 bit depth and 8 bit stencil for framebuffer attachments
+        const Depth24Stencil8 = gl::DEPTH24_STENCIL8;
+        /// 32 bit float depth for framebuffer attachments
+        const Depth32f = gl::DEPTH_COMPONENT32F;
+    }
+}
+
+impl TextureFormat {
+    /// Number of channels stored per pixel.
+    pub fn channels(self) -> usize {
+        match self {
+            TextureFormat::R8 | TextureFormat::R16f | TextureFormat::Depth32f => 1,
+            TextureFormat::Rg8 | TextureFormat::Depth24Stencil8 => 2,
+            TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8 | TextureFormat::Rgba32f => 4,
+            _ => unreachable!("Undefined flag for TextureFormat"),
+        }
+    }
+
+    /// Pixel format of client data uploaded into a texture of this format.
+    pub fn pixel_format(self) -> GLenum {
+        match self {
+            TextureFormat::Depth24Stencil8 => gl::DEPTH_STENCIL,
+            TextureFormat::Depth32f => gl::DEPTH_COMPONENT,
+            _ => match self.channels() {
+                1 => gl::RED,
+                2 => gl::RG,
+                _ => gl::RGBA,
+            },
+        }
+    }
+
+    /// Whether the format stores depth and/or stencil rather than color.
+    pub fn is_depth(self) -> bool {
+        matches!(
+            self,
+            TextureFormat::Depth24Stencil8 | TextureFormat::Depth32f
+        )
+    }
+}
@@ -1,11 +1,74 @@
+use crate::{
+    glenums::FramebufferStatus,
+    memory::LayoutMismatch,
+    shaders::{ShaderDiagnostic, ShaderKind},
+};
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum GlError {
+    #[error("Context creation failed with: {0}")]
+    Context(String),
     #[error("Buffer error: {0}")]
     Buffer(String),
+    #[error("Framebuffer error: {0}")]
+    Framebuffer(String),
+    #[error("Framebuffer '{label}' is incomplete: {status}")]
+    IncompleteFramebuffer {
+        label: String,
+        status: FramebufferStatus,
+    },
+    #[error("Image error: {0}")]
+    Image(String),
+    #[error("Resource error: {0}")]
+    Resource(String),
     #[error("Shader compilation failed with: {0}")]
     Shader(String),
+    #[error("{kind} shader from {} failed to compile:\n{}", display_origin(.origin), render(.diagnostics))]
+    ShaderCompile {
+        kind: ShaderKind,
+        /// File the shader was loaded from or None for inline sources
+        origin: Option<PathBuf>,
+        diagnostics: Vec<ShaderDiagnostic>,
+    },
+    #[error("Implementation limit exceeded: {0}")]
+    Limit(String),
     #[error("Linking shader program failed with: {0}")]
     ShaderProgram(String),
+    #[error("Sync error: {0}")]
+    Sync(String),
+    #[error("Texture error: {0}")]
+    Texture(String),
+    #[error("Vertex array '{label}' doesn't match program '{program}': {}", join(.mismatches))]
+    VertexLayout {
+        label: String,
+        program: String,
+        mismatches: Vec<LayoutMismatch>,
+    },
+}
+
+/// Path of a shader or a placeholder for inline sources.
+fn display_origin(path: &Option<PathBuf>) -> String {
+    path.as_deref()
+        .map(Path::to_string_lossy)
+        .map_or_else(|| "inline source".into(), |path| path.into_owned())
+}
+
+/// Render diagnostics separated by blank lines.
+fn render(diagnostics: &[ShaderDiagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// List mismatches on one line.
+fn join(mismatches: &[LayoutMismatch]) -> String {
+    mismatches
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
 }
@@ -1,7 +1,16 @@
-use std::hash::Hash;
+use std::{hash::Hash, rc::Rc};
 
 pub trait Label {
     type Output: AsRef<str> + Hash;
 
     fn label(&self) -> Self::Output;
 }
+
+// Shared resources are labelled like the resource itself.
+impl<T: Label + ?Sized> Label for Rc<T> {
+    type Output = T::Output;
+
+    fn label(&self) -> Self::Output {
+        self.as_ref().label()
+    }
+}
@@ -3,18 +3,24 @@
 // https://nercury.github.io/rust/opengl/tutorial/2018/02/10/opengl-in-rust-from-scratch-03-compiling-shaders.html
 // https://www.poor.dev/blog/terminal-anatomy/
 
+// Lets derive macros refer to this crate as ::gl_test from inside it.
+extern crate self as gl_test;
+
 pub(crate) mod context;
 pub(crate) mod glenums;
 pub(crate) mod glerror;
 pub(crate) mod label;
-pub(crate) mod memory;
+pub mod memory;
 pub(crate) mod resources;
 pub(crate) mod shaders;
 
-use glenums::{ClearKind, DrawMode};
+use glenums::{
+    ClearKind, DrawMode, FramebufferAttachment, FramebufferTarget, MagFilter, RenderbufferFormat,
+    TextureFormat,
+};
 use glutin::{
     dpi::LogicalSize,
-    event::{Event, WindowEvent},
+    event::{ElementState, Event, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
     ContextBuilder, PossiblyCurrent, WindowedContext,
@@ -22,20 +28,194 @@ use glutin::{
 
 use context::{Gl, Size};
 use glerror::GlError;
-use log::info;
-use resources::programs::{
-    rectangle::Rectangle,
-    triangle::{TriangleBuf, TriangleShader},
+use image::RgbaImage;
+use log::{error, info};
+use memory::{
+    stateful::{
+        Attachment, Framebuffer, Renderbuffer, Sampler, SamplerCache, Texture2D, VertexArray,
+    },
+    BufferBackend, UniformBuffer,
+};
+use nalgebra_glm as glm;
+use resources::{
+    programs::{
+        rectangle::{
+            Camera, Rectangle, TessellatedFrame, TessellatedShader, TexturedRectangle,
+            TexturedShader, CAMERA_BINDING,
+        },
+        triangle::{TriangleBuf, TriangleShader},
+    },
+    Handle, ResourceRegistry,
+};
+#[cfg(target_os = "linux")]
+use shaders::ShaderReloader;
+use shaders::{ProgramCache, ShaderProgram};
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
-use std::rc::Rc;
 
-use crate::context::{info::ContextInfo, Clear, Color};
+#[cfg(unix)]
+use crate::context::headless::HeadlessContext;
+use crate::context::{info::ContextInfo, Clear, Color, Patch};
+
+/// How often the windowed test checks for edited shaders.
+#[cfg(target_os = "linux")]
+const SHADER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Enable debug output, log context information and set the clear color.
+fn init_gl(gl: &Rc<Gl>) -> ContextInfo {
+    // Enable debug printing
+    gl.enable_debug_output();
+    gl.debug_message_control(
+        glenums::DebugSource::DontCare,
+        glenums::DebugType::DontCare,
+        glenums::DebugSeverity::DontCare,
+        true,
+    );
+
+    // Print information on the OpenGL context.
+    let context_info = gl.info().clone();
+    info!("{}", context_info.version);
+    info!("Vendor: {}", context_info.vendor);
+    info!("GPU: {}", context_info.renderer);
+    info!("GLSL version: {}", context_info.glsl);
+
+    // Set a base clear color
+    let clear = Clear {
+        color: Some(Color {
+            red: 220. / 255.,
+            green: 205. / 255.,
+            blue: 1.0,
+            alpha: 1.0,
+        }),
+        ..Default::default()
+    };
+    clear.set(gl);
+
+    context_info
+}
+
+/// Shaders and buffers drawn by [GlTest] and [GlHeadless].
+struct Programs {
+    resources: ResourceRegistry,
+    triangle_prog: Handle<ShaderProgram>,
+    tessellated_prog: Handle<ShaderProgram>,
+    textured_prog: Handle<ShaderProgram>,
+    // Patch size and levels that the tessellated program expects
+    tessellated_patch: Patch,
+    // Animation time for the tessellated program, written while drawing
+    tessellated_frame: RefCell<TessellatedFrame>,
+    trianglebuf: Handle<VertexArray>,
+    rectanglebuf: Handle<VertexArray>,
+    textured_rect: Handle<VertexArray>,
+    checker_texture: Handle<Texture2D>,
+    checker_sampler: Rc<Sampler>,
+    textured_camera: UniformBuffer<Camera>,
+}
+
+impl Programs {
+    /// Load shaders from files and construct buffers
+    ///
+    /// Buffers use direct state access if the context supports it. Programs are loaded from
+    /// `cache` when possible.
+    fn new(gl: &Rc<Gl>, context_info: &ContextInfo, cache: &ProgramCache) -> Result<Self, GlError> {
+        let backend = BufferBackend::from_info(context_info);
+        info!("Buffer backend: {backend:?}");
+        let mut samplers = SamplerCache::new(gl.clone(), context_info);
+        let mut resources = ResourceRegistry::new();
+
+        let TessellatedShader { shader, patch } = TessellatedShader::new(gl.clone(), cache)?;
+        let tessellated_prog = resources.programs.insert(shader)?;
+        let TexturedRectangle {
+            vao,
+            texture,
+            sampler,
+            camera,
+        } = TexturedRectangle::new(gl.clone(), backend, &mut samplers)?;
+
+        let programs = Self {
+            triangle_prog: resources
+                .programs
+                .insert(TriangleShader::new(gl.clone(), cache)?.shader)?,
+            tessellated_prog,
+            textured_prog: resources
+                .programs
+                .insert(TexturedShader::new(gl.clone(), cache)?.shader)?,
+            tessellated_patch: patch,
+            tessellated_frame: RefCell::new(TessellatedFrame::new(gl.clone(), backend)?),
+            trianglebuf: resources
+                .insert_vertex_array(TriangleBuf::new(gl.clone(), backend)?.vao)?,
+            rectanglebuf: resources
+                .insert_vertex_array(Rectangle::new(gl.clone(), backend)?.vao)?,
+            textured_rect: resources.insert_vertex_array(vao)?,
+            checker_texture: resources.textures.insert(texture)?,
+            checker_sampler: sampler,
+            textured_camera: camera,
+            resources,
+        };
+
+        // Catch mismatched vertex layouts before anything is drawn.
+        if context_info.supports_program_interface_query() {
+            programs.validate()?;
+        } else {
+            info!("Skipping vertex layout validation without program interface queries");
+        }
+        Ok(programs)
+    }
+
+    /// Check every vertex array against the program that draws it.
+    fn validate(&self) -> Result<(), GlError> {
+        let ResourceRegistry {
+            programs,
+            vertex_arrays,
+            ..
+        } = &self.resources;
+        vertex_arrays[self.trianglebuf].validate(&programs[self.triangle_prog])?;
+        vertex_arrays[self.rectanglebuf].validate(&programs[self.triangle_prog])?;
+        vertex_arrays[self.rectanglebuf].validate(&programs[self.tessellated_prog])?;
+        vertex_arrays[self.textured_rect].validate(&programs[self.textured_prog])
+    }
+
+    /// Watch the shader files of every program. Returns None if files can't be watched.
+    #[cfg(target_os = "linux")]
+    fn watch(&self, gl: &Rc<Gl>) -> Option<ShaderReloader> {
+        let watch = || -> Result<ShaderReloader, GlError> {
+            let mut reloader = ShaderReloader::new(gl.clone())?;
+            let programs = &self.resources.programs;
+            reloader.watch(&programs[self.triangle_prog], TriangleShader::descriptors())?;
+            reloader.watch(&programs[self.textured_prog], TexturedShader::descriptors())?;
+            reloader.watch(
+                &programs[self.tessellated_prog],
+                TessellatedShader::descriptors(),
+            )?;
+            Ok(reloader)
+        };
+
+        watch()
+            .map_err(|e| error!("Shader hot reloading is disabled: {e}"))
+            .ok()
+    }
+
+    /// Reload programs whose shader files changed. Returns true if any program was replaced.
+    #[cfg(target_os = "linux")]
+    fn reload(&mut self, reloader: &mut ShaderReloader) -> bool {
+        // Every program is checked even after one was replaced. Programs that aren't watched are
+        // skipped by the reloader.
+        self.resources
+            .programs
+            .iter_mut()
+            .fold(false, |reloaded, (_, program)| {
+                reloader.reload(program) | reloaded
+            })
+    }
+}
 
 pub struct GlTest {
     gl: Rc<Gl>,
-    triangle_prog: TriangleShader,
-    trianglebuf: TriangleBuf,
-    rectanglebuf: Rectangle,
+    programs: Programs,
     windowed_context: WindowedContext<PossiblyCurrent>,
     event_loop: EventLoop<()>,
 }
@@ -62,44 +242,14 @@ impl GlTest {
         info!("{:?}", windowed_context.get_pixel_format());
         // Load function pointers.
         let gl = Gl::load_gl(|addr| windowed_context.get_proc_address(addr));
-        // Enable debug printing
-        gl.enable_debug_output();
-        gl.debug_message_control(
-            glenums::DebugSource::DontCare,
-            glenums::DebugType::DontCare,
-            glenums::DebugSeverity::DontCare,
-            true,
-        );
-
-        // Print information on the OpenGL context.
-        let context_info = ContextInfo::new(&gl);
-        info!("{}", context_info.version);
-        info!("Vendor: {}", context_info.vendor);
-        info!("GPU: {}", context_info.renderer);
-        info!("GLSL version: {}", context_info.glsl);
-
-        // Load shaders from files and construct buffers
-        let triangle_prog = TriangleShader::new(gl.clone())?;
-        let trianglebuf = TriangleBuf::new(gl.clone())?;
-        let rectanglebuf = Rectangle::new(gl.clone())?;
-
-        // Set a base clear color
-        let clear = Clear {
-            color: Some(Color {
-                red: 220. / 255.,
-                green: 205. / 255.,
-                blue: 1.0,
-                alpha: 1.0,
-            }),
-            ..Default::default()
-        };
-        clear.set(&gl);
+        let context_info = init_gl(&gl);
+        // Skip recompiling every program on the next start
+        let cache = ProgramCache::new(&gl, program_cache_dir(), &context_info);
+        let programs = Programs::new(&gl, &context_info, &cache)?;
 
         Ok(Self {
             gl,
-            triangle_prog,
-            trianglebuf,
-            rectanglebuf,
+            programs,
             windowed_context,
             event_loop: el,
         })
@@ -110,13 +260,16 @@ impl GlTest {
         // I'll figure out a less ugly way to do this later
         let Self {
             gl,
-            triangle_prog,
-            trianglebuf,
-            rectanglebuf,
+            #[allow(unused_mut)]
+            mut programs,
             windowed_context,
             event_loop,
         } = self;
 
+        // Recompile shaders when their files are saved
+        #[cfg(target_os = "linux")]
+        let mut reloader = programs.watch(&gl);
+
         // Clear on start so the window has something to display.
         gl.clear(ClearKind::ColorBuffer);
         gl.viewport(context::Rect {
@@ -124,31 +277,61 @@ impl GlTest {
             ..Default::default()
         });
         windowed_context.swap_buffers().unwrap();
-        // Default shader at the moment
-        triangle_prog.shader.set_used();
+
+        // Last scene requested with a key press. Scenes are drawn right before swapping buffers
+        // so that screenshots capture exactly what is displayed.
+        let mut scene = Scene::Clear;
+        let mut screenshot = false;
+        // Animated scenes are drawn with the time since the window opened.
+        let start = Instant::now();
 
         event_loop.run(move |event, _, control_flow| {
             *control_flow = ControlFlow::Wait;
 
+            #[cfg(target_os = "linux")]
+            if let Some(reloader) = reloader.as_mut() {
+                // Wake up regularly to check for edited shaders
+                *control_flow =
+                    ControlFlow::WaitUntil(std::time::Instant::now() + SHADER_POLL_INTERVAL);
+                if let Event::NewEvents(glutin::event::StartCause::ResumeTimeReached { .. }) = event
+                {
+                    if reloader.poll() && programs.reload(reloader) {
+                        windowed_context.window().request_redraw()
+                    }
+                }
+            }
+
             match event {
                 Event::LoopDestroyed => (),
                 Event::WindowEvent { event, .. } => match event {
                     // Can't figure out scan codes yet.
-                    WindowEvent::KeyboardInput { input, .. } if input.virtual_keycode.is_some() => {
+                    WindowEvent::KeyboardInput { input, .. }
+                        if input.virtual_keycode.is_some()
+                            && input.state == ElementState::Pressed =>
+                    {
                         match input
                             .virtual_keycode
                             .expect("Virtual keycode is empty despite is_some()")
                         {
-                            glutin::event::VirtualKeyCode::A => {
-                                gl.clear(ClearKind::ColorBuffer);
-                                trianglebuf.vao.bind();
-                                gl.draw_elements(DrawMode::Triangles, 3, 0);
+                            VirtualKeyCode::A => {
+                                scene = Scene::Triangle;
+                                windowed_context.window().request_redraw()
+                            }
+                            VirtualKeyCode::B => {
+                                scene = Scene::Rectangle;
                                 windowed_context.window().request_redraw()
                             }
-                            glutin::event::VirtualKeyCode::B => {
-                                gl.clear(ClearKind::ColorBuffer);
-                                rectanglebuf.vao.bind();
-                                gl.draw_elements(DrawMode::Triangles, 6, 0);
+                            VirtualKeyCode::C => {
+                                scene = Scene::TexturedRectangle;
+                                windowed_context.window().request_redraw()
+                            }
+                            VirtualKeyCode::D => {
+                                scene = Scene::TessellatedRectangle;
+                                windowed_context.window().request_redraw()
+                            }
+                            // Save the next frame
+                            VirtualKeyCode::F12 => {
+                                screenshot = true;
                                 windowed_context.window().request_redraw()
                             }
                             _ => (),
@@ -169,10 +352,244 @@ impl GlTest {
                     _ => (),
                 },
                 Event::RedrawRequested(_) => {
+                    scene.draw(&gl, &programs, start.elapsed().as_secs_f32());
+
+                    // The back buffer is undefined after swapping so read it beforehand.
+                    if screenshot {
+                        screenshot = false;
+                        if let Err(e) = gl.screenshot(screenshot_path()) {
+                            error!("Failed to save screenshot: {e}");
+                        }
+                    }
                     windowed_context.swap_buffers().unwrap();
+
+                    // Keep animating the spinning triangle and the tessellated ripple.
+                    if matches!(scene, Scene::Triangle | Scene::TessellatedRectangle) {
+                        windowed_context.window().request_redraw()
+                    }
                 }
                 _ => (),
             }
         });
     }
 }
+
+/// Resources that may be drawn by [GlTest] and [GlHeadless].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scene {
+    Clear,
+    Triangle,
+    Rectangle,
+    TexturedRectangle,
+    TessellatedRectangle,
+}
+
+impl Scene {
+    /// Look up a scene by the name of its resource in [resources::programs].
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "triangle" => Some(Scene::Triangle),
+            "rectangle" => Some(Scene::Rectangle),
+            "textured_rectangle" => Some(Scene::TexturedRectangle),
+            "tessellated_rectangle" => Some(Scene::TessellatedRectangle),
+            _ => None,
+        }
+    }
+
+    /// Clear the framebuffer and draw the scene at `time` seconds into its animation.
+    fn draw(self, gl: &Rc<Gl>, programs: &Programs, time: f32) {
+        let resources = &programs.resources;
+        gl.clear(ClearKind::ColorBuffer);
+        match self {
+            Scene::Clear => (),
+            Scene::Triangle => {
+                // Spin the triangle around the screen's center.
+                let program = &resources.programs[programs.triangle_prog];
+                program.set_uniform("Transform", &glm::rotation(time, &glm::Vec3::z()));
+                program.set_used();
+                resources.vertex_arrays[programs.trianglebuf].bind();
+                gl.draw_elements(DrawMode::Triangles, 3, 0);
+            }
+            Scene::Rectangle => {
+                let program = &resources.programs[programs.triangle_prog];
+                program.set_uniform("Transform", &glm::Mat4::identity());
+                program.set_used();
+                resources.vertex_arrays[programs.rectanglebuf].bind();
+                gl.draw_elements(DrawMode::Triangles, 6, 0);
+            }
+            Scene::TexturedRectangle => {
+                resources.programs[programs.textured_prog].set_used();
+                resources.textures[programs.checker_texture].bind(0);
+                programs.checker_sampler.bind(0);
+                programs.textured_camera.bind(CAMERA_BINDING);
+                resources.vertex_arrays[programs.textured_rect].bind();
+                gl.draw_elements(DrawMode::Triangles, 6, 0);
+            }
+            Scene::TessellatedRectangle => {
+                let program = &resources.programs[programs.tessellated_prog];
+                program.set_uniform("Transform", &glm::Mat4::identity());
+                program.set_used();
+                if let Err(e) = programs.tessellated_patch.set(gl) {
+                    error!("Failed to set patch parameters: {e}");
+                }
+                let mut frame = programs.tessellated_frame.borrow_mut();
+                if let Err(e) = frame.begin(time) {
+                    error!("Failed to update frame uniforms: {e}");
+                }
+                resources.vertex_arrays[programs.rectanglebuf].bind();
+                gl.draw_elements(DrawMode::Patches, 6, 0);
+                if let Err(e) = frame.end() {
+                    error!("Failed to finish frame uniforms: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Directory of the windowed test's program binaries.
+fn program_cache_dir() -> PathBuf {
+    std::env::temp_dir().join("gl_test-programs")
+}
+
+/// Unique file name for a screenshot in the current directory.
+fn screenshot_path() -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    PathBuf::from(format!("screenshot-{timestamp}.png"))
+}
+
+/// Offscreen version of [GlTest] that doesn't need a window or a display server.
+///
+/// Resources are drawn into a framebuffer of the requested size which is useful for CI and tests.
+/// Animated resources are always drawn at their first frame so that renders are reproducible.
+#[cfg(unix)]
+pub struct GlHeadless {
+    gl: Rc<Gl>,
+    programs: Programs,
+    // Declared last so that every object above is deleted while the context is still alive.
+    context: HeadlessContext,
+}
+
+#[cfg(unix)]
+impl GlHeadless {
+    pub fn new(width: u32, height: u32) -> Result<Self, GlError> {
+        let context = HeadlessContext::new(Size { width, height })?;
+        let gl = context.gl();
+        let context_info = init_gl(&gl);
+        // Same resources as the windowed test. Programs are always compiled so that tests don't
+        // depend on binaries left behind by earlier runs.
+        let programs = Programs::new(&gl, &context_info, &ProgramCache::disabled())?;
+
+        Ok(Self {
+            gl,
+            programs,
+            context,
+        })
+    }
+
+    /// Size of the offscreen framebuffer.
+    pub fn size(&self) -> (u32, u32) {
+        let size = self.context.size();
+        (size.width, size.height)
+    }
+
+    /// Clear the framebuffer and draw the triangle.
+    pub fn draw_triangle(&self) {
+        Scene::Triangle.draw(&self.gl, &self.programs, 0.0);
+    }
+
+    /// Clear the framebuffer and draw the rectangle.
+    pub fn draw_rectangle(&self) {
+        Scene::Rectangle.draw(&self.gl, &self.programs, 0.0);
+    }
+
+    /// Draw a resource from [resources::programs] by name and read back the frame.
+    ///
+    /// Names are `"triangle"`, `"rectangle"`, `"textured_rectangle"` and
+    /// `"tessellated_rectangle"`.
+    pub fn render(&self, name: &str) -> Result<RgbaImage, GlError> {
+        let scene = Scene::from_name(name)
+            .ok_or_else(|| GlError::Resource(format!("No resource named '{name}'")))?;
+
+        scene.draw(&self.gl, &self.programs, 0.0);
+        self.finish();
+        Ok(self.read_pixels())
+    }
+
+    /// Draw a resource into a texture, then blit the texture into the offscreen framebuffer and
+    /// read back the frame.
+    ///
+    /// The result should be identical to [GlHeadless::render].
+    pub fn render_to_texture(&self, name: &str) -> Result<RgbaImage, GlError> {
+        let scene = Scene::from_name(name)
+            .ok_or_else(|| GlError::Resource(format!("No resource named '{name}'")))?;
+
+        let size = self.context.size();
+        let color = Texture2D::new(
+            self.gl.clone(),
+            size,
+            TextureFormat::Rgba8,
+            false,
+            "RenderTarget",
+        )?;
+        let depth_stencil = Renderbuffer::new(
+            self.gl.clone(),
+            size,
+            RenderbufferFormat::Depth24Stencil8,
+            "RenderTargetDepthStencil",
+        )?;
+        let target = Framebuffer::new(
+            self.gl.clone(),
+            [
+                (
+                    FramebufferAttachment::Color0,
+                    Attachment::Texture(color.into()),
+                ),
+                (
+                    FramebufferAttachment::DepthStencil,
+                    Attachment::Renderbuffer(depth_stencil),
+                ),
+            ],
+            "RenderTargetFramebuffer",
+        )?;
+
+        target.bind(FramebufferTarget::Draw);
+        scene.draw(&self.gl, &self.programs, 0.0);
+
+        let rect = context::Rect {
+            size,
+            ..Default::default()
+        };
+        target.blit(
+            self.context.framebuffer(),
+            rect,
+            rect,
+            ClearKind::ColorBuffer,
+            MagFilter::Nearest,
+        );
+        self.finish();
+        Ok(self.read_pixels())
+    }
+
+    /// Read back the entire offscreen framebuffer.
+    pub fn read_pixels(&self) -> RgbaImage {
+        self.context.bind_framebuffer();
+        self.gl.read_pixels(context::Rect {
+            size: self.context.size(),
+            ..Default::default()
+        })
+    }
+
+    /// Save the offscreen framebuffer to a PNG.
+    pub fn screenshot<P: AsRef<Path>>(&self, path: P) -> Result<(), GlError> {
+        self.context.bind_framebuffer();
+        self.gl.screenshot(path)
+    }
+
+    /// Block until every queued command has finished rendering.
+    pub fn finish(&self) {
+        unsafe { self.gl.Finish() }
+    }
+}
@@ -1,8 +1,23 @@
 //! Wrappers around OpenGL's memory management API.
 
+pub mod dsa;
+mod fence;
+mod gpubuffer;
 mod gpudata;
 mod layout;
+mod mapped;
 pub mod stateful;
+mod std140;
+mod std430;
+mod storagebuffer;
+mod uniformbuffer;
 
+pub use fence::Fence;
+pub use gpubuffer::{BufferBackend, GpuBuffer};
 pub use gpudata::{GpuData, GpuDataIndices, GpuDataVerts};
-pub use layout::Layout;
+pub use layout::{validate_layouts, Layout, LayoutMismatch};
+pub use mapped::MappedRange;
+pub use std140::{std140_align_to, Std140};
+pub use std430::Std430;
+pub use storagebuffer::StorageBuffer;
+pub use uniformbuffer::UniformBuffer;
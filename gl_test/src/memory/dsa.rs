@@ -0,0 +1,9 @@
+//! Direct state access objects that are modified by id instead of through global bindings.
+//!
+//! [ARB_direct_state_access](https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_direct_state_access.txt)
+
+mod buffer;
+mod ring;
+
+pub use buffer::DsaBuffer as Buffer;
+pub use ring::StreamRing;
@@ -1 +1,259 @@
-// https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_direct_state_access.txt
+use crate::{
+    context::{
+        gl::{
+            self,
+            types::{GLintptr, GLsizeiptr, GLuint, GLvoid},
+        },
+        Gl,
+    },
+    glenums::{BufferStorageFlags, BufferTarget, BufferUsage, MapAccess, ObjectName},
+    glerror::GlError,
+    label::Label,
+    memory::GpuBuffer,
+};
+use log::error;
+use std::{
+    cell::Cell,
+    ptr::{self, NonNull},
+    rc::Rc,
+};
+
+/// Buffer that never modifies global binding state.
+///
+/// Storage is mutable and reallocated by every [write](GpuBuffer::write_bytes) like the stateful
+/// [Buffer](crate::memory::stateful::Buffer) unless the buffer was created
+/// [with storage](DsaBuffer::with_storage).
+//#[derive(Debug)]
+pub struct DsaBuffer {
+    gl: Rc<Gl>,
+    id: GLuint,
+    target: BufferTarget,
+    size: Cell<usize>,
+    // Flags of immutable storage or None if the storage is mutable
+    flags: Cell<Option<BufferStorageFlags>>,
+    label: Rc<str>,
+}
+
+impl DsaBuffer {
+    /// Create a new buffer object which will be used with `target`.
+    ///
+    /// Unlike GenBuffers, CreateBuffers returns a fully initialized object so it doesn't need to
+    /// be bound once before use.
+    pub fn new<S>(gl: Rc<Gl>, target: BufferTarget, label: S) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let mut id = 0;
+        unsafe { gl.CreateBuffers(1, &mut id) }
+
+        if id == 0 {
+            error!("CreateBuffers did not create a buffer. Possible context error?");
+            Err(GlError::Buffer(format!(
+                "CreateBuffers failed to create a buffer.\nObject id = 0 for {target:?}"
+            )))
+        } else {
+            let label = label.into();
+            gl.object_label(ObjectName::Buffer, id, &label);
+            Ok(Self {
+                gl,
+                id,
+                target,
+                size: Cell::new(0),
+                flags: Cell::new(None),
+                label,
+            })
+        }
+    }
+
+    /// Create a buffer with `size` bytes of uninitialized immutable storage.
+    ///
+    /// `flags` must request every way the buffer will be accessed, such as
+    /// [BufferStorageFlags::MapWrite] to map the buffer for writing. The storage can't be
+    /// resized, so writes must fit into it.
+    pub fn with_storage<S>(
+        gl: Rc<Gl>,
+        target: BufferTarget,
+        size: usize,
+        flags: BufferStorageFlags,
+        label: S,
+    ) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let buffer = Self::new(gl, target, label)?;
+        if size == 0 {
+            return Err(GlError::Buffer(format!(
+                "Storage for '{}' must not be empty",
+                buffer.label
+            )));
+        }
+
+        buffer.allocate(ptr::null(), size, flags);
+        Ok(buffer)
+    }
+
+    /// Allocate immutable storage and optionally copy `size` bytes from `data` into it.
+    fn allocate(&self, data: *const GLvoid, size: usize, flags: BufferStorageFlags) {
+        unsafe {
+            self.gl
+                .NamedBufferStorage(self.id, size as GLsizeiptr, data, flags.bits())
+        }
+        self.size.set(size);
+        self.flags.set(Some(flags));
+    }
+
+    /// Flags that the buffer's immutable storage was allocated with or None if its storage is
+    /// mutable.
+    pub fn storage_flags(&self) -> Option<BufferStorageFlags> {
+        self.flags.get()
+    }
+
+    /// Bind this buffer to its target, e.g. to draw from it.
+    pub fn bind(&self) {
+        unsafe { self.gl.BindBuffer(self.target.bits(), self.id) }
+    }
+}
+
+impl GpuBuffer for DsaBuffer {
+    fn id(&self) -> GLuint {
+        self.id
+    }
+
+    fn target(&self) -> BufferTarget {
+        self.target
+    }
+
+    fn size(&self) -> usize {
+        self.size.get()
+    }
+
+    /// Copy bytes into the buffer, reallocating its storage.
+    ///
+    /// Immutable storage isn't reallocated. Instead `data` replaces the start of the buffer and
+    /// must fit into it.
+    fn write_bytes(&self, data: &[u8], usage: BufferUsage) -> Result<(), GlError> {
+        if self.flags.get().is_none() {
+            unsafe {
+                self.gl.NamedBufferData(
+                    self.id,
+                    data.len() as GLsizeiptr,
+                    data.as_ptr() as *const GLvoid,
+                    usage.bits(),
+                )
+            }
+            self.size.set(data.len());
+            return Ok(());
+        }
+
+        let size = self.size.get();
+        if data.len() > size {
+            return Err(GlError::Buffer(format!(
+                "Can't write {} bytes into '{}' which has {size} bytes of immutable storage",
+                data.len(),
+                self.label
+            )));
+        }
+        self.write_bytes_at(0, data)
+    }
+
+    fn write_bytes_at(&self, offset: usize, data: &[u8]) -> Result<(), GlError> {
+        if self
+            .flags
+            .get()
+            .is_some_and(|flags| !flags.contains(BufferStorageFlags::DynamicStorage))
+        {
+            return Err(GlError::Buffer(format!(
+                "'{}' wasn't created with dynamic storage so it can't be written to",
+                self.label
+            )));
+        }
+
+        unsafe {
+            self.gl.NamedBufferSubData(
+                self.id,
+                offset as GLintptr,
+                data.len() as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+            )
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&self, offset: usize, data: &mut [u8]) {
+        unsafe {
+            self.gl.GetNamedBufferSubData(
+                self.id,
+                offset as GLintptr,
+                data.len() as GLsizeiptr,
+                data.as_mut_ptr() as *mut GLvoid,
+            )
+        }
+    }
+
+    fn copy_bytes(&self, other: &dyn GpuBuffer, src: usize, dst: usize, len: usize) {
+        unsafe {
+            self.gl.CopyNamedBufferSubData(
+                self.id,
+                other.id(),
+                src as GLintptr,
+                dst as GLintptr,
+                len as GLsizeiptr,
+            )
+        }
+    }
+
+    fn map_bytes(
+        &self,
+        offset: usize,
+        len: usize,
+        access: MapAccess,
+    ) -> Result<NonNull<u8>, GlError> {
+        // Immutable storage can only be mapped in ways that were requested when it was allocated.
+        if let Some(flags) = self.flags.get().filter(|flags| {
+            (access.contains(MapAccess::Read) && !flags.contains(BufferStorageFlags::MapRead))
+                || (access.contains(MapAccess::Write)
+                    && !flags.contains(BufferStorageFlags::MapWrite))
+        }) {
+            return Err(GlError::Buffer(format!(
+                "'{}' can't be mapped with {access:?} since its storage flags are {flags:?}",
+                self.label
+            )));
+        }
+
+        let ptr = unsafe {
+            self.gl.MapNamedBufferRange(
+                self.id,
+                offset as GLintptr,
+                len as GLsizeiptr,
+                access.bits(),
+            )
+        };
+
+        NonNull::new(ptr as *mut u8).ok_or_else(|| {
+            error!("MapNamedBufferRange failed to map '{}'", self.label);
+            GlError::Buffer(format!(
+                "Failed to map {len} bytes at offset {offset} of '{}'. Is it already mapped?",
+                self.label
+            ))
+        })
+    }
+
+    fn unmap(&self) -> bool {
+        unsafe { self.gl.UnmapNamedBuffer(self.id) == gl::TRUE }
+    }
+}
+
+impl Drop for DsaBuffer {
+    fn drop(&mut self) {
+        self.gl.log_deletion(ObjectName::Buffer, self.id);
+        unsafe { self.gl.DeleteBuffers(1, &self.id) }
+    }
+}
+
+impl Label for DsaBuffer {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.label.clone()
+    }
+}
@@ -0,0 +1,243 @@
+use super::Buffer;
+use crate::{
+    context::{
+        gl::types::{GLintptr, GLsizeiptr},
+        Gl,
+    },
+    glenums::{BufferStorageFlags, BufferTarget, MapAccess},
+    glerror::GlError,
+    label::Label,
+    memory::{Fence, GpuBuffer, GpuData},
+};
+use log::error;
+use std::{ptr::NonNull, rc::Rc, time::Duration};
+
+/// Number of segments so the CPU can fill one while the GPU reads the others.
+const SEGMENTS: usize = 3;
+/// Longest wait for the GPU to release a segment before giving up.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Triple buffered ring allocator for data that changes every frame.
+///
+/// The buffer is mapped once with persistent, coherent access so writes are plain memory copies.
+/// Each frame writes into its own segment which is guarded by a fence until the GPU has finished
+/// drawing from it. The CPU only waits if it's more than two frames ahead.
+///
+/// The buffer is mapped with direct state access so this requires OpenGL 4.5 or
+/// [ARB_direct_state_access](https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_direct_state_access.txt).
+///
+/// ```ignore
+/// ring.begin_frame()?;
+/// let offset = ring.push(&vertices, stride)?;
+/// // draw with a base vertex of offset / stride
+/// ring.end_frame()?;
+/// ```
+//#[derive(Debug)]
+pub struct StreamRing {
+    gl: Rc<Gl>,
+    buffer: Rc<Buffer>,
+    mapping: NonNull<u8>,
+    segment_size: usize,
+    // Index of the segment that's currently written to
+    segment: usize,
+    // Bytes used in the current segment
+    cursor: usize,
+    fences: [Option<Fence>; SEGMENTS],
+}
+
+impl StreamRing {
+    /// Allocate and map a buffer with three segments of `segment_size` bytes.
+    pub fn new<S>(
+        gl: Rc<Gl>,
+        target: BufferTarget,
+        segment_size: usize,
+        label: S,
+    ) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let size = segment_size * SEGMENTS;
+        let buffer = Buffer::with_storage(
+            gl.clone(),
+            target,
+            size,
+            BufferStorageFlags::MapWrite
+                | BufferStorageFlags::MapPersistent
+                | BufferStorageFlags::MapCoherent,
+            label,
+        )?;
+
+        let access = MapAccess::Write | MapAccess::Persistent | MapAccess::Coherent;
+        let mapping = unsafe {
+            gl.MapNamedBufferRange(
+                buffer.id(),
+                0 as GLintptr,
+                size as GLsizeiptr,
+                access.bits(),
+            )
+        };
+        let mapping = NonNull::new(mapping as *mut u8).ok_or_else(|| {
+            error!("MapNamedBufferRange failed to map '{}'", buffer.label());
+            GlError::Buffer(format!(
+                "Failed to persistently map {size} bytes of '{}'",
+                buffer.label()
+            ))
+        })?;
+
+        Ok(Self {
+            gl,
+            buffer: Rc::new(buffer),
+            mapping,
+            segment_size,
+            segment: 0,
+            cursor: 0,
+            fences: Default::default(),
+        })
+    }
+
+    /// Wait until the GPU is done with the current segment and start writing from its beginning.
+    pub fn begin_frame(&mut self) -> Result<(), GlError> {
+        if let Some(fence) = self.fences[self.segment].take() {
+            fence.wait(WAIT_TIMEOUT)?;
+        }
+        self.cursor = 0;
+        Ok(())
+    }
+
+    /// Fence the commands that read the current segment and move on to the next one.
+    ///
+    /// Call this after every draw that uses this frame's data was issued.
+    pub fn end_frame(&mut self) -> Result<(), GlError> {
+        self.fences[self.segment] = Some(Fence::new(self.gl.clone())?);
+        self.segment = (self.segment + 1) % SEGMENTS;
+        Ok(())
+    }
+
+    /// Copy bytes into the current segment.
+    ///
+    /// Returns the offset of the data from the start of the buffer which is a multiple of
+    /// `align`. Vertices aligned to their stride can be drawn with a base vertex of
+    /// `offset / stride`.
+    pub fn push_bytes(&mut self, data: &[u8], align: usize) -> Result<usize, GlError> {
+        let base = self.segment * self.segment_size;
+        let align = align.max(1);
+        let offset = (base + self.cursor).next_multiple_of(align);
+        let end = offset + data.len();
+
+        if end > base + self.segment_size {
+            return Err(GlError::Buffer(format!(
+                "Segments of '{}' are {} bytes but {} bytes were written this frame",
+                self.buffer.label(),
+                self.segment_size,
+                end - base
+            )));
+        }
+
+        // The mapping is coherent so the copy is visible to commands issued after this.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                self.mapping.as_ptr().add(offset),
+                data.len(),
+            )
+        }
+        self.cursor = end - base;
+        Ok(offset)
+    }
+
+    /// Copy data into the current segment. See [StreamRing::push_bytes].
+    pub fn push<D>(&mut self, data: &D, align: usize) -> Result<usize, GlError>
+    where
+        D: GpuData,
+    {
+        self.push_bytes(data.as_bytes(), align)
+    }
+
+    /// Buffer that the ring writes into, e.g. to reference it in a vertex array.
+    pub fn buffer(&self) -> Rc<dyn GpuBuffer> {
+        self.buffer.clone()
+    }
+
+    /// Bytes available for each frame.
+    pub fn segment_size(&self) -> usize {
+        self.segment_size
+    }
+
+    /// Bind `len` bytes at `offset` to the indexed `binding` point of the ring's target.
+    ///
+    /// Only indexed targets like [BufferTarget::Uniform] and [BufferTarget::ShaderStorage] can be
+    /// bound to a range. `offset` must be aligned to the target's offset alignment.
+    pub fn bind_range(&self, binding: u32, offset: usize, len: usize) {
+        unsafe {
+            self.gl.BindBufferRange(
+                self.buffer.target().bits(),
+                binding,
+                self.buffer.id(),
+                offset as GLintptr,
+                len as GLsizeiptr,
+            )
+        }
+    }
+}
+
+impl Drop for StreamRing {
+    fn drop(&mut self) {
+        // The buffer may outlive the ring if it's referenced elsewhere.
+        unsafe { self.gl.UnmapNamedBuffer(self.buffer.id()) };
+    }
+}
+
+impl Label for StreamRing {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.buffer.label()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{context::headless::HeadlessContext, glenums::BufferUsage};
+
+    #[test]
+    fn frames_rotate_through_segments() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let mut ring = StreamRing::new(gl.clone(), BufferTarget::Array, 64, "Ring").unwrap();
+        let frames = 7;
+        let len = 16;
+
+        // Each frame's data is copied out right after it's pushed. Later frames reuse the
+        // segments so the copies only match if every frame waited for and wrote its own segment.
+        let dest = Buffer::new(gl, BufferTarget::CopyWrite, "RingFrames").unwrap();
+        let dest: &dyn GpuBuffer = &dest;
+        dest.write_bytes(&vec![0; frames * len], BufferUsage::StaticRead)
+            .unwrap();
+        let ring_buffer = ring.buffer();
+
+        for frame in 0..frames {
+            ring.begin_frame().unwrap();
+            let data = [frame as u8; 16];
+            let offset = ring.push_bytes(&data, 16).unwrap();
+            assert_eq!(offset / ring.segment_size(), frame % SEGMENTS);
+            ring_buffer.copy_to(dest, offset, frame * len, len).unwrap();
+            ring.end_frame().unwrap();
+        }
+
+        let expected: Vec<u8> = (0..frames).flat_map(|frame| [frame as u8; 16]).collect();
+        assert_eq!(dest.read_back::<u8>().unwrap(), expected);
+    }
+
+    #[test]
+    fn segments_reject_overflow() {
+        let context = HeadlessContext::for_tests();
+        let mut ring = StreamRing::new(context.gl(), BufferTarget::Array, 32, "Ring").unwrap();
+        ring.begin_frame().unwrap();
+
+        // Alignment padding counts towards the segment's size.
+        assert_eq!(ring.push_bytes(&[1; 4], 1).unwrap(), 0);
+        assert_eq!(ring.push_bytes(&[2; 16], 16).unwrap(), 16);
+        assert!(ring.push_bytes(&[3], 1).is_err());
+    }
+}
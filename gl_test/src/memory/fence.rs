@@ -0,0 +1,64 @@
+use crate::{
+    context::{
+        gl::{self, types::GLsync},
+        Gl,
+    },
+    glerror::GlError,
+};
+use log::error;
+use std::{rc::Rc, time::Duration};
+
+/// Sync object which is signaled once every command issued before it has completed.
+///
+/// [glFenceSync](https://docs.gl/gl4/glFenceSync)
+//#[derive(Debug)]
+pub struct Fence {
+    gl: Rc<Gl>,
+    sync: GLsync,
+}
+
+impl Fence {
+    /// Insert a fence after the commands issued so far.
+    pub fn new(gl: Rc<Gl>) -> Result<Self, GlError> {
+        let sync = unsafe { gl.FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        if sync.is_null() {
+            error!("FenceSync did not create a sync object. Possible context error?");
+            return Err(GlError::Sync(
+                "FenceSync returned a null sync object".into(),
+            ));
+        }
+
+        Ok(Self { gl, sync })
+    }
+
+    /// Check if the fence was signaled without blocking.
+    pub fn is_signaled(&self) -> bool {
+        let status = unsafe { self.gl.ClientWaitSync(self.sync, 0, 0) };
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+
+    /// Block until the fence is signaled or `timeout` passes.
+    ///
+    /// Pending commands are flushed first, otherwise the fence might never be signaled.
+    pub fn wait(&self, timeout: Duration) -> Result<(), GlError> {
+        let timeout = timeout.as_nanos().min(u64::MAX as u128) as u64;
+        let status = unsafe {
+            self.gl
+                .ClientWaitSync(self.sync, gl::SYNC_FLUSH_COMMANDS_BIT, timeout)
+        };
+
+        match status {
+            gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => Ok(()),
+            gl::TIMEOUT_EXPIRED => Err(GlError::Sync(format!(
+                "Fence wasn't signaled within {timeout} ns"
+            ))),
+            _ => Err(GlError::Sync("ClientWaitSync failed".into())),
+        }
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteSync(self.sync) }
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{
+    context::{gl::types::GLuint, info::ContextInfo, Gl},
+    glenums::{BufferTarget, BufferUsage, MapAccess},
+    glerror::GlError,
+    label::Label,
+    memory::{dsa, stateful, GpuData, MappedRange},
+};
+use bytemuck::Pod;
+use std::{mem::size_of, ptr::NonNull, rc::Rc};
+
+/// Buffer object backed by either the stateful or the direct state access API.
+///
+/// Both backends create the same kind of OpenGL object so they're interchangeable wherever a
+/// buffer is referenced by id, such as in a [VertexArray](super::stateful::VertexArray).
+pub trait GpuBuffer: Label<Output = Rc<str>> {
+    /// Return OpenGL object id.
+    fn id(&self) -> GLuint;
+
+    /// Target that the buffer is meant to be bound to.
+    fn target(&self) -> BufferTarget;
+
+    /// Number of bytes allocated for the buffer.
+    fn size(&self) -> usize;
+
+    /// Copy bytes into the buffer, reallocating it to `data.len()` bytes.
+    fn write_bytes(&self, data: &[u8], usage: BufferUsage) -> Result<(), GlError>;
+
+    /// Copy bytes into the buffer at `offset` without reallocating it.
+    ///
+    /// Ranges aren't checked against the buffer's size here. Use [write_at](#method.write_at)
+    /// instead.
+    fn write_bytes_at(&self, offset: usize, data: &[u8]) -> Result<(), GlError>;
+
+    /// Copy bytes starting at `offset` into `data`.
+    ///
+    /// Ranges aren't checked against the buffer's size here. Use [read_back](#method.read_back)
+    /// instead.
+    fn read_bytes(&self, offset: usize, data: &mut [u8]);
+
+    /// Copy `len` bytes from `src` in this buffer to `dst` in `other`.
+    ///
+    /// Ranges aren't checked against the buffers' sizes here. Use [copy_to](#method.copy_to)
+    /// instead.
+    fn copy_bytes(&self, other: &dyn GpuBuffer, src: usize, dst: usize, len: usize);
+
+    /// Map `len` bytes starting at `offset` into client memory.
+    ///
+    /// Ranges aren't checked against the buffer's size here. Use [map_range](#method.map_range)
+    /// which unmaps the buffer when it's dropped.
+    fn map_bytes(
+        &self,
+        offset: usize,
+        len: usize,
+        access: MapAccess,
+    ) -> Result<NonNull<u8>, GlError>;
+
+    /// Release the buffer's mapping.
+    ///
+    /// Returns false if the buffer's contents were corrupted while it was mapped.
+    fn unmap(&self) -> bool;
+}
+
+impl dyn GpuBuffer + '_ {
+    /// Copy data into the buffer, reallocating it to the size of `data`.
+    pub fn write<D>(&self, data: &D, usage: BufferUsage) -> Result<(), GlError>
+    where
+        D: GpuData,
+    {
+        self.write_bytes(data.as_bytes(), usage)
+    }
+
+    /// Copy `data` into the buffer starting at `offset` bytes.
+    ///
+    /// The buffer isn't reallocated so the data must fit into its current storage.
+    pub fn write_at<T>(&self, offset: usize, data: &[T]) -> Result<(), GlError>
+    where
+        T: Pod,
+    {
+        let data: &[u8] = bytemuck::cast_slice(data);
+        self.check_range(offset, data.len())?;
+        self.write_bytes_at(offset, data)
+    }
+
+    /// Copy the whole buffer into client memory.
+    ///
+    /// Fails if the buffer's size isn't a multiple of `T`'s size.
+    pub fn read_back<T>(&self) -> Result<Vec<T>, GlError>
+    where
+        T: Pod,
+    {
+        let size = self.size();
+        let len = self.element_count::<T>(size)?;
+
+        let mut data = vec![T::zeroed(); len];
+        if size > 0 {
+            self.read_bytes(0, bytemuck::cast_slice_mut(&mut data));
+        }
+        Ok(data)
+    }
+
+    /// Copy `len` bytes from `src` in this buffer to `dst` in `other`.
+    ///
+    /// Both buffers may be the same as long as the ranges don't overlap.
+    pub fn copy_to(
+        &self,
+        other: &dyn GpuBuffer,
+        src: usize,
+        dst: usize,
+        len: usize,
+    ) -> Result<(), GlError> {
+        self.check_range(src, len)?;
+        other.check_range(dst, len)?;
+
+        if self.id() == other.id() && src < dst + len && dst < src + len {
+            return Err(GlError::Buffer(format!(
+                "Copy of {len} bytes from {src} to {dst} overlaps in '{}'",
+                self.label()
+            )));
+        }
+
+        if len > 0 {
+            self.copy_bytes(other, src, dst, len);
+        }
+        Ok(())
+    }
+
+    /// Map `len` elements starting at `offset` bytes as a typed slice.
+    ///
+    /// The buffer is unmapped when the returned guard is dropped. `offset` must be aligned to
+    /// `T`. Ranges that weren't mapped with [MapAccess::Read] can only be written to.
+    pub fn map_range<T>(
+        &self,
+        offset: usize,
+        len: usize,
+        access: MapAccess,
+    ) -> Result<MappedRange<'_, T>, GlError>
+    where
+        T: Pod,
+    {
+        let size = len * size_of::<T>();
+        self.check_range(offset, size)?;
+
+        if size == 0 {
+            return Err(GlError::Buffer(format!(
+                "Can't map an empty range of '{}'",
+                self.label()
+            )));
+        }
+        if !offset.is_multiple_of(std::mem::align_of::<T>()) {
+            return Err(GlError::Buffer(format!(
+                "Offset {offset} into '{}' isn't aligned to {}",
+                self.label(),
+                std::any::type_name::<T>()
+            )));
+        }
+
+        let ptr = self.map_bytes(offset, size, access)?;
+        // Mappings are aligned to at least 64 bytes so offset is the only source of misalignment.
+        Ok(unsafe { MappedRange::new(self, ptr.cast(), len, access) })
+    }
+
+    /// Check that `len` bytes starting at `offset` lie inside the buffer's storage.
+    fn check_range(&self, offset: usize, len: usize) -> Result<(), GlError> {
+        let size = self.size();
+        match offset.checked_add(len) {
+            Some(end) if end <= size => Ok(()),
+            _ => Err(GlError::Buffer(format!(
+                "Range of {len} bytes at offset {offset} is out of bounds for '{}' which has {size} bytes",
+                self.label()
+            ))),
+        }
+    }
+
+    /// Number of `T`s that fit exactly into `size` bytes.
+    fn element_count<T>(&self, size: usize) -> Result<usize, GlError> {
+        let elem = size_of::<T>();
+        if elem == 0 || !size.is_multiple_of(elem) {
+            Err(GlError::Buffer(format!(
+                "'{}' has {size} bytes which can't be read as {}",
+                self.label(),
+                std::any::type_name::<T>()
+            )))
+        } else {
+            Ok(size / elem)
+        }
+    }
+}
+
+/// API used to create and update buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferBackend {
+    /// Buffers are bound to a target before they're modified.
+    Stateful,
+    /// Buffers are modified by id without touching global state.
+    Dsa,
+}
+
+impl BufferBackend {
+    /// Pick the direct state access backend if the context supports it.
+    pub fn from_info(info: &ContextInfo) -> Self {
+        if info.supports_dsa() {
+            BufferBackend::Dsa
+        } else {
+            BufferBackend::Stateful
+        }
+    }
+
+    /// Create an empty buffer with this backend.
+    pub fn create<S>(
+        self,
+        gl: Rc<Gl>,
+        target: BufferTarget,
+        label: S,
+    ) -> Result<Rc<dyn GpuBuffer>, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        Ok(match self {
+            BufferBackend::Stateful => Rc::new(stateful::Buffer::new(gl, target, label)?),
+            BufferBackend::Dsa => Rc::new(dsa::Buffer::new(gl, target, label)?),
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::context::headless::HeadlessContext;
+
+    const BACKENDS: [BufferBackend; 2] = [BufferBackend::Stateful, BufferBackend::Dsa];
+
+    #[test]
+    fn writes_reallocate_on_both_backends() {
+        let context = HeadlessContext::for_tests();
+        for backend in BACKENDS {
+            let buffer = backend
+                .create(context.gl(), BufferTarget::Array, "Writes")
+                .unwrap();
+
+            for (value, len) in [(1u32, 4), (2, 16), (3, 2)] {
+                let data = vec![value; len];
+                buffer
+                    .write_bytes(bytemuck::cast_slice(&data), BufferUsage::DynamicDraw)
+                    .unwrap();
+                assert_eq!(buffer.size(), len * 4, "{backend:?}");
+                assert_eq!(buffer.read_back::<u32>().unwrap(), data, "{backend:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_offsets_are_rejected() {
+        let context = HeadlessContext::for_tests();
+        for backend in BACKENDS {
+            let buffer = backend
+                .create(context.gl(), BufferTarget::Array, "Range")
+                .unwrap();
+            buffer
+                .write_bytes(bytemuck::cast_slice(&[0u32; 4]), BufferUsage::DynamicDraw)
+                .unwrap();
+
+            assert!(buffer.write_at(12, &[1u32; 2]).is_err(), "{backend:?}");
+            assert!(buffer.write_at(usize::MAX, &[1u8]).is_err(), "{backend:?}");
+            assert!(buffer.copy_to(&*buffer, 16, 0, 1).is_err(), "{backend:?}");
+            assert!(
+                buffer.map_range::<u32>(8, 3, MapAccess::Read).is_err(),
+                "{backend:?}"
+            );
+            // Nothing was written by the rejected calls.
+            assert_eq!(buffer.read_back::<u32>().unwrap(), [0; 4], "{backend:?}");
+        }
+    }
+
+    #[test]
+    fn overlapping_copies_are_rejected() {
+        let context = HeadlessContext::for_tests();
+        for backend in BACKENDS {
+            let buffer = backend
+                .create(context.gl(), BufferTarget::Array, "Overlap")
+                .unwrap();
+            buffer
+                .write_bytes(
+                    bytemuck::cast_slice(&[1u32, 2, 3, 4]),
+                    BufferUsage::DynamicDraw,
+                )
+                .unwrap();
+
+            assert!(buffer.copy_to(&*buffer, 0, 4, 8).is_err(), "{backend:?}");
+            assert!(buffer.copy_to(&*buffer, 4, 0, 8).is_err(), "{backend:?}");
+            // Adjacent ranges don't overlap.
+            buffer.copy_to(&*buffer, 0, 8, 8).unwrap();
+            assert_eq!(
+                buffer.read_back::<u32>().unwrap(),
+                [1, 2, 1, 2],
+                "{backend:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn mapped_ranges_round_trip() {
+        let context = HeadlessContext::for_tests();
+        for backend in BACKENDS {
+            let buffer = backend
+                .create(context.gl(), BufferTarget::Array, "Mapped")
+                .unwrap();
+            buffer
+                .write_bytes(
+                    bytemuck::cast_slice(&[1u32, 2, 3, 4]),
+                    BufferUsage::DynamicDraw,
+                )
+                .unwrap();
+
+            {
+                let mut range = buffer
+                    .map_range::<u32>(4, 2, MapAccess::Read | MapAccess::Write)
+                    .unwrap();
+                assert_eq!(range.len(), 2);
+                assert_eq!(range.as_slice(), Some(&[2, 3][..]), "{backend:?}");
+                let values = range.as_mut_slice().unwrap();
+                values[0] *= 10;
+                values[1] *= 10;
+            }
+            assert_eq!(
+                buffer.read_back::<u32>().unwrap(),
+                [1, 20, 30, 4],
+                "{backend:?}"
+            );
+
+            // Write-only ranges can be written but not read.
+            let mut range = buffer.map_range::<u32>(0, 2, MapAccess::Write).unwrap();
+            assert!(!range.is_readable());
+            assert_eq!(range.len(), 2);
+            assert!(range.as_slice().is_none());
+            assert!(range.as_mut_slice().is_none());
+            range.write(1, &[6]).unwrap();
+            range.write(0, &[5]).unwrap();
+            assert!(range.write(1, &[7, 8]).is_err());
+            drop(range);
+            assert_eq!(
+                buffer.read_back::<u32>().unwrap(),
+                [5, 6, 30, 4],
+                "{backend:?}"
+            );
+
+            // Read-only ranges can't be written.
+            let mut range = buffer.map_range::<u32>(8, 2, MapAccess::Read).unwrap();
+            assert_eq!(range.as_slice(), Some(&[30, 4][..]), "{backend:?}");
+            assert!(range.as_mut_slice().is_none());
+            assert!(range.write(0, &[1]).is_err());
+        }
+    }
+}
@@ -9,6 +9,12 @@ pub trait GpuData {
     #[must_use]
     fn as_ptr(&self) -> *const Self::Data;
     fn size_total(&self) -> usize;
+
+    /// View the data as raw bytes.
+    fn as_bytes(&self) -> &[u8] {
+        // as_ptr points to size_total bytes owned by self.
+        unsafe { std::slice::from_raw_parts(self.as_ptr() as *const u8, self.size_total()) }
+    }
 }
 
 // Trait for a bytes array containing vertices.
@@ -1,3 +1,9 @@
+use crate::{glenums::GlslType, shaders::ProgramVariable};
+use std::{
+    fmt::{self, Display, Formatter},
+    mem::size_of,
+};
+
 #[derive(Debug, Clone, Copy)]
 pub struct Layout {
     /// Location index for shader
@@ -31,3 +37,255 @@ pub struct Layout {
     /// Start location for current component as an offset in bytes
     pub start: usize,
 }
+
+impl Layout {
+    /// Byte just past this component in the first vertex.
+    fn end(&self) -> usize {
+        // Components are always uploaded as floats by VertexArray
+        self.start + self.size * size_of::<f32>()
+    }
+}
+
+/// Difference between a [Layout] and the vertex inputs of a shader program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutMismatch {
+    /// No active input at the layout's location
+    MissingInput { index: usize },
+    /// Number of components differs from the input's type
+    ComponentCount {
+        index: usize,
+        size: usize,
+        kind: GlslType,
+    },
+    /// Input isn't a float type so it can't be fed with float components
+    BaseType { index: usize, kind: GlslType },
+    /// Component extends into the next vertex
+    Stride {
+        index: usize,
+        end: usize,
+        stride: usize,
+    },
+    /// Component of the first vertex lies outside the vertex buffer
+    OutOfBounds {
+        index: usize,
+        end: usize,
+        buffer_size: usize,
+    },
+    /// Active input that isn't described by any layout
+    Unassigned { name: String, location: u32 },
+}
+
+impl Display for LayoutMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutMismatch::MissingInput { index } => {
+                write!(f, "location {index} isn't an active input")
+            }
+            LayoutMismatch::ComponentCount { index, size, kind } => write!(
+                f,
+                "location {index} has {size} components but the input is a {kind}"
+            ),
+            LayoutMismatch::BaseType { index, kind } => write!(
+                f,
+                "location {index} is a {kind} which can't be read from floats"
+            ),
+            LayoutMismatch::Stride { index, end, stride } => write!(
+                f,
+                "location {index} ends at byte {end} which is past the stride of {stride}"
+            ),
+            LayoutMismatch::OutOfBounds {
+                index,
+                end,
+                buffer_size,
+            } => write!(
+                f,
+                "location {index} ends at byte {end} but the vertex buffer has {buffer_size} bytes"
+            ),
+            LayoutMismatch::Unassigned { name, location } => {
+                write!(f, "input '{name}' at location {location} has no layout")
+            }
+        }
+    }
+}
+
+/// Check `layouts` against a program's vertex `inputs` and the size of the vertex buffer.
+///
+/// Returns every mismatch rather than stopping at the first.
+pub fn validate_layouts(
+    layouts: &[Layout],
+    inputs: &[ProgramVariable],
+    buffer_size: usize,
+) -> Vec<LayoutMismatch> {
+    let mut mismatches = Vec::new();
+
+    for layout in layouts {
+        let index = layout.index;
+        let input = inputs
+            .iter()
+            .find(|input| input.location == Some(index as u32));
+
+        match input {
+            None => mismatches.push(LayoutMismatch::MissingInput { index }),
+            Some(input) if !input.kind.is_float() => mismatches.push(LayoutMismatch::BaseType {
+                index,
+                kind: input.kind,
+            }),
+            Some(input) if input.kind.components() != Some(layout.size) => {
+                mismatches.push(LayoutMismatch::ComponentCount {
+                    index,
+                    size: layout.size,
+                    kind: input.kind,
+                })
+            }
+            Some(_) => (),
+        }
+
+        // A stride of zero means the components are tightly packed.
+        let end = layout.end();
+        if layout.stride != 0 && end > layout.stride {
+            mismatches.push(LayoutMismatch::Stride {
+                index,
+                end,
+                stride: layout.stride,
+            });
+        }
+        if end > buffer_size {
+            mismatches.push(LayoutMismatch::OutOfBounds {
+                index,
+                end,
+                buffer_size,
+            });
+        }
+    }
+
+    // Built-in inputs such as gl_VertexID don't have a location.
+    for input in inputs {
+        if let Some(location) = input.location {
+            if !layouts.iter().any(|layout| layout.index as u32 == location) {
+                mismatches.push(LayoutMismatch::Unassigned {
+                    name: input.name.clone(),
+                    location,
+                });
+            }
+        }
+    }
+
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STRIDE: usize = 6 * size_of::<f32>();
+
+    fn input(name: &str, kind: GlslType, location: Option<u32>) -> ProgramVariable {
+        ProgramVariable {
+            name: name.into(),
+            kind,
+            location,
+            array_size: 1,
+        }
+    }
+
+    fn layout(index: usize, size: usize, start: usize) -> Layout {
+        Layout {
+            index,
+            size,
+            stride: STRIDE,
+            start,
+        }
+    }
+
+    /// Position and color of a triangle along with a built-in input.
+    fn inputs() -> Vec<ProgramVariable> {
+        vec![
+            input("Position", GlslType::Vec3, Some(0)),
+            input("Color", GlslType::Vec3, Some(1)),
+            input("gl_VertexID", GlslType::Int, None),
+        ]
+    }
+
+    #[test]
+    fn matching_layouts_pass() {
+        let layouts = [layout(0, 3, 0), layout(1, 3, 12)];
+        assert_eq!(validate_layouts(&layouts, &inputs(), 3 * STRIDE), []);
+    }
+
+    #[test]
+    fn missing_and_unassigned_inputs_are_reported() {
+        let layouts = [layout(0, 3, 0), layout(2, 3, 12)];
+        assert_eq!(
+            validate_layouts(&layouts, &inputs(), 3 * STRIDE),
+            [
+                LayoutMismatch::MissingInput { index: 2 },
+                LayoutMismatch::Unassigned {
+                    name: "Color".into(),
+                    location: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn component_counts_and_types_are_checked() {
+        let inputs = [
+            input("Position", GlslType::Vec3, Some(0)),
+            input("Id", GlslType::IVec2, Some(1)),
+        ];
+        let layouts = [layout(0, 2, 0), layout(1, 2, 12)];
+        let mismatches = validate_layouts(&layouts, &inputs, 3 * STRIDE);
+        assert_eq!(
+            mismatches,
+            [
+                LayoutMismatch::ComponentCount {
+                    index: 0,
+                    size: 2,
+                    kind: GlslType::Vec3
+                },
+                LayoutMismatch::BaseType {
+                    index: 1,
+                    kind: GlslType::IVec2
+                },
+            ]
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            "location 0 has 2 components but the input is a vec3"
+        );
+    }
+
+    #[test]
+    fn components_must_fit_the_stride_and_buffer() {
+        // The color overlaps the next vertex and the buffer is smaller than one vertex.
+        let layouts = [layout(0, 3, 0), layout(1, 3, 16)];
+        assert_eq!(
+            validate_layouts(&layouts, &inputs(), 20),
+            [
+                LayoutMismatch::Stride {
+                    index: 1,
+                    end: 28,
+                    stride: STRIDE
+                },
+                LayoutMismatch::OutOfBounds {
+                    index: 1,
+                    end: 28,
+                    buffer_size: 20
+                },
+            ]
+        );
+
+        // Tightly packed components don't have a stride to exceed.
+        let packed = [
+            Layout {
+                stride: 0,
+                ..layout(0, 3, 0)
+            },
+            Layout {
+                stride: 0,
+                ..layout(1, 3, 36)
+            },
+        ];
+        assert_eq!(validate_layouts(&packed, &inputs(), 72), []);
+    }
+}
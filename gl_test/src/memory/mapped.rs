@@ -0,0 +1,116 @@
+use crate::{glenums::MapAccess, glerror::GlError, memory::GpuBuffer};
+use log::error;
+use std::ptr::NonNull;
+
+/// Typed view of a mapped buffer range which unmaps the buffer when it's dropped.
+///
+/// Created by [map_range](GpuBuffer#method.map_range). The contents of mappings without
+/// [MapAccess::Read] are unspecified, so they can only be written to with
+/// [write](#method.write), while mappings without [MapAccess::Write] can only be read.
+pub struct MappedRange<'a, T> {
+    buffer: &'a dyn GpuBuffer,
+    ptr: NonNull<T>,
+    len: usize,
+    access: MapAccess,
+}
+
+impl<'a, T> MappedRange<'a, T> {
+    /// Wrap a mapping of `len` elements of `buffer`.
+    ///
+    /// # Safety
+    /// `ptr` must be an aligned mapping of at least `len` elements that stays valid until
+    /// `buffer` is unmapped.
+    pub(crate) unsafe fn new(
+        buffer: &'a dyn GpuBuffer,
+        ptr: NonNull<T>,
+        len: usize,
+        access: MapAccess,
+    ) -> Self {
+        Self {
+            buffer,
+            ptr,
+            len,
+            access,
+        }
+    }
+
+    /// Number of mapped elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no elements are mapped.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the range was mapped with [MapAccess::Read] and may be read.
+    pub fn is_readable(&self) -> bool {
+        self.access.contains(MapAccess::Read)
+    }
+
+    /// Whether the range was mapped with [MapAccess::Write] and may be written.
+    pub fn is_writable(&self) -> bool {
+        self.access.contains(MapAccess::Write)
+    }
+
+    /// The mapped elements, or None if the range isn't readable.
+    pub fn as_slice(&self) -> Option<&[T]> {
+        // The buffer can't be mapped twice so this is the only view of the range.
+        self.is_readable()
+            .then(|| unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) })
+    }
+
+    /// The mapped elements for reading and writing, or None unless the range is both readable
+    /// and writable.
+    pub fn as_mut_slice(&mut self) -> Option<&mut [T]> {
+        (self.is_readable() && self.is_writable())
+            .then(|| unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) })
+    }
+}
+
+impl<T: Copy> MappedRange<'_, T> {
+    /// Copy `values` into the range starting at element `index` without reading it.
+    ///
+    /// Fails if the range isn't writable or `values` don't fit.
+    pub fn write(&mut self, index: usize, values: &[T]) -> Result<(), GlError> {
+        if !self.is_writable() {
+            return Err(GlError::Buffer(format!(
+                "'{}' was mapped without write access",
+                self.buffer.label()
+            )));
+        }
+        if index
+            .checked_add(values.len())
+            .is_none_or(|end| end > self.len)
+        {
+            return Err(GlError::Buffer(format!(
+                "Writing {} elements at {index} is out of bounds for a mapping of {} elements of '{}'",
+                values.len(),
+                self.len,
+                self.buffer.label()
+            )));
+        }
+
+        // Only writes through the pointer so the unspecified contents are never read.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                values.as_ptr(),
+                self.ptr.as_ptr().add(index),
+                values.len(),
+            )
+        }
+        Ok(())
+    }
+}
+
+impl<T> Drop for MappedRange<'_, T> {
+    fn drop(&mut self) {
+        if !self.buffer.unmap() {
+            error!(
+                "Contents of '{}' were corrupted while it was mapped",
+                self.buffer.label()
+            );
+        }
+    }
+}
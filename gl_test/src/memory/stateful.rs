@@ -1,7 +1,13 @@
 //! Stateful buffers and other objects that modify global state.
 
 mod buffer;
+mod framebuffer;
+mod sampler;
+mod texture;
 mod vao;
 
 pub use buffer::ClassicBuffer as Buffer;
+pub use framebuffer::{Attachment, Framebuffer, Renderbuffer};
+pub use sampler::{Sampler, SamplerCache, SamplerDescriptor};
+pub use texture::Texture2D;
 pub use vao::VertexArray;
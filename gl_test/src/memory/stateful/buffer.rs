@@ -1,23 +1,28 @@
 use crate::{
     context::{
-        gl::types::{GLsizeiptr, GLuint, GLvoid},
+        gl::{
+            self,
+            types::{GLint, GLintptr, GLsizeiptr, GLuint, GLvoid},
+        },
         Gl,
     },
-    glenums::{BufferTarget, BufferUsage},
+    glenums::{BufferTarget, BufferUsage, MapAccess, ObjectName},
     glerror::GlError,
     label::Label,
-    memory::GpuData,
+    memory::{GpuBuffer, GpuData},
 };
 use log::error;
-use std::rc::Rc;
+use std::{cell::Cell, ptr::NonNull, rc::Rc};
 
-// Refactor this later into DSA and non-DSA buffer types
-// https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_direct_state_access.txt
+/// Buffer that's bound to its target whenever it's modified.
+///
+/// See [dsa::Buffer](crate::memory::dsa::Buffer) for a buffer that doesn't modify global state.
 //#[derive(Debug)]
 pub struct ClassicBuffer {
     gl: Rc<Gl>,
     id: GLuint,
     target: BufferTarget,
+    size: Cell<usize>,
     label: Rc<str>,
 }
 
@@ -42,10 +47,20 @@ impl ClassicBuffer {
             )))
         } else {
             let label = label.into();
+            unsafe {
+                // Reserved names only become buffer objects once they're bound, which they must be
+                // to be labelled. The copy target is used so that no other binding is disturbed.
+                let mut previous: GLint = 0;
+                gl.GetIntegerv(gl::COPY_WRITE_BUFFER_BINDING, &mut previous);
+                gl.BindBuffer(gl::COPY_WRITE_BUFFER, id);
+                gl.BindBuffer(gl::COPY_WRITE_BUFFER, previous as _);
+            }
+            gl.object_label(ObjectName::Buffer, id, &label);
             Ok(Self {
                 gl,
                 id,
                 target,
+                size: Cell::new(0),
                 label,
             })
         }
@@ -82,24 +97,127 @@ impl ClassicBuffer {
     where
         D: GpuData,
     {
+        self.write_raw(data.as_bytes(), usage)
+    }
+
+    fn write_raw(&self, data: &[u8], usage: BufferUsage) {
         // Bind current buffer to copy the data to the appropriate object
         self.bind();
 
         unsafe {
-            // Allocate VRAM of size data.size() and copy the data into the buffer
+            // Allocate VRAM of size data.len() and copy the data into the buffer
             // BufferData is a non-DSA function that modifies the global target binding
             self.gl.BufferData(
                 self.target.bits(),
-                data.size_total() as GLsizeiptr,
+                data.len() as GLsizeiptr,
                 data.as_ptr() as *const GLvoid,
                 usage.bits(),
             )
         }
+        self.size.set(data.len());
+    }
+
+    /// Bind this buffer to a copy target.
+    ///
+    /// Sub-updates use the copy targets so that they don't disturb bindings used for drawing,
+    /// e.g. the element buffer of the current vertex array.
+    fn bind_copy(&self, target: BufferTarget) {
+        unsafe { self.gl.BindBuffer(target.bits(), self.id) }
+    }
+}
+
+impl GpuBuffer for ClassicBuffer {
+    fn id(&self) -> GLuint {
+        self.id
+    }
+
+    fn target(&self) -> BufferTarget {
+        self.target
+    }
+
+    fn size(&self) -> usize {
+        self.size.get()
+    }
+
+    /// Copy bytes into the buffer, reallocating its storage.
+    fn write_bytes(&self, data: &[u8], usage: BufferUsage) -> Result<(), GlError> {
+        self.write_raw(data, usage);
+        Ok(())
+    }
+
+    fn write_bytes_at(&self, offset: usize, data: &[u8]) -> Result<(), GlError> {
+        self.bind_copy(BufferTarget::CopyWrite);
+        unsafe {
+            self.gl.BufferSubData(
+                gl::COPY_WRITE_BUFFER,
+                offset as GLintptr,
+                data.len() as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+            )
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&self, offset: usize, data: &mut [u8]) {
+        self.bind_copy(BufferTarget::CopyRead);
+        unsafe {
+            self.gl.GetBufferSubData(
+                gl::COPY_READ_BUFFER,
+                offset as GLintptr,
+                data.len() as GLsizeiptr,
+                data.as_mut_ptr() as *mut GLvoid,
+            )
+        }
+    }
+
+    fn copy_bytes(&self, other: &dyn GpuBuffer, src: usize, dst: usize, len: usize) {
+        self.bind_copy(BufferTarget::CopyRead);
+        unsafe {
+            self.gl.BindBuffer(gl::COPY_WRITE_BUFFER, other.id());
+            self.gl.CopyBufferSubData(
+                gl::COPY_READ_BUFFER,
+                gl::COPY_WRITE_BUFFER,
+                src as GLintptr,
+                dst as GLintptr,
+                len as GLsizeiptr,
+            )
+        }
+    }
+
+    fn map_bytes(
+        &self,
+        offset: usize,
+        len: usize,
+        access: MapAccess,
+    ) -> Result<NonNull<u8>, GlError> {
+        self.bind_copy(BufferTarget::CopyWrite);
+        let ptr = unsafe {
+            self.gl.MapBufferRange(
+                gl::COPY_WRITE_BUFFER,
+                offset as GLintptr,
+                len as GLsizeiptr,
+                access.bits(),
+            )
+        };
+
+        NonNull::new(ptr as *mut u8).ok_or_else(|| {
+            error!("MapBufferRange failed to map '{}'", self.label);
+            GlError::Buffer(format!(
+                "Failed to map {len} bytes at offset {offset} of '{}'. Is it already mapped?",
+                self.label
+            ))
+        })
+    }
+
+    fn unmap(&self) -> bool {
+        self.bind_copy(BufferTarget::CopyWrite);
+        unsafe { self.gl.UnmapBuffer(gl::COPY_WRITE_BUFFER) == gl::TRUE }
     }
 }
 
 impl Drop for ClassicBuffer {
     fn drop(&mut self) {
+        self.gl.log_deletion(ObjectName::Buffer, self.id);
         unsafe { self.gl.DeleteBuffers(1, &self.id) }
     }
 }
@@ -0,0 +1,363 @@
+use super::Texture2D;
+use crate::{
+    context::{
+        gl::{
+            self,
+            types::{GLenum, GLint, GLuint},
+        },
+        Gl, Rect, Size,
+    },
+    glenums::{
+        ClearKind, FramebufferAttachment, FramebufferStatus, FramebufferTarget, MagFilter,
+        RenderbufferFormat,
+    },
+    glerror::GlError,
+    label::Label,
+};
+use log::error;
+use std::rc::Rc;
+
+/// Image storage that can only be rendered to, not sampled.
+//#[derive(Debug)]
+pub struct Renderbuffer {
+    gl: Rc<Gl>,
+    id: GLuint,
+    size: Size,
+    format: RenderbufferFormat,
+    label: Rc<str>,
+}
+
+impl Renderbuffer {
+    pub fn new<S>(
+        gl: Rc<Gl>,
+        size: Size,
+        format: RenderbufferFormat,
+        label: S,
+    ) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let label = label.into();
+
+        let mut id = 0;
+        unsafe { gl.GenRenderbuffers(1, &mut id) }
+        if id == 0 {
+            error!("GenRenderbuffers did not reserve a renderbuffer name. Possible context error?");
+            return Err(GlError::Framebuffer(format!(
+                "GenRenderbuffers failed to reserve a renderbuffer name for '{label}'"
+            )));
+        }
+
+        unsafe {
+            gl.BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl.RenderbufferStorage(
+                gl::RENDERBUFFER,
+                format.bits(),
+                size.width as _,
+                size.height as _,
+            );
+            gl.BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+
+        Ok(Self {
+            gl,
+            id,
+            size,
+            format,
+            label,
+        })
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn format(&self) -> RenderbufferFormat {
+        self.format
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteRenderbuffers(1, &self.id) }
+    }
+}
+
+impl Label for Renderbuffer {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.label.clone()
+    }
+}
+
+/// Image attached to a [Framebuffer].
+///
+/// Textures are reference counted so they can be sampled after rendering into them.
+pub enum Attachment {
+    Texture(Rc<Texture2D>),
+    Renderbuffer(Renderbuffer),
+}
+
+impl Attachment {
+    pub fn size(&self) -> Size {
+        match self {
+            Attachment::Texture(texture) => texture.size(),
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.size(),
+        }
+    }
+}
+
+/// Framebuffer object which owns its attachments.
+//#[derive(Debug)]
+pub struct Framebuffer {
+    gl: Rc<Gl>,
+    id: GLuint,
+    attachments: Vec<(FramebufferAttachment, Attachment)>,
+    size: Size,
+    label: Rc<str>,
+}
+
+impl Framebuffer {
+    /// Create a framebuffer from a set of attachments and check that it's complete.
+    ///
+    /// Color attachments are enabled as draw buffers in the order given. The framebuffer is as
+    /// large as the smallest attachment.
+    pub fn new<S, I>(gl: Rc<Gl>, attachments: I, label: S) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+        I: IntoIterator<Item = (FramebufferAttachment, Attachment)>,
+    {
+        let label = label.into();
+        let attachments: Vec<_> = attachments.into_iter().collect();
+
+        let mut id = 0;
+        unsafe { gl.GenFramebuffers(1, &mut id) }
+        if id == 0 {
+            error!("GenFramebuffers did not reserve a framebuffer name. Possible context error?");
+            return Err(GlError::Framebuffer(format!(
+                "GenFramebuffers failed to reserve a framebuffer name for '{label}'"
+            )));
+        }
+
+        // Bound for both drawing and reading so that the read buffer set below is this
+        // framebuffer's. Both bindings are restored so creating a framebuffer doesn't redirect
+        // rendering or readback.
+        let (mut previous_draw, mut previous_read): (GLint, GLint) = (0, 0);
+        unsafe {
+            gl.GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut previous_draw);
+            gl.GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut previous_read);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, id);
+        }
+
+        let mut draw_buffers: Vec<GLenum> = Vec::new();
+        for (point, attachment) in &attachments {
+            unsafe {
+                match attachment {
+                    Attachment::Texture(texture) => gl.FramebufferTexture2D(
+                        gl::FRAMEBUFFER,
+                        point.bits(),
+                        gl::TEXTURE_2D,
+                        texture.id(),
+                        0,
+                    ),
+                    Attachment::Renderbuffer(renderbuffer) => gl.FramebufferRenderbuffer(
+                        gl::FRAMEBUFFER,
+                        point.bits(),
+                        gl::RENDERBUFFER,
+                        renderbuffer.id,
+                    ),
+                }
+            }
+
+            if point.is_color() {
+                draw_buffers.push(point.bits());
+            }
+        }
+
+        unsafe {
+            if draw_buffers.is_empty() {
+                // Depth only framebuffers, e.g. for shadow maps
+                gl.DrawBuffer(gl::NONE);
+                gl.ReadBuffer(gl::NONE);
+            } else {
+                gl.DrawBuffers(draw_buffers.len() as _, draw_buffers.as_ptr());
+            }
+        }
+
+        let status = unsafe { gl.CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        unsafe {
+            gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, previous_draw as _);
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, previous_read as _);
+        }
+
+        let size = attachments
+            .iter()
+            .map(|(_, attachment)| attachment.size())
+            .reduce(|smallest, size| Size {
+                width: smallest.width.min(size.width),
+                height: smallest.height.min(size.height),
+            })
+            .unwrap_or_default();
+
+        let framebuffer = Self {
+            gl,
+            id,
+            attachments,
+            size,
+            label,
+        };
+
+        if status == gl::FRAMEBUFFER_COMPLETE {
+            Ok(framebuffer)
+        } else {
+            // The status is kept as is for Display even if it's not a known flag.
+            let status = unsafe { FramebufferStatus::from_bits_unchecked(status) };
+            error!(
+                "Framebuffer '{}' is incomplete: {status}",
+                framebuffer.label
+            );
+            Err(GlError::IncompleteFramebuffer {
+                label: framebuffer.label.to_string(),
+                status,
+            })
+        }
+    }
+
+    /// Bind this framebuffer for drawing, reading, or both.
+    pub fn bind(&self, target: FramebufferTarget) {
+        unsafe { self.gl.BindFramebuffer(target.bits(), self.id) }
+    }
+
+    /// Bind the window system's default framebuffer.
+    pub fn unbind_any(gl: &Rc<Gl>, target: FramebufferTarget) {
+        unsafe { gl.BindFramebuffer(target.bits(), 0) }
+    }
+
+    /// Copy a region of this framebuffer into the framebuffer currently bound for drawing.
+    ///
+    /// `mask` selects which buffers are copied. Depth and stencil may only be copied with
+    /// [MagFilter::Nearest].
+    pub fn blit_to_draw(&self, src: Rect, dst: Rect, mask: ClearKind, filter: MagFilter) {
+        unsafe {
+            let mut previous: GLint = 0;
+            self.gl
+                .GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut previous);
+            self.bind(FramebufferTarget::Read);
+
+            self.gl.BlitFramebuffer(
+                src.x as _,
+                src.y as _,
+                (src.x + src.size.width) as _,
+                (src.y + src.size.height) as _,
+                dst.x as _,
+                dst.y as _,
+                (dst.x + dst.size.width) as _,
+                (dst.y + dst.size.height) as _,
+                mask.bits(),
+                filter.bits(),
+            );
+
+            self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, previous as _);
+        }
+    }
+
+    /// Copy a region of this framebuffer into another framebuffer.
+    pub fn blit(
+        &self,
+        target: &Framebuffer,
+        src: Rect,
+        dst: Rect,
+        mask: ClearKind,
+        filter: MagFilter,
+    ) {
+        let mut previous: GLint = 0;
+        unsafe {
+            self.gl
+                .GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut previous);
+        }
+        target.bind(FramebufferTarget::Draw);
+        self.blit_to_draw(src, dst, mask, filter);
+        unsafe {
+            self.gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, previous as _);
+        }
+    }
+
+    /// Texture attached to `point`, if any, e.g. to sample a rendered scene.
+    pub fn texture(&self, point: FramebufferAttachment) -> Option<&Rc<Texture2D>> {
+        self.attachments
+            .iter()
+            .find_map(|(attached, attachment)| match attachment {
+                Attachment::Texture(texture) if *attached == point => Some(texture),
+                _ => None,
+            })
+    }
+
+    /// Largest area that every attachment covers.
+    pub fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        // Attachments are dropped after the framebuffer which is fine since deleting an attached
+        // image only detaches it.
+        unsafe { self.gl.DeleteFramebuffers(1, &self.id) }
+    }
+}
+
+impl Label for Framebuffer {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.label.clone()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::context::headless::HeadlessContext;
+
+    #[test]
+    fn depth_only_keeps_read_buffer() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        context.bind_framebuffer();
+        let (mut read_buffer, mut draw_binding, mut read_binding) = (0, 0, 0);
+        unsafe { gl.GetIntegerv(gl::READ_BUFFER, &mut read_buffer) }
+
+        let depth = Renderbuffer::new(
+            gl.clone(),
+            context.size(),
+            RenderbufferFormat::Depth24Stencil8,
+            "ShadowDepth",
+        )
+        .unwrap();
+        let shadow = Framebuffer::new(
+            gl.clone(),
+            [(
+                FramebufferAttachment::DepthStencil,
+                Attachment::Renderbuffer(depth),
+            )],
+            "Shadow",
+        )
+        .unwrap();
+
+        let mut after = 0;
+        unsafe {
+            gl.GetIntegerv(gl::READ_BUFFER, &mut after);
+            gl.GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut draw_binding);
+            gl.GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut read_binding);
+        }
+        assert_eq!(after, read_buffer);
+        assert_eq!(draw_binding as GLuint, context.framebuffer().id);
+        assert_eq!(read_binding as GLuint, context.framebuffer().id);
+
+        // The new framebuffer's own read buffer is disabled.
+        shadow.bind(FramebufferTarget::Read);
+        unsafe { gl.GetIntegerv(gl::READ_BUFFER, &mut after) }
+        assert_eq!(after as GLenum, gl::NONE);
+    }
+}
@@ -0,0 +1,330 @@
+use crate::{
+    context::{
+        gl::{self, types::GLuint},
+        info::ContextInfo,
+        Gl,
+    },
+    glenums::{CompareFunc, CompareMode, MagFilter, MinFilter, SamplerParameter, WrapMode},
+    glerror::GlError,
+    label::Label,
+};
+use log::{error, info, warn};
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+/// Sampling state shared by any number of textures.
+///
+/// Descriptors are hashable so that identical samplers can be shared with [SamplerCache].
+#[derive(Debug, Clone, Copy)]
+pub struct SamplerDescriptor {
+    pub min_filter: MinFilter,
+    pub mag_filter: MagFilter,
+    pub wrap_s: WrapMode,
+    pub wrap_t: WrapMode,
+    pub wrap_r: WrapMode,
+    /// Maximum anisotropic filtering samples. 1.0 disables anisotropic filtering.
+    ///
+    /// Ignored if the context doesn't support anisotropic filtering.
+    pub anisotropy: f32,
+    /// Bias added to the mipmap level that is sampled.
+    pub lod_bias: f32,
+    pub compare_mode: CompareMode,
+    pub compare_func: CompareFunc,
+}
+
+impl Default for SamplerDescriptor {
+    fn default() -> Self {
+        Self {
+            min_filter: Default::default(),
+            mag_filter: Default::default(),
+            wrap_s: Default::default(),
+            wrap_t: Default::default(),
+            wrap_r: Default::default(),
+            anisotropy: 1.0,
+            lod_bias: 0.0,
+            compare_mode: Default::default(),
+            compare_func: Default::default(),
+        }
+    }
+}
+
+// Floats aren't Eq or Hash, so the descriptor compares their bits instead. Descriptors are only
+// compared to find identical samplers so treating NaN or -0.0 as distinct values is fine.
+impl PartialEq for SamplerDescriptor {
+    fn eq(&self, other: &Self) -> bool {
+        self.min_filter == other.min_filter
+            && self.mag_filter == other.mag_filter
+            && self.wrap_s == other.wrap_s
+            && self.wrap_t == other.wrap_t
+            && self.wrap_r == other.wrap_r
+            && self.anisotropy.to_bits() == other.anisotropy.to_bits()
+            && self.lod_bias.to_bits() == other.lod_bias.to_bits()
+            && self.compare_mode == other.compare_mode
+            && self.compare_func == other.compare_func
+    }
+}
+
+impl Eq for SamplerDescriptor {}
+
+impl Hash for SamplerDescriptor {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.min_filter.hash(state);
+        self.mag_filter.hash(state);
+        self.wrap_s.hash(state);
+        self.wrap_t.hash(state);
+        self.wrap_r.hash(state);
+        self.anisotropy.to_bits().hash(state);
+        self.lod_bias.to_bits().hash(state);
+        self.compare_mode.hash(state);
+        self.compare_func.hash(state);
+    }
+}
+
+/// Sampler object which overrides the sampling state of textures bound to the same unit.
+//#[derive(Debug)]
+pub struct Sampler {
+    gl: Rc<Gl>,
+    id: GLuint,
+    descriptor: SamplerDescriptor,
+    label: Rc<str>,
+}
+
+impl Sampler {
+    pub fn new<S>(
+        gl: Rc<Gl>,
+        context_info: &ContextInfo,
+        descriptor: SamplerDescriptor,
+        label: S,
+    ) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let label = label.into();
+
+        let mut id = 0;
+        unsafe { gl.GenSamplers(1, &mut id) }
+        if id == 0 {
+            error!("GenSamplers did not reserve a sampler name. Possible context error?");
+            return Err(GlError::Texture(format!(
+                "GenSamplers failed to reserve a sampler name for '{label}'"
+            )));
+        }
+
+        let sampler = Self {
+            gl,
+            id,
+            descriptor,
+            label,
+        };
+        sampler.apply(context_info.supports_anisotropic_filtering());
+        Ok(sampler)
+    }
+
+    /// Copy the descriptor's state into the sampler object.
+    fn apply(&self, anisotropic: bool) {
+        let gl = &self.gl;
+        let descriptor = &self.descriptor;
+        let parameters = [
+            (SamplerParameter::MinFilter, descriptor.min_filter.bits()),
+            (SamplerParameter::MagFilter, descriptor.mag_filter.bits()),
+            (SamplerParameter::WrapS, descriptor.wrap_s.bits()),
+            (SamplerParameter::WrapT, descriptor.wrap_t.bits()),
+            (SamplerParameter::WrapR, descriptor.wrap_r.bits()),
+            (
+                SamplerParameter::CompareMode,
+                descriptor.compare_mode.bits(),
+            ),
+            (
+                SamplerParameter::CompareFunc,
+                descriptor.compare_func.bits(),
+            ),
+        ];
+
+        unsafe {
+            // Sampler parameters are set by id so binding isn't needed.
+            for (parameter, value) in parameters {
+                gl.SamplerParameteri(self.id, parameter.bits(), value as _);
+            }
+            gl.SamplerParameterf(
+                self.id,
+                SamplerParameter::LodBias.bits(),
+                descriptor.lod_bias,
+            );
+
+            let max_anisotropy = || {
+                let mut max = 1.0;
+                gl.GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY, &mut max);
+                max
+            };
+            match clamp_anisotropy(descriptor.anisotropy, anisotropic, max_anisotropy) {
+                Some(anisotropy) => gl.SamplerParameterf(
+                    self.id,
+                    SamplerParameter::MaxAnisotropy.bits(),
+                    anisotropy,
+                ),
+                None if descriptor.anisotropy > 1.0 => warn!(
+                    "Anisotropic filtering isn't supported so sampler '{}' doesn't use it",
+                    self.label
+                ),
+                None => {}
+            }
+        }
+    }
+
+    /// Bind this sampler to a texture unit.
+    pub fn bind(&self, unit: u32) {
+        unsafe { self.gl.BindSampler(unit, self.id) }
+    }
+
+    /// Unbind any sampler from a texture unit so the texture's own state is used.
+    pub fn unbind_any(gl: &Rc<Gl>, unit: u32) {
+        unsafe { gl.BindSampler(unit, 0) }
+    }
+
+    /// State that this sampler was created with.
+    pub fn descriptor(&self) -> &SamplerDescriptor {
+        &self.descriptor
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteSamplers(1, &self.id) }
+    }
+}
+
+impl Label for Sampler {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.label.clone()
+    }
+}
+
+/// Deduplicates samplers with identical state.
+pub struct SamplerCache {
+    gl: Rc<Gl>,
+    context_info: ContextInfo,
+    samplers: HashMap<SamplerDescriptor, Rc<Sampler>>,
+}
+
+impl SamplerCache {
+    pub fn new(gl: Rc<Gl>, context_info: &ContextInfo) -> Self {
+        Self {
+            gl,
+            context_info: context_info.clone(),
+            samplers: HashMap::new(),
+        }
+    }
+
+    /// Retrieve a sampler matching `descriptor`, creating it if it doesn't exist.
+    pub fn get(&mut self, descriptor: SamplerDescriptor) -> Result<Rc<Sampler>, GlError> {
+        if let Some(sampler) = self.samplers.get(&descriptor) {
+            return Ok(sampler.clone());
+        }
+
+        let label = format!("Sampler{}", self.samplers.len());
+        info!("Creating sampler '{label}': {descriptor:?}");
+        let sampler = Rc::new(Sampler::new(
+            self.gl.clone(),
+            &self.context_info,
+            descriptor,
+            label,
+        )?);
+        self.samplers.insert(descriptor, sampler.clone());
+        Ok(sampler)
+    }
+
+    /// Number of unique samplers.
+    pub fn len(&self) -> usize {
+        self.samplers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samplers.is_empty()
+    }
+
+    /// Drop every cached sampler. Samplers still in use are deleted once their last handle is
+    /// dropped.
+    pub fn clear(&mut self) {
+        self.samplers.clear()
+    }
+}
+
+/// Anisotropy to set for a `requested` level, clamped to the implementation's maximum, or None
+/// if the default of 1 is kept.
+///
+/// Anisotropy can't even be queried without the extension or OpenGL 4.6, so `max` is only called
+/// when it's `supported`.
+fn clamp_anisotropy(requested: f32, supported: bool, max: impl FnOnce() -> f32) -> Option<f32> {
+    (requested > 1.0 && supported).then(|| requested.min(max()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anisotropy_is_clamped_when_supported() {
+        let no_query = || panic!("The maximum can't be queried without support");
+        assert_eq!(clamp_anisotropy(16.0, true, || 8.0), Some(8.0));
+        assert_eq!(clamp_anisotropy(4.0, true, || 8.0), Some(4.0));
+        assert_eq!(clamp_anisotropy(16.0, false, no_query), None);
+        assert_eq!(clamp_anisotropy(1.0, true, no_query), None);
+    }
+
+    #[cfg(unix)]
+    mod headless {
+        use super::*;
+        use crate::context::{headless::HeadlessContext, info::ApiVersion};
+
+        /// Anisotropy of `sampler` as reported by OpenGL along with any error that was raised.
+        fn anisotropy(gl: &Gl, sampler: &Sampler) -> (f32, u32) {
+            let mut anisotropy = 0.0;
+            unsafe {
+                gl.GetSamplerParameterfv(
+                    sampler.id,
+                    SamplerParameter::MaxAnisotropy.bits(),
+                    &mut anisotropy,
+                );
+                (anisotropy, gl.GetError())
+            }
+        }
+
+        #[test]
+        fn anisotropy_follows_support() {
+            let context = HeadlessContext::for_tests();
+            let gl = context.gl();
+            let mut context_info = ContextInfo::new(&gl);
+            let descriptor = SamplerDescriptor {
+                anisotropy: 16.0,
+                ..Default::default()
+            };
+
+            let sampler = Sampler::new(gl.clone(), &context_info, descriptor, "Native").unwrap();
+            let (value, error) = anisotropy(&gl, &sampler);
+            if context_info.supports_anisotropic_filtering() {
+                let mut max = 0.0;
+                unsafe { gl.GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY, &mut max) }
+                assert_eq!(value, 16f32.min(max));
+            } else {
+                assert_eq!(value, 1.0);
+            }
+            assert_eq!(error, gl::NO_ERROR);
+
+            // Pretend to be an older context without the extensions.
+            context_info.version = ApiVersion { major: 4, minor: 5 };
+            context_info
+                .extensions
+                .retain(|extension| !extension.contains("texture_filter_anisotropic"));
+            let unsupported =
+                Sampler::new(gl.clone(), &context_info, descriptor, "Unsupported").unwrap();
+            let (value, error) = anisotropy(&gl, &unsupported);
+            assert_eq!(value, 1.0);
+            assert_eq!(error, gl::NO_ERROR);
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use crate::{
+    context::{
+        gl::{
+            self,
+            types::{GLenum, GLint, GLsizei, GLuint, GLvoid},
+        },
+        Gl, Size,
+    },
+    glenums::TextureFormat,
+    glerror::GlError,
+    label::Label,
+};
+use image::{DynamicImage, GenericImageView};
+use log::{error, info};
+use std::{path::Path, rc::Rc};
+
+/// Two dimensional texture with immutable storage.
+//#[derive(Debug)]
+pub struct Texture2D {
+    gl: Rc<Gl>,
+    id: GLuint,
+    size: Size,
+    format: TextureFormat,
+    levels: u32,
+    label: Rc<str>,
+}
+
+impl Texture2D {
+    /// Allocate an empty texture of `size` pixels.
+    ///
+    /// Storage for every mipmap level is allocated up front if `mipmaps` is set.
+    pub fn new<S>(
+        gl: Rc<Gl>,
+        size: Size,
+        format: TextureFormat,
+        mipmaps: bool,
+        label: S,
+    ) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let label = label.into();
+        if size.width == 0 || size.height == 0 {
+            return Err(GlError::Texture(format!(
+                "Texture '{label}' must not be empty: {}x{}",
+                size.width, size.height
+            )));
+        }
+
+        let mut id = 0;
+        unsafe { gl.GenTextures(1, &mut id) }
+        if id == 0 {
+            error!("GenTextures did not reserve a texture name. Possible context error?");
+            return Err(GlError::Texture(format!(
+                "GenTextures failed to reserve a texture name for '{label}'"
+            )));
+        }
+
+        // Each mip level halves the largest dimension until it's a single pixel.
+        let levels = if mipmaps {
+            32 - size.width.max(size.height).leading_zeros()
+        } else {
+            1
+        };
+
+        let texture = Self {
+            gl,
+            id,
+            size,
+            format,
+            levels,
+            label,
+        };
+        texture.with_bound(|gl| unsafe {
+            // Immutable storage can't be resized, but it's always mipmap complete.
+            gl.TexStorage2D(
+                gl::TEXTURE_2D,
+                levels as GLsizei,
+                format.bits(),
+                size.width as _,
+                size.height as _,
+            );
+        });
+        Ok(texture)
+    }
+
+    /// Create a texture from a decoded image.
+    ///
+    /// The image is converted to the channels expected by `format`.
+    pub fn from_image<S>(
+        gl: Rc<Gl>,
+        image: &DynamicImage,
+        format: TextureFormat,
+        mipmaps: bool,
+        label: S,
+    ) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let size = Size {
+            width: image.width(),
+            height: image.height(),
+        };
+        let texture = Self::new(gl, size, format, mipmaps, label)?;
+        texture.write(image)?;
+
+        if mipmaps {
+            texture.generate_mipmaps();
+        }
+        Ok(texture)
+    }
+
+    /// Load and decode an image file into a texture.
+    pub fn from_file<P, S>(
+        gl: Rc<Gl>,
+        path: P,
+        format: TextureFormat,
+        mipmaps: bool,
+        label: S,
+    ) -> Result<Self, GlError>
+    where
+        P: AsRef<Path>,
+        S: Into<Rc<str>>,
+    {
+        let path = path.as_ref();
+        let path_name = path.to_string_lossy();
+        info!("Loading texture from file: {}", path_name);
+
+        let image =
+            image::open(path).map_err(|e| GlError::Image(format!("{e}\nPath: {}", path_name)))?;
+        Self::from_image(gl, &image, format, mipmaps, label)
+    }
+
+    /// Copy an image into the base level of the texture.
+    ///
+    /// Images larger than the texture are cropped. Depth textures can't be written to.
+    pub fn write(&self, image: &DynamicImage) -> Result<(), GlError> {
+        if self.format.is_depth() {
+            return Err(GlError::Texture(format!(
+                "Images can't be copied into depth texture '{}'",
+                self.label
+            )));
+        }
+
+        // OpenGL expects the bottom row first.
+        let image = image.flipv();
+        let row_length = image.width();
+        let width = row_length.min(self.size.width);
+        let height = image.height().min(self.size.height);
+
+        // Float formats are uploaded as floats so that nothing is clamped or truncated by the
+        // driver. Integer images are normalized to [0, 1] first.
+        let (data, data_type): (Vec<u8>, GLenum) = match self.format {
+            TextureFormat::R8 => (image.into_luma8().into_raw(), gl::UNSIGNED_BYTE),
+            TextureFormat::Rg8 => (image.into_luma_alpha8().into_raw(), gl::UNSIGNED_BYTE),
+            TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8 => {
+                (image.into_rgba8().into_raw(), gl::UNSIGNED_BYTE)
+            }
+            TextureFormat::R16f => (f32_bytes(image.into_luma16().into_raw()), gl::FLOAT),
+            TextureFormat::Rgba32f => (f32_bytes(image.into_rgba16().into_raw()), gl::FLOAT),
+            _ => unreachable!("Depth formats are checked above"),
+        };
+
+        self.with_bound(|gl| unsafe {
+            // Rows of one and two channel images aren't necessarily four byte aligned.
+            let mut alignment: GLint = 4;
+            gl.GetIntegerv(gl::UNPACK_ALIGNMENT, &mut alignment);
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            // The source image's row length is used in case it was cropped.
+            let mut previous_row_length: GLint = 0;
+            gl.GetIntegerv(gl::UNPACK_ROW_LENGTH, &mut previous_row_length);
+            gl.PixelStorei(gl::UNPACK_ROW_LENGTH, row_length as GLint);
+
+            gl.TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                0,
+                0,
+                width as _,
+                height as _,
+                self.format.pixel_format(),
+                data_type,
+                data.as_ptr() as *const GLvoid,
+            );
+
+            // Restore previous state
+            gl.PixelStorei(gl::UNPACK_ROW_LENGTH, previous_row_length);
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, alignment);
+        });
+        Ok(())
+    }
+
+    /// Regenerate every mipmap level from the base level.
+    pub fn generate_mipmaps(&self) {
+        self.with_bound(|gl| unsafe { gl.GenerateMipmap(gl::TEXTURE_2D) })
+    }
+
+    /// Bind this texture to a texture unit.
+    ///
+    /// Note that the active texture unit is left set to `unit`.
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            self.gl.ActiveTexture(gl::TEXTURE0 + unit);
+            self.gl.BindTexture(gl::TEXTURE_2D, self.id);
+        }
+    }
+
+    /// Unbind any texture from the active texture unit.
+    pub fn unbind(&self) {
+        unsafe { self.gl.BindTexture(gl::TEXTURE_2D, 0) }
+    }
+
+    /// Dimensions of the base level.
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Internal format of the texture.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Return OpenGL object id.
+    pub(super) fn id(&self) -> GLuint {
+        // Used by [Framebuffer](super::Framebuffer) to attach textures
+        self.id
+    }
+
+    /// Number of mipmap levels including the base level.
+    pub fn levels(&self) -> u32 {
+        self.levels
+    }
+
+    /// Bind the texture to the active unit while `f` runs, then restore the unit's texture.
+    ///
+    /// Used for modifying the texture without disturbing the units that the caller set up.
+    fn with_bound<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Gl) -> R,
+    {
+        let mut previous: GLint = 0;
+        unsafe {
+            self.gl.GetIntegerv(gl::TEXTURE_BINDING_2D, &mut previous);
+            self.gl.BindTexture(gl::TEXTURE_2D, self.id);
+        }
+        let result = f(&self.gl);
+        unsafe { self.gl.BindTexture(gl::TEXTURE_2D, previous as _) }
+        result
+    }
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteTextures(1, &self.id) }
+    }
+}
+
+impl Label for Texture2D {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.label.clone()
+    }
+}
+
+/// Normalize shorts to floats as native endian bytes for uploading.
+fn f32_bytes(data: Vec<u16>) -> Vec<u8> {
+    data.into_iter()
+        .map(|short| short as f32 / u16::MAX as f32)
+        .flat_map(f32::to_ne_bytes)
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::context::headless::HeadlessContext;
+    use image::{ImageBuffer, Rgba};
+
+    #[test]
+    fn float_upload_keeps_precision() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        // 1 / 65535 would round to 0 as a half float, so this checks for full floats.
+        let image = ImageBuffer::from_pixel(1, 1, Rgba([1u16, 32768, 65535, 12345]));
+        let texture = Texture2D::from_image(
+            gl.clone(),
+            &DynamicImage::ImageRgba16(image),
+            TextureFormat::Rgba32f,
+            false,
+            "Float",
+        )
+        .unwrap();
+
+        let mut texel = [0f32; 4];
+        texture.with_bound(|gl| unsafe {
+            gl.GetTexImage(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA,
+                gl::FLOAT,
+                texel.as_mut_ptr() as *mut GLvoid,
+            )
+        });
+        let expected = [1u16, 32768, 65535, 12345].map(|short| short as f32 / u16::MAX as f32);
+        assert_eq!(texel, expected);
+    }
+
+    #[test]
+    fn writing_keeps_bindings() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let size = Size {
+            width: 2,
+            height: 2,
+        };
+        let bound = Texture2D::new(gl.clone(), size, TextureFormat::Rgba8, false, "Bound").unwrap();
+        bound.bind(3);
+        let texture =
+            Texture2D::new(gl.clone(), size, TextureFormat::Rgba8, true, "Written").unwrap();
+        let image = DynamicImage::new_rgba8(2, 2);
+        texture.write(&image).unwrap();
+        texture.generate_mipmaps();
+
+        let (mut active, mut binding) = (0, 0);
+        unsafe {
+            gl.GetIntegerv(gl::ACTIVE_TEXTURE, &mut active);
+            gl.GetIntegerv(gl::TEXTURE_BINDING_2D, &mut binding);
+        }
+        assert_eq!(active as GLenum, gl::TEXTURE0 + 3);
+        assert_eq!(binding as GLuint, bound.id());
+    }
+
+    #[test]
+    fn cropped_writes_keep_unpack_state() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let size = Size {
+            width: 2,
+            height: 2,
+        };
+        let texture =
+            Texture2D::new(gl.clone(), size, TextureFormat::Rgba8, false, "Cropped").unwrap();
+        unsafe { gl.PixelStorei(gl::UNPACK_ROW_LENGTH, 7) }
+
+        // The third column is cropped.
+        let image = ImageBuffer::from_fn(3, 2, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        texture.write(&DynamicImage::ImageRgba8(image)).unwrap();
+
+        let mut texels = [[0u8; 4]; 4];
+        texture.with_bound(|gl| unsafe {
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl.GetTexImage(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                texels.as_mut_ptr() as *mut GLvoid,
+            );
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 4);
+        });
+        // OpenGL's first row is the image's bottom row.
+        assert_eq!(
+            texels,
+            [
+                [0, 1, 0, 255],
+                [1, 1, 0, 255],
+                [0, 0, 0, 255],
+                [1, 0, 0, 255]
+            ]
+        );
+
+        let mut row_length = 0;
+        unsafe { gl.GetIntegerv(gl::UNPACK_ROW_LENGTH, &mut row_length) }
+        assert_eq!(row_length, 7);
+    }
+}
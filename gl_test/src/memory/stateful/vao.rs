@@ -6,30 +6,36 @@ use crate::{
         },
         Gl,
     },
-    glenums::BufferTarget,
+    glenums::ObjectName,
     glerror::GlError,
     label::Label,
-    memory::{stateful::Buffer, Layout},
+    memory::{validate_layouts, GpuBuffer, Layout},
+    shaders::ShaderProgram,
 };
 
 use log::error;
 use std::rc::Rc;
 
 /// Vertex Array objects store metadata on vertex buffers
+///
+/// Buffers from either backend may be used since they're only referenced by id. Buffers are
+/// reference counted so they may be shared, e.g. with a [StreamRing](crate::memory::dsa::StreamRing).
 //#[derive(Debug)]
 pub struct VertexArray {
     gl: Rc<Gl>,
     id: GLuint,
-    vbo: Buffer,
-    ebo: Option<Buffer>,
+    vbo: Rc<dyn GpuBuffer>,
+    ebo: Option<Rc<dyn GpuBuffer>>,
+    // Kept to validate the vertex array against shader programs
+    layouts: Box<[Layout]>,
     label: Rc<str>,
 }
 
 impl VertexArray {
     pub fn new<S>(
         gl: Rc<Gl>,
-        mut vbo: Buffer,
-        ebo: Option<Buffer>,
+        vbo: Rc<dyn GpuBuffer>,
+        ebo: Option<Rc<dyn GpuBuffer>>,
         layouts: &[Layout],
         label: S,
     ) -> Result<Self, GlError>
@@ -39,7 +45,7 @@ impl VertexArray {
         // Bind the current buffer to the ARRAY_BUFFER target.
         // Note: VBO may be bound at any time before VertexAttribPointer since that's the function
         // that reads the global state.
-        vbo.rebind(BufferTarget::Array);
+        unsafe { gl.BindBuffer(gl::ARRAY_BUFFER, vbo.id()) }
 
         // Create a single Vertex Array object.
         let mut id = 0;
@@ -58,9 +64,13 @@ impl VertexArray {
             ));
         }
 
+        // The VAO exists now that it's bound.
+        let label = label.into();
+        gl.object_label(ObjectName::VertexArray, id, &label);
+
         // Not sure, but I think the element buffer should be bound after the VAO.
         if let Some(ebo) = ebo.as_ref() {
-            ebo.bind();
+            unsafe { gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo.id()) }
         }
 
         // Associate memory layout with VAO
@@ -83,27 +93,53 @@ impl VertexArray {
         // Vertex arrays need to be unbound before the EBO (and VBO?) or else the unbind call is
         // saved to the VAO.
         unsafe { gl.BindVertexArray(0) }
-        if let Some(ebo) = ebo.as_ref() {
-            ebo.unbind()
+        unsafe {
+            if ebo.is_some() {
+                gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+            }
+            gl.BindBuffer(gl::ARRAY_BUFFER, 0);
         }
-        vbo.unbind();
 
-        let label = label.into();
         Ok(Self {
             gl,
             id,
             vbo,
             ebo,
+            layouts: layouts.into(),
             label,
         })
     }
 
-    pub fn vertex_buffer(&self) -> &Buffer {
-        &self.vbo
+    /// Check that the layouts match `program`'s vertex inputs and fit into the vertex buffer.
+    ///
+    /// Every mismatch is reported in a [GlError::VertexLayout]. Requires OpenGL 4.3 for
+    /// [ShaderProgram::interface].
+    pub fn validate(&self, program: &ShaderProgram) -> Result<(), GlError> {
+        let inputs = program.interface().inputs;
+        let mismatches = validate_layouts(&self.layouts, &inputs, self.vbo.size());
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(GlError::VertexLayout {
+                label: self.label.to_string(),
+                program: program.label().to_string(),
+                mismatches,
+            })
+        }
+    }
+
+    pub fn vertex_buffer(&self) -> &dyn GpuBuffer {
+        self.vbo.as_ref()
+    }
+
+    pub fn element_buffer(&self) -> Option<&dyn GpuBuffer> {
+        self.ebo.as_deref()
     }
 
-    pub fn element_buffer(&self) -> Option<&Buffer> {
-        self.ebo.as_ref()
+    /// Vertex buffer followed by the element buffer if there is one.
+    pub fn buffers(&self) -> impl Iterator<Item = &Rc<dyn GpuBuffer>> {
+        std::iter::once(&self.vbo).chain(&self.ebo)
     }
 
     pub fn bind(&self) {
@@ -118,6 +154,7 @@ impl VertexArray {
 
 impl Drop for VertexArray {
     fn drop(&mut self) {
+        self.gl.log_deletion(ObjectName::VertexArray, self.id);
         unsafe { self.gl.DeleteVertexArrays(1, &self.id) }
     }
 }
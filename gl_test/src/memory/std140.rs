@@ -0,0 +1,214 @@
+use nalgebra_glm as glm;
+
+pub use gl_test_derive::Std140;
+
+/// Type with a [std140](https://www.khronos.org/opengl/wiki/Interface_Block_(GLSL)#Memory_layout)
+/// layout which is used by uniform blocks.
+///
+/// Implement this with `#[derive(Std140)]` for structs that mirror a uniform block. Scalars,
+/// vectors and matrices from `nalgebra-glm` and `cgmath`, and arrays of any std140 type are
+/// implemented here. Rust arrays are always GLSL arrays, so `[f32; 3]` is a `float[3]` rather than
+/// a `vec3`.
+///
+/// Fields of derived structs must be aligned to a power of two of at least four bytes, which is
+/// checked when the struct is compiled:
+///
+/// ```compile_fail
+/// use gl_test::memory::Std140;
+///
+/// struct Packed([u8; 3]);
+///
+/// impl Std140 for Packed {
+///     const ALIGN: usize = 3;
+///     const SIZE: usize = 3;
+///
+///     fn write_std140(&self, out: &mut [u8]) {
+///         out.copy_from_slice(&self.0)
+///     }
+/// }
+///
+/// #[derive(Std140)]
+/// struct Block {
+///     packed: Packed,
+/// }
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` doesn't have a std140 layout",
+    note = "use f32, i32, u32, bool, vectors, matrices, arrays, or structs that derive Std140"
+)]
+pub trait Std140 {
+    /// Base alignment in bytes.
+    const ALIGN: usize;
+    /// Size in bytes. Arrays and structs include their trailing padding.
+    const SIZE: usize;
+
+    /// Write the value into `out` which is exactly [Std140::SIZE] bytes.
+    ///
+    /// Padding is left untouched.
+    fn write_std140(&self, out: &mut [u8]);
+
+    /// Copy the value into a new buffer with zeroed padding.
+    fn to_std140(&self) -> Vec<u8>
+    where
+        Self: Sized,
+    {
+        let mut out = vec![0; Self::SIZE];
+        self.write_std140(&mut out);
+        out
+    }
+}
+
+/// Round `offset` up to the next multiple of `align`.
+///
+/// Used by `#[derive(Std140)]` to compute field offsets at compile time.
+pub const fn std140_align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+/// Write four byte scalars.
+fn write_scalars<const N: usize>(scalars: [[u8; 4]; N], out: &mut [u8]) {
+    for (scalar, out) in scalars.iter().zip(out.chunks_exact_mut(4)) {
+        out.copy_from_slice(scalar)
+    }
+}
+
+/// Implement [Std140] for scalars and vectors of `N` scalars.
+macro_rules! impl_vector {
+    ($($t:ty, $n:literal, $align:literal => $scalars:expr),+ $(,)?) => {
+        $(
+            impl Std140 for $t {
+                const ALIGN: usize = $align;
+                const SIZE: usize = 4 * $n;
+
+                fn write_std140(&self, out: &mut [u8]) {
+                    let scalars: [[u8; 4]; $n] = $scalars(self);
+                    write_scalars(scalars, out)
+                }
+            }
+        )+
+    };
+}
+
+/// Implement [Std140] for column major float matrices with `C` columns of `R` rows.
+///
+/// Columns are stored like an array of vectors so each column is padded to 16 bytes.
+macro_rules! impl_matrix {
+    ($($t:ty, $c:literal, $r:literal => |$m:ident| $floats:expr),+ $(,)?) => {
+        $(
+            impl Std140 for $t {
+                const ALIGN: usize = 16;
+                const SIZE: usize = 16 * $c;
+
+                fn write_std140(&self, out: &mut [u8]) {
+                    let $m = self;
+                    let floats: &[f32] = $floats;
+                    for (column, out) in floats.chunks_exact($r).zip(out.chunks_exact_mut(16)) {
+                        for (float, out) in column.iter().zip(out.chunks_exact_mut(4)) {
+                            out.copy_from_slice(&float.to_ne_bytes())
+                        }
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_vector!(
+    f32, 1, 4 => |v: &f32| [v.to_ne_bytes()],
+    i32, 1, 4 => |v: &i32| [v.to_ne_bytes()],
+    u32, 1, 4 => |v: &u32| [v.to_ne_bytes()],
+    // GLSL booleans are four bytes
+    bool, 1, 4 => |v: &bool| [(*v as u32).to_ne_bytes()],
+);
+
+// nalgebra-glm
+impl_vector!(
+    glm::Vec2, 2, 8 => |v: &glm::Vec2| [v.x, v.y].map(f32::to_ne_bytes),
+    glm::Vec3, 3, 16 => |v: &glm::Vec3| [v.x, v.y, v.z].map(f32::to_ne_bytes),
+    glm::Vec4, 4, 16 => |v: &glm::Vec4| [v.x, v.y, v.z, v.w].map(f32::to_ne_bytes),
+    glm::IVec2, 2, 8 => |v: &glm::IVec2| [v.x, v.y].map(i32::to_ne_bytes),
+    glm::IVec3, 3, 16 => |v: &glm::IVec3| [v.x, v.y, v.z].map(i32::to_ne_bytes),
+    glm::IVec4, 4, 16 => |v: &glm::IVec4| [v.x, v.y, v.z, v.w].map(i32::to_ne_bytes),
+    glm::UVec2, 2, 8 => |v: &glm::UVec2| [v.x, v.y].map(u32::to_ne_bytes),
+    glm::UVec3, 3, 16 => |v: &glm::UVec3| [v.x, v.y, v.z].map(u32::to_ne_bytes),
+    glm::UVec4, 4, 16 => |v: &glm::UVec4| [v.x, v.y, v.z, v.w].map(u32::to_ne_bytes),
+);
+impl_matrix!(
+    glm::Mat2, 2, 2 => |m| m.as_slice(),
+    glm::Mat3, 3, 3 => |m| m.as_slice(),
+    glm::Mat4, 4, 4 => |m| m.as_slice(),
+);
+
+// cgmath
+impl_vector!(
+    cgmath::Vector2<f32>, 2, 8 => |v: &cgmath::Vector2<f32>| [v.x, v.y].map(f32::to_ne_bytes),
+    cgmath::Vector3<f32>, 3, 16 => |v: &cgmath::Vector3<f32>| [v.x, v.y, v.z].map(f32::to_ne_bytes),
+    cgmath::Vector4<f32>, 4, 16 => |v: &cgmath::Vector4<f32>| {
+        [v.x, v.y, v.z, v.w].map(f32::to_ne_bytes)
+    },
+    cgmath::Point3<f32>, 3, 16 => |p: &cgmath::Point3<f32>| [p.x, p.y, p.z].map(f32::to_ne_bytes),
+);
+impl_matrix!(
+    cgmath::Matrix2<f32>, 2, 2 => |m| AsRef::<[f32; 4]>::as_ref(m),
+    cgmath::Matrix3<f32>, 3, 3 => |m| AsRef::<[f32; 9]>::as_ref(m),
+    cgmath::Matrix4<f32>, 4, 4 => |m| AsRef::<[f32; 16]>::as_ref(m),
+);
+
+/// Arrays are padded so that each element starts on a multiple of 16 bytes.
+impl<T, const N: usize> Std140 for [T; N]
+where
+    T: Std140,
+{
+    const ALIGN: usize = std140_align_to(T::ALIGN, 16);
+    const SIZE: usize = std140_align_to(T::SIZE, Self::ALIGN) * N;
+
+    fn write_std140(&self, out: &mut [u8]) {
+        let stride = std140_align_to(T::SIZE, Self::ALIGN);
+        for (element, out) in self.iter().zip(out.chunks_exact_mut(stride)) {
+            element.write_std140(&mut out[..T::SIZE])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Std140)]
+    struct Mixed {
+        transform: glm::Mat4,
+        position: glm::Vec3,
+        scale: f32,
+        weights: [f32; 3],
+        uv: glm::Vec2,
+    }
+
+    #[test]
+    fn derived_offsets_follow_std140() {
+        assert_eq!(Mixed::__STD140_OFFSETS, [0, 64, 76, 80, 128]);
+        assert_eq!(Mixed::ALIGN, 16);
+        assert_eq!(Mixed::SIZE, 144);
+    }
+
+    #[test]
+    fn derived_values_are_written_at_offsets() {
+        let mixed = Mixed {
+            transform: glm::Mat4::identity(),
+            position: glm::vec3(1.0, 2.0, 3.0),
+            scale: 4.0,
+            weights: [5.0, 6.0, 7.0],
+            uv: glm::vec2(8.0, 9.0),
+        };
+        let bytes = mixed.to_std140();
+        let float =
+            |offset: usize| f32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        assert_eq!(bytes.len(), 144);
+        assert_eq!([0, 20, 40, 60].map(float), [1.0; 4]);
+        assert_eq!([64, 68, 72, 76].map(float), [1.0, 2.0, 3.0, 4.0]);
+        // Array elements are padded to 16 bytes.
+        assert_eq!([80, 96, 112].map(float), [5.0, 6.0, 7.0]);
+        assert_eq!([84, 100].map(float), [0.0; 2]);
+        assert_eq!([128, 132].map(float), [8.0, 9.0]);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::memory::std140_align_to;
+use nalgebra_glm as glm;
+
+pub use gl_test_derive::Std430;
+
+/// Type with a [std430](https://www.khronos.org/opengl/wiki/Interface_Block_(GLSL)#Memory_layout)
+/// layout which is used by shader storage blocks.
+///
+/// Unlike [Std140](super::Std140), arrays and structs aren't padded to 16 bytes and values can
+/// be read back from the GPU. Implement this with `#[derive(Std430)]` for structs. As with std140,
+/// `[f32; 3]` is a `float[3]` rather than a `vec3`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` doesn't have a std430 layout",
+    note = "use f32, i32, u32, bool, vectors, matrices, arrays, or structs that derive Std430"
+)]
+pub trait Std430: Sized {
+    /// Base alignment in bytes.
+    const ALIGN: usize;
+    /// Size in bytes. Structs include their trailing padding.
+    const SIZE: usize;
+
+    /// Write the value into `out` which is exactly [Std430::SIZE] bytes.
+    ///
+    /// Padding is left untouched.
+    fn write_std430(&self, out: &mut [u8]);
+
+    /// Read a value from `bytes` which is exactly [Std430::SIZE] bytes.
+    fn read_std430(bytes: &[u8]) -> Self;
+
+    /// Distance between elements of an array of this type.
+    fn stride() -> usize {
+        std140_align_to(Self::SIZE, Self::ALIGN)
+    }
+}
+
+/// Implement [Std430] for scalars and vectors of `N` scalars.
+macro_rules! impl_vector {
+    ($($t:ty, $s:ty, $n:literal, $align:literal => |$v:ident| $write:expr, |$c:ident| $read:expr),+ $(,)?) => {
+        $(
+            impl Std430 for $t {
+                const ALIGN: usize = $align;
+                const SIZE: usize = 4 * $n;
+
+                fn write_std430(&self, out: &mut [u8]) {
+                    let $v = self;
+                    let scalars: [$s; $n] = $write;
+                    for (scalar, out) in scalars.iter().zip(out.chunks_exact_mut(4)) {
+                        out.copy_from_slice(&scalar.to_ne_bytes())
+                    }
+                }
+
+                fn read_std430(bytes: &[u8]) -> Self {
+                    let $c: [$s; $n] = std::array::from_fn(|i| {
+                        let scalar = bytes[i * 4..i * 4 + 4].try_into().expect("Four bytes");
+                        <$s>::from_ne_bytes(scalar)
+                    });
+                    $read
+                }
+            }
+        )+
+    };
+}
+
+/// Implement [Std430] for column major float matrices with `C` columns of `R` rows.
+///
+/// Columns are stored like an array of vectors so `vec3` columns are padded to 16 bytes.
+macro_rules! impl_matrix {
+    ($($t:ty, $c:literal, $r:literal, $stride:literal => |$m:ident| $floats:expr, |$f:ident| $read:expr),+ $(,)?) => {
+        $(
+            impl Std430 for $t {
+                const ALIGN: usize = $stride;
+                const SIZE: usize = $stride * $c;
+
+                fn write_std430(&self, out: &mut [u8]) {
+                    let $m = self;
+                    let floats: &[f32] = $floats;
+                    for (column, out) in floats.chunks_exact($r).zip(out.chunks_exact_mut($stride)) {
+                        for (float, out) in column.iter().zip(out.chunks_exact_mut(4)) {
+                            out.copy_from_slice(&float.to_ne_bytes())
+                        }
+                    }
+                }
+
+                fn read_std430(bytes: &[u8]) -> Self {
+                    // Tightly packed columns
+                    let $f: [f32; $c * $r] = std::array::from_fn(|i| {
+                        let offset = i / $r * $stride + i % $r * 4;
+                        let float = bytes[offset..offset + 4].try_into().expect("Four bytes");
+                        f32::from_ne_bytes(float)
+                    });
+                    $read
+                }
+            }
+        )+
+    };
+}
+
+impl_vector!(
+    f32, f32, 1, 4 => |v| [*v], |c| c[0],
+    i32, i32, 1, 4 => |v| [*v], |c| c[0],
+    u32, u32, 1, 4 => |v| [*v], |c| c[0],
+    // GLSL booleans are four bytes
+    bool, u32, 1, 4 => |v| [*v as u32], |c| c[0] != 0,
+);
+
+// nalgebra-glm
+impl_vector!(
+    glm::Vec2, f32, 2, 8 => |v| [v.x, v.y], |c| glm::vec2(c[0], c[1]),
+    glm::Vec3, f32, 3, 16 => |v| [v.x, v.y, v.z], |c| glm::vec3(c[0], c[1], c[2]),
+    glm::Vec4, f32, 4, 16 => |v| [v.x, v.y, v.z, v.w], |c| glm::vec4(c[0], c[1], c[2], c[3]),
+    glm::IVec2, i32, 2, 8 => |v| [v.x, v.y], |c| glm::IVec2::new(c[0], c[1]),
+    glm::IVec3, i32, 3, 16 => |v| [v.x, v.y, v.z], |c| glm::IVec3::new(c[0], c[1], c[2]),
+    glm::IVec4, i32, 4, 16 => |v| [v.x, v.y, v.z, v.w], |c| glm::IVec4::new(c[0], c[1], c[2], c[3]),
+    glm::UVec2, u32, 2, 8 => |v| [v.x, v.y], |c| glm::UVec2::new(c[0], c[1]),
+    glm::UVec3, u32, 3, 16 => |v| [v.x, v.y, v.z], |c| glm::UVec3::new(c[0], c[1], c[2]),
+    glm::UVec4, u32, 4, 16 => |v| [v.x, v.y, v.z, v.w], |c| glm::UVec4::new(c[0], c[1], c[2], c[3]),
+);
+impl_matrix!(
+    glm::Mat2, 2, 2, 8 => |m| m.as_slice(), |f| glm::Mat2::from_column_slice(&f),
+    glm::Mat3, 3, 3, 16 => |m| m.as_slice(), |f| glm::Mat3::from_column_slice(&f),
+    glm::Mat4, 4, 4, 16 => |m| m.as_slice(), |f| glm::Mat4::from_column_slice(&f),
+);
+
+// cgmath
+impl_vector!(
+    cgmath::Vector2<f32>, f32, 2, 8 => |v| [v.x, v.y], |c| cgmath::Vector2::from(c),
+    cgmath::Vector3<f32>, f32, 3, 16 => |v| [v.x, v.y, v.z], |c| cgmath::Vector3::from(c),
+    cgmath::Vector4<f32>, f32, 4, 16 => |v| [v.x, v.y, v.z, v.w], |c| cgmath::Vector4::from(c),
+    cgmath::Point3<f32>, f32, 3, 16 => |p| [p.x, p.y, p.z], |c| cgmath::Point3::from(c),
+);
+impl_matrix!(
+    cgmath::Matrix2<f32>, 2, 2, 8 => |m| AsRef::<[f32; 4]>::as_ref(m), |f| {
+        cgmath::Matrix2::new(f[0], f[1], f[2], f[3])
+    },
+    cgmath::Matrix3<f32>, 3, 3, 16 => |m| AsRef::<[f32; 9]>::as_ref(m), |f| {
+        cgmath::Matrix3::new(f[0], f[1], f[2], f[3], f[4], f[5], f[6], f[7], f[8])
+    },
+    cgmath::Matrix4<f32>, 4, 4, 16 => |m| AsRef::<[f32; 16]>::as_ref(m), |f| {
+        cgmath::Matrix4::new(
+            f[0], f[1], f[2], f[3], f[4], f[5], f[6], f[7], f[8], f[9], f[10], f[11], f[12], f[13],
+            f[14], f[15],
+        )
+    },
+);
+
+/// Array elements are only padded to their own alignment.
+impl<T, const N: usize> Std430 for [T; N]
+where
+    T: Std430,
+{
+    const ALIGN: usize = T::ALIGN;
+    const SIZE: usize = std140_align_to(T::SIZE, T::ALIGN) * N;
+
+    fn write_std430(&self, out: &mut [u8]) {
+        for (element, out) in self.iter().zip(out.chunks_exact_mut(T::stride())) {
+            element.write_std430(&mut out[..T::SIZE])
+        }
+    }
+
+    fn read_std430(bytes: &[u8]) -> Self {
+        std::array::from_fn(|i| {
+            let offset = i * T::stride();
+            T::read_std430(&bytes[offset..offset + T::SIZE])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Std140;
+
+    #[derive(Debug, PartialEq, Std430)]
+    struct Particle {
+        mass: f32,
+        velocity: glm::Vec3,
+        weights: [f32; 3],
+        uv: glm::Vec2,
+    }
+
+    #[test]
+    fn derived_offsets_follow_std430() {
+        // The array isn't padded to 16 bytes so uv fits right after it.
+        assert_eq!(Particle::__STD430_OFFSETS, [0, 16, 28, 40]);
+        assert_eq!(Particle::ALIGN, 16);
+        assert_eq!(Particle::SIZE, 48);
+        assert_eq!(Particle::stride(), 48);
+    }
+
+    #[test]
+    fn arrays_are_packed_tighter_than_std140() {
+        assert_eq!(<[f32; 3] as Std430>::SIZE, 12);
+        assert_eq!(<[f32; 3] as Std140>::SIZE, 48);
+        assert_eq!(<[glm::Vec2; 2] as Std430>::SIZE, 16);
+        assert_eq!(<[glm::Vec3; 2] as Std430>::SIZE, 32);
+        assert_eq!(<glm::Mat2 as Std430>::SIZE, 16);
+        assert_eq!(<glm::Mat3 as Std430>::SIZE, 48);
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let particle = Particle {
+            mass: 1.0,
+            velocity: glm::vec3(2.0, 3.0, 4.0),
+            weights: [5.0, 6.0, 7.0],
+            uv: glm::vec2(8.0, 9.0),
+        };
+        let mut bytes = vec![0; Particle::SIZE];
+        particle.write_std430(&mut bytes);
+        assert_eq!(Particle::read_std430(&bytes), particle);
+
+        let matrix = glm::mat3(1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0);
+        let mut bytes = vec![0; <glm::Mat3 as Std430>::SIZE];
+        matrix.write_std430(&mut bytes);
+        assert_eq!(glm::Mat3::read_std430(&bytes), matrix);
+
+        let matrix = cgmath::Matrix3::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0);
+        matrix.write_std430(&mut bytes);
+        assert_eq!(cgmath::Matrix3::read_std430(&bytes), matrix);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    context::{gl, Gl},
+    glenums::{BufferTarget, BufferUsage},
+    glerror::GlError,
+    label::Label,
+    memory::{BufferBackend, GpuBuffer, Std430},
+};
+use std::{marker::PhantomData, rc::Rc};
+
+/// Buffer holding a runtime sized std430 array for a shader storage block.
+///
+/// The array matches a block whose last member is unsized, e.g. `buffer Particles { Particle
+/// particles[]; };`. Bind the buffer to the block's binding point before drawing or dispatching.
+//#[derive(Debug)]
+pub struct StorageBuffer<T> {
+    gl: Rc<Gl>,
+    buffer: Rc<dyn GpuBuffer>,
+    len: usize,
+    _value: PhantomData<T>,
+}
+
+impl<T> StorageBuffer<T>
+where
+    T: Std430,
+{
+    /// Create a storage buffer with the backend's storage initialized to `values`.
+    ///
+    /// The buffer's length is fixed to `values.len()` elements.
+    pub fn new<S>(
+        gl: Rc<Gl>,
+        backend: BufferBackend,
+        values: &[T],
+        label: S,
+    ) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let buffer = backend.create(gl.clone(), BufferTarget::ShaderStorage, label)?;
+        buffer.write_bytes(&Self::to_std430(values), BufferUsage::DynamicDraw)?;
+
+        Ok(Self {
+            gl,
+            buffer,
+            len: values.len(),
+            _value: PhantomData,
+        })
+    }
+
+    /// Create a storage buffer of `len` zeroed elements, e.g. for a shader's output.
+    pub fn zeroed<S>(
+        gl: Rc<Gl>,
+        backend: BufferBackend,
+        len: usize,
+        label: S,
+    ) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let buffer = backend.create(gl.clone(), BufferTarget::ShaderStorage, label)?;
+        buffer.write_bytes(&vec![0; len * T::stride()], BufferUsage::DynamicDraw)?;
+
+        Ok(Self {
+            gl,
+            buffer,
+            len,
+            _value: PhantomData,
+        })
+    }
+
+    /// Replace the elements starting at `index` without reallocating the buffer.
+    pub fn set(&self, index: usize, values: &[T]) -> Result<(), GlError> {
+        let offset = index.checked_mul(T::stride()).ok_or_else(|| {
+            GlError::Buffer(format!(
+                "Index {index} is out of bounds for '{}'",
+                self.label()
+            ))
+        })?;
+        self.buffer.write_at(offset, &Self::to_std430(values))
+    }
+
+    /// Copy every element back into client memory.
+    pub fn read_back(&self) -> Result<Vec<T>, GlError> {
+        let bytes = self.buffer.read_back::<u8>()?;
+        Ok(bytes
+            .chunks_exact(T::stride())
+            .take(self.len)
+            .map(|element| T::read_std430(&element[..T::SIZE]))
+            .collect())
+    }
+
+    /// Number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bind the buffer to the indexed shader storage `binding` point.
+    pub fn bind(&self, binding: u32) {
+        unsafe {
+            self.gl
+                .BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, self.buffer.id())
+        }
+    }
+
+    /// Underlying buffer object.
+    pub fn buffer(&self) -> Rc<dyn GpuBuffer> {
+        self.buffer.clone()
+    }
+
+    /// Copy `values` into a new buffer with zeroed padding.
+    fn to_std430(values: &[T]) -> Vec<u8> {
+        let stride = T::stride();
+        let mut out = vec![0; values.len() * stride];
+        for (value, out) in values.iter().zip(out.chunks_exact_mut(stride)) {
+            value.write_std430(&mut out[..T::SIZE])
+        }
+        out
+    }
+}
+
+impl<T> Label for StorageBuffer<T> {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.buffer.label()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        context::headless::HeadlessContext,
+        glenums::MemoryBarrier,
+        shaders::{ComputeProgram, ShaderDescriptor, ShaderFrom, ShaderKind},
+    };
+    use nalgebra_glm as glm;
+
+    const BACKENDS: [BufferBackend; 2] = [BufferBackend::Stateful, BufferBackend::Dsa];
+
+    #[derive(Debug, Clone, Copy, PartialEq, Std430)]
+    struct Particle {
+        position: glm::Vec3,
+        mass: f32,
+        velocity: glm::Vec2,
+    }
+
+    fn particle(i: f32) -> Particle {
+        Particle {
+            position: glm::vec3(i, i + 1.0, i + 2.0),
+            mass: i * 10.0,
+            velocity: glm::vec2(-i, i),
+        }
+    }
+
+    #[test]
+    fn elements_round_trip() {
+        let context = HeadlessContext::for_tests();
+        for backend in BACKENDS {
+            let particles: Vec<_> = (0..4).map(|i| particle(i as f32)).collect();
+            let buffer =
+                StorageBuffer::new(context.gl(), backend, &particles, "Particles").unwrap();
+            assert_eq!(buffer.len(), 4);
+            assert_eq!(buffer.buffer().size(), 4 * Particle::stride());
+            assert_eq!(buffer.read_back().unwrap(), particles, "{backend:?}");
+
+            buffer.set(2, &[particle(7.0), particle(8.0)]).unwrap();
+            assert!(buffer.set(3, &[particle(9.0), particle(9.0)]).is_err());
+            let expected = [particle(0.0), particle(1.0), particle(7.0), particle(8.0)];
+            assert_eq!(buffer.read_back().unwrap(), expected, "{backend:?}");
+
+            let zeroed =
+                StorageBuffer::<Particle>::zeroed(context.gl(), backend, 3, "Zeroed").unwrap();
+            let zero = Particle {
+                position: glm::Vec3::zeros(),
+                mass: 0.0,
+                velocity: glm::Vec2::zeros(),
+            };
+            assert_eq!(zeroed.read_back().unwrap(), [zero; 3], "{backend:?}");
+        }
+    }
+
+    #[test]
+    fn shaders_write_elements() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let program = ComputeProgram::new(
+            gl.clone(),
+            ShaderDescriptor::new(
+                ShaderKind::Compute,
+                ShaderFrom::Source(
+                    "#version 430 core
+                    layout (local_size_x = 4) in;
+
+                    struct Particle {
+                        vec3 Position;
+                        float Mass;
+                        vec2 Velocity;
+                    };
+
+                    layout (std430, binding = 2) buffer Particles {
+                        Particle particles[];
+                    };
+
+                    void main() {
+                        Particle particle = particles[gl_GlobalInvocationID.x];
+                        particle.Position += vec3(particle.Velocity, 0.0);
+                        particle.Mass *= 2.0;
+                        particles[gl_GlobalInvocationID.x] = particle;
+                    }"
+                    .into(),
+                ),
+            ),
+            "Integrate",
+        )
+        .unwrap();
+
+        for backend in BACKENDS {
+            let particles: Vec<_> = (0..8).map(|i| particle(i as f32)).collect();
+            let buffer = StorageBuffer::new(gl.clone(), backend, &particles, "Particles").unwrap();
+            buffer.bind(2);
+            program.dispatch([2, 1, 1]).unwrap();
+            program.memory_barrier(MemoryBarrier::BufferUpdate);
+
+            let expected: Vec<_> = particles
+                .iter()
+                .map(|particle| Particle {
+                    position: particle.position
+                        + glm::vec3(particle.velocity.x, particle.velocity.y, 0.0),
+                    mass: particle.mass * 2.0,
+                    ..*particle
+                })
+                .collect();
+            assert_eq!(buffer.read_back().unwrap(), expected, "{backend:?}");
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{
+    context::{gl, Gl},
+    glenums::{BufferTarget, BufferUsage},
+    glerror::GlError,
+    label::Label,
+    memory::{BufferBackend, GpuBuffer, Std140},
+};
+use std::{marker::PhantomData, rc::Rc};
+
+/// Buffer holding a single std140 value for a uniform block.
+///
+/// Bind the buffer to the same binding point that the block was assigned with
+/// [ShaderProgram::bind_uniform_block](crate::shaders::ShaderProgram::bind_uniform_block).
+//#[derive(Debug)]
+pub struct UniformBuffer<T> {
+    gl: Rc<Gl>,
+    buffer: Rc<dyn GpuBuffer>,
+    _value: PhantomData<T>,
+}
+
+impl<T> UniformBuffer<T>
+where
+    T: Std140,
+{
+    /// Create a uniform buffer with the backend's storage initialized to `value`.
+    pub fn new<S>(gl: Rc<Gl>, backend: BufferBackend, value: &T, label: S) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let buffer = backend.create(gl.clone(), BufferTarget::Uniform, label)?;
+        buffer.write_bytes(&value.to_std140(), BufferUsage::DynamicDraw)?;
+
+        Ok(Self {
+            gl,
+            buffer,
+            _value: PhantomData,
+        })
+    }
+
+    /// Replace the buffer's contents without reallocating it.
+    pub fn set(&self, value: &T) -> Result<(), GlError> {
+        self.buffer.write_at(0, &value.to_std140())
+    }
+
+    /// Bind the buffer to the indexed uniform `binding` point.
+    pub fn bind(&self, binding: u32) {
+        unsafe {
+            self.gl
+                .BindBufferBase(gl::UNIFORM_BUFFER, binding, self.buffer.id())
+        }
+    }
+
+    /// Underlying buffer object.
+    pub fn buffer(&self) -> Rc<dyn GpuBuffer> {
+        self.buffer.clone()
+    }
+}
+
+impl<T> Label for UniformBuffer<T> {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.buffer.label()
+    }
+}
@@ -1,3 +1,6 @@
 //! Resource management.
 
 pub mod programs;
+mod registry;
+
+pub use registry::{Handle, ResourceRegistry};
@@ -2,3 +2,13 @@
 
 pub mod rectangle;
 pub mod triangle;
+
+use std::path::{Path, PathBuf};
+
+/// Path of `name` in the workspace's assets directory.
+///
+/// Assets are found from the crate's manifest so that they load regardless of the working
+/// directory, e.g. in tests that run in parallel.
+fn asset(name: &str) -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../assets")).join(name)
+}
@@ -1,14 +1,17 @@
+use nalgebra_glm as glm;
 use std::rc::Rc;
 
+use super::asset;
 use crate::{
-    context::Gl,
-    glenums::{BufferTarget, BufferUsage},
+    context::{gl, Gl, Patch},
+    glenums::{BufferTarget, BufferUsage, MagFilter, MinFilter, TextureFormat, WrapMode},
     glerror::GlError,
     memory::{
-        stateful::{Buffer, VertexArray},
-        GpuDataIndices, GpuDataVerts,
+        dsa::StreamRing,
+        stateful::{Sampler, SamplerCache, SamplerDescriptor, Texture2D, VertexArray},
+        BufferBackend, GpuDataIndices, GpuDataVerts, Std140, UniformBuffer,
     },
-    shaders::datatypes,
+    shaders::{datatypes, ProgramCache, ShaderDescriptor, ShaderFrom, ShaderKind, ShaderProgram},
 };
 
 pub struct Rectangle {
@@ -16,15 +19,15 @@ pub struct Rectangle {
 }
 
 impl Rectangle {
-    pub fn new(gl: Rc<Gl>) -> Result<Self, GlError> {
+    pub fn new(gl: Rc<Gl>, backend: BufferBackend) -> Result<Self, GlError> {
         let rect_verts = datatypes::Rectangle::default();
         // Create buffer object for rectangle vertices
-        let vbo = Buffer::new(gl.clone(), BufferTarget::Array, "RectangleVertices")?;
-        vbo.write(&rect_verts, BufferUsage::StaticDraw);
+        let vbo = backend.create(gl.clone(), BufferTarget::Array, "RectangleVertices")?;
+        vbo.write(&rect_verts, BufferUsage::StaticDraw)?;
 
         // Element array buffer for repeated vertices
-        let ebo = Buffer::new(gl.clone(), BufferTarget::ElementArray, "RectangleEBO")?;
-        ebo.write(&rect_verts.indices(), BufferUsage::StaticDraw);
+        let ebo = backend.create(gl.clone(), BufferTarget::ElementArray, "RectangleEBO")?;
+        ebo.write(&rect_verts.indices(), BufferUsage::StaticDraw)?;
 
         // Metadata for vertex buffer
         let vao = VertexArray::new(
@@ -37,3 +40,224 @@ impl Rectangle {
         Ok(Self { vao })
     }
 }
+
+/// Binding point of the textured program's `Camera` uniform block.
+pub const CAMERA_BINDING: u32 = 0;
+
+/// Mirror of the textured program's `Camera` uniform block.
+#[derive(Debug, Clone, Copy, Std140)]
+pub struct Camera {
+    pub view: glm::Mat4,
+    pub projection: glm::Mat4,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            view: glm::Mat4::identity(),
+            projection: glm::Mat4::identity(),
+        }
+    }
+}
+
+pub struct TexturedShader {
+    pub shader: ShaderProgram,
+}
+
+impl TexturedShader {
+    /// Shaders that make up the program.
+    pub fn descriptors() -> [ShaderDescriptor; 2] {
+        [
+            ShaderDescriptor::new(
+                ShaderKind::Vertex,
+                ShaderFrom::FilePath(asset("shaders/textured.vert")),
+            ),
+            ShaderDescriptor::new(
+                ShaderKind::Fragment,
+                ShaderFrom::FilePath(asset("shaders/textured.frag")),
+            ),
+        ]
+    }
+
+    pub fn new(gl: Rc<Gl>, cache: &ProgramCache) -> Result<Self, GlError> {
+        let shader = cache.program(gl, Self::descriptors(), "TexturedShader")?;
+        shader.bind_uniform_block("Camera", CAMERA_BINDING)?;
+
+        Ok(Self { shader })
+    }
+}
+
+/// Draws the [Rectangle]'s triangles as patches which are subdivided into a rippled grid.
+pub struct TessellatedShader {
+    pub shader: ShaderProgram,
+    pub patch: Patch,
+}
+
+impl TessellatedShader {
+    /// Shaders that make up the program.
+    pub fn descriptors() -> [ShaderDescriptor; 3] {
+        [
+            ShaderDescriptor::new(
+                ShaderKind::Vertex,
+                ShaderFrom::FilePath(asset("shaders/triangle.vert")),
+            ),
+            ShaderDescriptor::new(
+                ShaderKind::TessEvaluation,
+                ShaderFrom::FilePath(asset("shaders/tessellated.tese")),
+            ),
+            ShaderDescriptor::new(
+                ShaderKind::Fragment,
+                ShaderFrom::FilePath(asset("shaders/triangle.frag")),
+            ),
+        ]
+    }
+
+    pub fn new(gl: Rc<Gl>, cache: &ProgramCache) -> Result<Self, GlError> {
+        // Reuses the triangle's stages around an evaluation shader. Without a control shader the
+        // patch's default levels decide how finely each triangle is split.
+        let shader = cache.program(gl.clone(), Self::descriptors(), "TessellatedShader")?;
+
+        // One patch per triangle of the rectangle's element buffer
+        let patch = Patch {
+            vertices: 3,
+            outer_levels: [8.0; 4],
+            inner_levels: [8.0; 2],
+        };
+        patch.set(&gl)?;
+
+        Ok(Self { shader, patch })
+    }
+}
+
+/// Binding point of the tessellated program's `Frame` uniform block.
+pub const FRAME_BINDING: u32 = 1;
+/// Size of the `Frame` block which is padded to a vec4.
+const FRAME_SIZE: usize = 16;
+
+/// Values of the tessellated program's `Frame` uniform block which change every frame.
+///
+/// With direct state access each frame is streamed into its own segment of a [StreamRing] so
+/// that updating the block never waits for the previous frame's draw.
+pub enum TessellatedFrame {
+    Streamed { ring: StreamRing, align: usize },
+    Single(UniformBuffer<f32>),
+}
+
+impl TessellatedFrame {
+    pub fn new(gl: Rc<Gl>, backend: BufferBackend) -> Result<Self, GlError> {
+        match backend {
+            BufferBackend::Dsa => {
+                // Ranges bound to a uniform block must start at this alignment.
+                let mut align = 0;
+                unsafe { gl.GetIntegerv(gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT, &mut align) }
+                let align = align.max(1) as usize;
+                let ring = StreamRing::new(
+                    gl,
+                    BufferTarget::Uniform,
+                    FRAME_SIZE.next_multiple_of(align),
+                    "TessellatedFrameRing",
+                )?;
+                Ok(Self::Streamed { ring, align })
+            }
+            BufferBackend::Stateful => Ok(Self::Single(UniformBuffer::new(
+                gl,
+                backend,
+                &0.0,
+                "TessellatedFrame",
+            )?)),
+        }
+    }
+
+    /// Write the frame's `time` in seconds and bind the block to [FRAME_BINDING].
+    ///
+    /// Call [end](#method.end) after the frame's draws were issued.
+    pub fn begin(&mut self, time: f32) -> Result<(), GlError> {
+        match self {
+            Self::Streamed { ring, align } => {
+                ring.begin_frame()?;
+                let mut frame = [0; FRAME_SIZE];
+                time.write_std140(&mut frame[..f32::SIZE]);
+                let offset = ring.push_bytes(&frame, *align)?;
+                ring.bind_range(FRAME_BINDING, offset, FRAME_SIZE);
+            }
+            Self::Single(buffer) => {
+                buffer.set(&time)?;
+                buffer.bind(FRAME_BINDING);
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish the frame started with [begin](#method.begin).
+    pub fn end(&mut self) -> Result<(), GlError> {
+        match self {
+            Self::Streamed { ring, .. } => ring.end_frame(),
+            Self::Single(_) => Ok(()),
+        }
+    }
+}
+
+pub struct TexturedRectangle {
+    pub vao: VertexArray,
+    pub texture: Texture2D,
+    pub sampler: Rc<Sampler>,
+    pub camera: UniformBuffer<Camera>,
+}
+
+impl TexturedRectangle {
+    pub fn new(
+        gl: Rc<Gl>,
+        backend: BufferBackend,
+        samplers: &mut SamplerCache,
+    ) -> Result<Self, GlError> {
+        let rect_verts = datatypes::TexturedRectangle::default();
+        let vbo = backend.create(gl.clone(), BufferTarget::Array, "TexturedRectangleVertices")?;
+        vbo.write(&rect_verts, BufferUsage::StaticDraw)?;
+
+        let ebo = backend.create(
+            gl.clone(),
+            BufferTarget::ElementArray,
+            "TexturedRectangleEBO",
+        )?;
+        ebo.write(&rect_verts.indices(), BufferUsage::StaticDraw)?;
+
+        // Sampled by the fragment shader from texture unit 0
+        let texture = Texture2D::from_file(
+            gl.clone(),
+            asset("textures/checker.png"),
+            TextureFormat::Srgb8Alpha8,
+            true,
+            "CheckerTexture",
+        )?;
+        // Keep the checkerboard's edges sharp when magnified
+        let sampler = samplers.get(SamplerDescriptor {
+            min_filter: MinFilter::LinearMipmapLinear,
+            mag_filter: MagFilter::Nearest,
+            wrap_s: WrapMode::ClampToEdge,
+            wrap_t: WrapMode::ClampToEdge,
+            ..Default::default()
+        })?;
+
+        // Looks at the rectangle head on
+        let camera = UniformBuffer::new(
+            gl.clone(),
+            backend,
+            &Camera::default(),
+            "TexturedRectangleCamera",
+        )?;
+
+        let vao = VertexArray::new(
+            gl,
+            vbo,
+            Some(ebo),
+            &rect_verts.memory_layout(),
+            "TexturedRectangleVAO",
+        )?;
+        Ok(Self {
+            vao,
+            texture,
+            sampler,
+            camera,
+        })
+    }
+}
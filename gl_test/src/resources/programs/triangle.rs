@@ -1,12 +1,12 @@
+use super::asset;
 use crate::{
     context::Gl,
     glenums::{BufferTarget, BufferUsage},
     glerror::GlError,
-    memory::{
-        stateful::{Buffer, VertexArray},
-        GpuDataIndices, GpuDataVerts,
+    memory::{stateful::VertexArray, BufferBackend, GpuDataIndices, GpuDataVerts},
+    shaders::{
+        datatypes::Triangle, ProgramCache, ShaderDescriptor, ShaderFrom, ShaderKind, ShaderProgram,
     },
-    shaders::{datatypes::Triangle, ShaderDescriptor, ShaderFrom, ShaderKind, ShaderProgram},
 };
 use std::rc::Rc;
 
@@ -15,22 +15,23 @@ pub struct TriangleShader {
 }
 
 impl TriangleShader {
-    pub fn new(gl: Rc<Gl>) -> Result<Self, GlError> {
+    /// Shaders that make up the program.
+    pub fn descriptors() -> [ShaderDescriptor; 2] {
+        [
+            ShaderDescriptor::new(
+                ShaderKind::Vertex,
+                ShaderFrom::FilePath(asset("shaders/triangle.vert")),
+            ),
+            ShaderDescriptor::new(
+                ShaderKind::Fragment,
+                ShaderFrom::FilePath(asset("shaders/triangle.frag")),
+            ),
+        ]
+    }
+
+    pub fn new(gl: Rc<Gl>, cache: &ProgramCache) -> Result<Self, GlError> {
         // Load triangle shaders from source and link them into a program
-        let shader = ShaderProgram::from_raw(
-            gl,
-            [
-                ShaderDescriptor {
-                    kind: ShaderKind::Vertex,
-                    from: ShaderFrom::FilePath("assets/shaders/triangle.vert".into()),
-                },
-                ShaderDescriptor {
-                    kind: ShaderKind::Fragment,
-                    from: ShaderFrom::FilePath("assets/shaders/triangle.frag".into()),
-                },
-            ],
-            "TriangleShader",
-        )?;
+        let shader = cache.program(gl, Self::descriptors(), "TriangleShader")?;
 
         Ok(Self { shader })
     }
@@ -41,15 +42,15 @@ pub struct TriangleBuf {
 }
 
 impl TriangleBuf {
-    pub fn new(gl: Rc<Gl>) -> Result<Self, GlError> {
+    pub fn new(gl: Rc<Gl>, backend: BufferBackend) -> Result<Self, GlError> {
         // Allocate buffers and read data into them
         let triangle_verts = Triangle::default();
-        let vbo = Buffer::new(gl.clone(), BufferTarget::Array, "TriangleVerts")?;
-        vbo.write(&triangle_verts, BufferUsage::StaticDraw);
+        let vbo = backend.create(gl.clone(), BufferTarget::Array, "TriangleVerts")?;
+        vbo.write(&triangle_verts, BufferUsage::StaticDraw)?;
 
         // Element array
-        let ebo = Buffer::new(gl.clone(), BufferTarget::ElementArray, "TriangleEBO")?;
-        ebo.write(&triangle_verts.indices(), BufferUsage::StaticDraw);
+        let ebo = backend.create(gl.clone(), BufferTarget::ElementArray, "TriangleEBO")?;
+        ebo.write(&triangle_verts.indices(), BufferUsage::StaticDraw)?;
 
         // Triangle vertices metadata
         let vao = VertexArray::new(
@@ -0,0 +1,389 @@
+use crate::{
+    glerror::GlError,
+    label::Label,
+    memory::{stateful::Texture2D, stateful::VertexArray, GpuBuffer},
+    shaders::ShaderProgram,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::{Index, IndexMut},
+    rc::Rc,
+};
+
+/// Lightweight reference to a resource stored in one of the registries of a [ResourceRegistry].
+///
+/// Handles are plain indices, so they're cheap to copy and don't keep the resource alive. A handle
+/// to a removed resource stays invalid even if its slot is reused.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _resource: PhantomData<fn() -> T>,
+}
+
+// Implemented by hand because derives would require T to implement the traits too.
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+struct Slot<T> {
+    // Incremented whenever the slot is emptied to invalidate old handles
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Resources of one type stored by their [Label].
+///
+/// Labels are unique within a registry, so resources can be looked up by label as well as by
+/// [Handle].
+pub struct Registry<T> {
+    slots: Vec<Slot<T>>,
+    // Indices of empty slots
+    free: Vec<u32>,
+    labels: HashMap<Box<str>, Handle<T>>,
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            labels: HashMap::new(),
+        }
+    }
+}
+
+// Removal and iteration aren't used by the demo yet.
+#[allow(dead_code)]
+impl<T: Label> Registry<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `resource` under its label.
+    ///
+    /// Fails if another resource already has the same label.
+    pub fn insert(&mut self, resource: T) -> Result<Handle<T>, GlError> {
+        let label: Box<str> = resource.label().as_ref().into();
+        if self.labels.contains_key(&label) {
+            return Err(GlError::Resource(format!(
+                "A resource labelled '{label}' is already registered"
+            )));
+        }
+
+        let handle = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.value = Some(resource);
+                Handle {
+                    index,
+                    generation: slot.generation,
+                    _resource: PhantomData,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(resource),
+                });
+                Handle {
+                    index: (self.slots.len() - 1) as u32,
+                    generation: 0,
+                    _resource: PhantomData,
+                }
+            }
+        };
+        self.labels.insert(label, handle);
+        Ok(handle)
+    }
+
+    /// Resource of `handle` or None if it was removed.
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    /// Handle of the resource labelled `label`.
+    pub fn find(&self, label: &str) -> Option<Handle<T>> {
+        self.labels.get(label).copied()
+    }
+
+    /// Resource labelled `label`.
+    pub fn get_by_label(&self, label: &str) -> Option<&T> {
+        self.find(label).and_then(|handle| self.get(handle))
+    }
+
+    /// Take the resource of `handle` out of the registry, which frees its label.
+    ///
+    /// Returns None if it was already removed.
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let slot = self
+            .slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)?;
+        let resource = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        self.labels.remove(resource.label().as_ref());
+        Some(resource)
+    }
+
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Every resource with its handle in insertion order, unless slots were reused.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let handle = Handle {
+                index: index as u32,
+                generation: slot.generation,
+                _resource: PhantomData,
+            };
+            slot.value.as_ref().map(|resource| (handle, resource))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle<T>, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let handle = Handle {
+                    index: index as u32,
+                    generation: slot.generation,
+                    _resource: PhantomData,
+                };
+                slot.value.as_mut().map(|resource| (handle, resource))
+            })
+    }
+}
+
+impl<T: Label> Index<Handle<T>> for Registry<T> {
+    type Output = T;
+
+    /// Panics if the resource was removed.
+    fn index(&self, handle: Handle<T>) -> &Self::Output {
+        self.get(handle)
+            .unwrap_or_else(|| panic!("{handle:?} refers to a removed resource"))
+    }
+}
+
+impl<T: Label> IndexMut<Handle<T>> for Registry<T> {
+    fn index_mut(&mut self, handle: Handle<T>) -> &mut Self::Output {
+        self.get_mut(handle)
+            .unwrap_or_else(|| panic!("{handle:?} refers to a removed resource"))
+    }
+}
+
+/// Registries for every kind of resource that the renderer draws with.
+#[derive(Default)]
+pub struct ResourceRegistry {
+    pub programs: Registry<ShaderProgram>,
+    pub buffers: Registry<Rc<dyn GpuBuffer>>,
+    pub vertex_arrays: Registry<VertexArray>,
+    pub textures: Registry<Texture2D>,
+}
+
+impl ResourceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `vertex_array` along with the buffers it sources vertices and indices from.
+    ///
+    /// Buffers may be shared with vertex arrays that were registered before. Every label is
+    /// checked first, so nothing is registered if any of them is taken.
+    pub fn insert_vertex_array(
+        &mut self,
+        vertex_array: VertexArray,
+    ) -> Result<Handle<VertexArray>, GlError> {
+        let vertex_array_label = vertex_array.label();
+        if self.vertex_arrays.find(&vertex_array_label).is_some() {
+            return Err(GlError::Resource(format!(
+                "A resource labelled '{vertex_array_label}' is already registered"
+            )));
+        }
+        let mut new_buffers: Vec<&Rc<dyn GpuBuffer>> = Vec::new();
+        for buffer in vertex_array.buffers() {
+            let label = buffer.label();
+            let taken_by = self
+                .buffers
+                .get_by_label(&label)
+                .or_else(|| new_buffers.iter().copied().find(|new| new.label() == label));
+            match taken_by {
+                Some(taken_by) if !Rc::ptr_eq(taken_by, buffer) => {
+                    return Err(GlError::Resource(format!(
+                        "A resource labelled '{label}' is already registered"
+                    )))
+                }
+                Some(_) => {}
+                None => new_buffers.push(buffer),
+            }
+        }
+
+        for buffer in vertex_array.buffers() {
+            let registered = self
+                .buffers
+                .get_by_label(&buffer.label())
+                .is_some_and(|registered| Rc::ptr_eq(registered, buffer));
+            if !registered {
+                self.buffers.insert(buffer.clone())?;
+            }
+        }
+        self.vertex_arrays.insert(vertex_array)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Named(&'static str);
+
+    impl Label for Named {
+        type Output = &'static str;
+
+        fn label(&self) -> Self::Output {
+            self.0
+        }
+    }
+
+    #[test]
+    fn duplicate_labels_are_rejected() {
+        let mut registry = Registry::new();
+        let first = registry.insert(Named("First")).unwrap();
+        assert!(matches!(
+            registry.insert(Named("First")),
+            Err(GlError::Resource(_))
+        ));
+        assert_eq!(registry.len(), 1);
+        assert_eq!(registry[first], Named("First"));
+    }
+
+    #[test]
+    fn removed_handles_stay_invalid_after_reuse() {
+        let mut registry = Registry::new();
+        let first = registry.insert(Named("First")).unwrap();
+        let second = registry.insert(Named("Second")).unwrap();
+        assert_eq!(registry.remove(first), Some(Named("First")));
+        assert_eq!(registry.remove(first), None);
+        assert!(!registry.contains(first));
+        assert!(registry.find("First").is_none());
+
+        // The freed slot is reused with a new generation, and the label can be taken again.
+        let third = registry.insert(Named("First")).unwrap();
+        assert_eq!(third.index, first.index);
+        assert_ne!(third, first);
+        assert!(registry.get(first).is_none());
+        assert_eq!(registry.get(third), Some(&Named("First")));
+        assert_eq!(registry[second], Named("Second"));
+    }
+
+    #[test]
+    fn resources_are_found_and_iterated() {
+        let mut registry = Registry::new();
+        assert!(registry.is_empty());
+        let handles: Vec<_> = ["A", "B", "C"]
+            .into_iter()
+            .map(|label| registry.insert(Named(label)).unwrap())
+            .collect();
+        assert_eq!(registry.find("B"), Some(handles[1]));
+        assert_eq!(registry.get_by_label("C"), Some(&Named("C")));
+        assert!(registry.find("D").is_none());
+
+        registry.remove(handles[1]);
+        let labels: Vec<_> = registry.iter().map(|(_, resource)| resource.0).collect();
+        assert_eq!(labels, ["A", "C"]);
+        let visited: Vec<_> = registry.iter_mut().map(|(handle, _)| handle).collect();
+        assert_eq!(visited, [handles[0], handles[2]]);
+    }
+
+    #[cfg(unix)]
+    mod headless {
+        use super::*;
+        use crate::{
+            context::headless::HeadlessContext, glenums::BufferTarget, memory::BufferBackend,
+        };
+
+        #[test]
+        fn vertex_arrays_are_inserted_with_their_buffers() {
+            let context = HeadlessContext::for_tests();
+            let gl = context.gl();
+            let buffer = |label| {
+                BufferBackend::Stateful
+                    .create(gl.clone(), BufferTarget::Array, label)
+                    .unwrap()
+            };
+            let vertex_array =
+                |vbo, label| VertexArray::new(gl.clone(), vbo, None, &[], label).unwrap();
+
+            let mut registry = ResourceRegistry::new();
+            let shared = buffer("Vertices");
+            registry
+                .insert_vertex_array(vertex_array(shared.clone(), "First"))
+                .unwrap();
+            // Sharing a registered buffer is fine.
+            registry
+                .insert_vertex_array(vertex_array(shared, "Second"))
+                .unwrap();
+            assert_eq!(registry.buffers.len(), 1);
+
+            // A different buffer with a taken label registers nothing.
+            let result = registry.insert_vertex_array(vertex_array(buffer("Vertices"), "Third"));
+            assert!(matches!(result, Err(GlError::Resource(_))));
+            assert!(registry.vertex_arrays.find("Third").is_none());
+
+            // A taken vertex array label doesn't register its new buffers either.
+            let result = registry.insert_vertex_array(vertex_array(buffer("Other"), "First"));
+            assert!(matches!(result, Err(GlError::Resource(_))));
+            assert!(registry.buffers.find("Other").is_none());
+            assert_eq!(registry.vertex_arrays.len(), 2);
+        }
+    }
+}
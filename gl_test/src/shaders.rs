@@ -1,7 +1,32 @@
+mod computeprogram;
 pub mod datatypes;
+mod diagnostics;
+#[cfg(target_os = "linux")]
+mod hotreload;
+mod preprocessor;
+mod programcache;
+mod programpipeline;
+mod reflection;
 mod shader;
 mod shaderprogram;
+mod spirv;
+mod uniform;
+mod variants;
 
+#[allow(unused_imports)]
+pub use computeprogram::ComputeProgram;
+pub use diagnostics::{DiagnosticSeverity, ShaderDiagnostic};
+#[cfg(target_os = "linux")]
+pub use hotreload::ShaderReloader;
+pub use preprocessor::ShaderOptions;
+pub use programcache::ProgramCache;
+#[allow(unused_imports)]
+pub use programpipeline::ProgramPipeline;
+pub use reflection::{ProgramInterface, ProgramVariable};
 pub(super) use shader::Shader;
 pub use shader::{ShaderDescriptor, ShaderFrom, ShaderKind};
 pub use shaderprogram::ShaderProgram;
+pub use spirv::{SpirvBinary, SpirvModule};
+pub use uniform::Uniform;
+#[allow(unused_imports)]
+pub use variants::ShaderVariants;
@@ -0,0 +1,259 @@
+use super::{Shader, ShaderDescriptor, ShaderKind, ShaderProgram};
+use crate::{
+    context::{
+        gl::{
+            self,
+            types::{GLint, GLintptr},
+        },
+        Gl,
+    },
+    glenums::{BufferTarget, MemoryBarrier},
+    glerror::GlError,
+    label::Label,
+    memory::GpuBuffer,
+};
+use std::rc::Rc;
+
+/// Size in bytes of the `num_groups_x`, `num_groups_y` and `num_groups_z` integers read by
+/// [glDispatchComputeIndirect](https://docs.gl/gl4/glDispatchComputeIndirect).
+const INDIRECT_COMMAND_SIZE: usize = 3 * std::mem::size_of::<u32>();
+
+/// Program with a single compute shader.
+///
+/// Compute shaders don't take part in drawing. Instead they're dispatched over a grid of work
+/// groups and communicate through storage buffers and images. Requires OpenGL 4.3.
+#[allow(dead_code)]
+pub struct ComputeProgram {
+    gl: Rc<Gl>,
+    program: ShaderProgram,
+    // Declared by the shader with `layout(local_size_x = ...) in;`
+    work_group_size: [u32; 3],
+    // Implementation limit of work groups per dispatch
+    max_work_groups: [u32; 3],
+}
+
+#[allow(dead_code)]
+impl ComputeProgram {
+    /// Compile and link a compute shader.
+    pub fn new<S>(gl: Rc<Gl>, descriptor: ShaderDescriptor, label: S) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let label = label.into();
+        if descriptor.kind != ShaderKind::Compute {
+            return Err(GlError::ShaderProgram(format!(
+                "Compute program '{label}' can't be created from a {} shader",
+                descriptor.kind
+            )));
+        }
+
+        let shader = Shader::new(gl.clone(), descriptor)?;
+        let program = ShaderProgram::from_shaders(gl.clone(), &[shader], label)?;
+
+        let mut work_group_size: [GLint; 3] = [0; 3];
+        unsafe {
+            gl.GetProgramiv(
+                program.id(),
+                gl::COMPUTE_WORK_GROUP_SIZE,
+                work_group_size.as_mut_ptr(),
+            )
+        }
+
+        let mut max_work_groups: [GLint; 3] = [0; 3];
+        for (axis, max) in max_work_groups.iter_mut().enumerate() {
+            unsafe { gl.GetIntegeri_v(gl::MAX_COMPUTE_WORK_GROUP_COUNT, axis as _, max) }
+        }
+
+        Ok(Self {
+            gl,
+            program,
+            work_group_size: work_group_size.map(|size| size as u32),
+            max_work_groups: max_work_groups.map(|max| max as u32),
+        })
+    }
+
+    /// Linked program, e.g. to set uniforms or bind blocks.
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    /// Number of invocations in each work group along x, y and z.
+    pub fn work_group_size(&self) -> [u32; 3] {
+        self.work_group_size
+    }
+
+    /// Number of work groups needed to cover at least `invocations` along each axis.
+    pub fn work_groups_for(&self, invocations: [u32; 3]) -> [u32; 3] {
+        std::array::from_fn(|axis| invocations[axis].div_ceil(self.work_group_size[axis].max(1)))
+    }
+
+    /// Run the shader over a grid of `work_groups` with
+    /// [glDispatchCompute](https://docs.gl/gl4/glDispatchCompute).
+    ///
+    /// Writes to buffers and images aren't visible to later commands until a
+    /// [memory_barrier](#method.memory_barrier) is issued.
+    pub fn dispatch(&self, work_groups: [u32; 3]) -> Result<(), GlError> {
+        let exceeded = work_groups
+            .iter()
+            .zip(self.max_work_groups)
+            .any(|(&groups, max)| groups > max);
+        if exceeded {
+            return Err(GlError::Limit(format!(
+                "Dispatch of {work_groups:?} work groups for '{}' exceeds the limit of {:?}",
+                self.label(),
+                self.max_work_groups
+            )));
+        }
+
+        self.program.set_used();
+        let [x, y, z] = work_groups;
+        unsafe { self.gl.DispatchCompute(x, y, z) }
+        Ok(())
+    }
+
+    /// Run the shader with the work group counts stored in `buffer` at `offset` bytes with
+    /// [glDispatchComputeIndirect](https://docs.gl/gl4/glDispatchComputeIndirect).
+    ///
+    /// The command is three `u32`s so a previous dispatch can size the next one. Issue
+    /// [MemoryBarrier::Command] after writing the command from a shader.
+    pub fn dispatch_indirect(&self, buffer: &dyn GpuBuffer, offset: usize) -> Result<(), GlError> {
+        if !offset.is_multiple_of(4) {
+            return Err(GlError::Buffer(format!(
+                "Indirect dispatch offset {offset} into '{}' isn't a multiple of four",
+                buffer.label()
+            )));
+        }
+        let size = buffer.size();
+        if offset
+            .checked_add(INDIRECT_COMMAND_SIZE)
+            .is_none_or(|end| end > size)
+        {
+            return Err(GlError::Buffer(format!(
+                "Indirect dispatch command at offset {offset} is out of bounds for '{}' which has {size} bytes",
+                buffer.label()
+            )));
+        }
+
+        self.program.set_used();
+        unsafe {
+            let mut previous: GLint = 0;
+            self.gl
+                .GetIntegerv(gl::DISPATCH_INDIRECT_BUFFER_BINDING, &mut previous);
+            self.gl
+                .BindBuffer(BufferTarget::DispatchIndirect.bits(), buffer.id());
+
+            self.gl.DispatchComputeIndirect(offset as GLintptr);
+
+            // Restore previous state
+            self.gl
+                .BindBuffer(BufferTarget::DispatchIndirect.bits(), previous as _);
+        }
+        Ok(())
+    }
+
+    /// Make the results of previous dispatches visible to operations that read them in the ways
+    /// given by `barriers`.
+    pub fn memory_barrier(&self, barriers: MemoryBarrier) {
+        self.gl.memory_barrier(barriers)
+    }
+}
+
+impl Label for ComputeProgram {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.program.label()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        context::headless::HeadlessContext,
+        glenums::{BufferUsage, MemoryBarrier},
+        memory::{BufferBackend, StorageBuffer},
+        shaders::ShaderFrom,
+    };
+
+    fn sequence(gl: Rc<Gl>) -> ComputeProgram {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../assets/shaders/sequence.comp"
+        );
+        ComputeProgram::new(
+            gl,
+            ShaderDescriptor::new(ShaderKind::Compute, ShaderFrom::FilePath(path.into())),
+            "Sequence",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn dispatches_are_read_back() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let program = sequence(gl.clone());
+        assert_eq!(program.work_group_size(), [64, 1, 1]);
+
+        // The last work group is partially out of bounds.
+        let values = StorageBuffer::<f32>::zeroed(gl, BufferBackend::Dsa, 100, "Values").unwrap();
+        values.bind(0);
+        program.program().set_uniform("Step", &0.5f32);
+        let work_groups = program.work_groups_for([100, 1, 1]);
+        assert_eq!(work_groups, [2, 1, 1]);
+        program.dispatch(work_groups).unwrap();
+        program.memory_barrier(MemoryBarrier::BufferUpdate);
+
+        let expected: Vec<_> = (0..100).map(|i| i as f32 * 0.5).collect();
+        assert_eq!(values.read_back().unwrap(), expected);
+    }
+
+    #[test]
+    fn indirect_dispatches_read_their_command() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let program = sequence(gl.clone());
+        program.program().set_uniform("Step", &1.0f32);
+        let values =
+            StorageBuffer::<f32>::zeroed(gl.clone(), BufferBackend::Dsa, 128, "Values").unwrap();
+        values.bind(0);
+
+        // One work group only covers the first half.
+        let command = BufferBackend::Dsa
+            .create(gl, BufferTarget::DispatchIndirect, "Command")
+            .unwrap();
+        command
+            .write_bytes(
+                bytemuck::cast_slice(&[0u32, 1, 1, 1]),
+                BufferUsage::StaticDraw,
+            )
+            .unwrap();
+        assert!(program.dispatch_indirect(&*command, 2).is_err());
+        assert!(program.dispatch_indirect(&*command, 8).is_err());
+        program.dispatch_indirect(&*command, 4).unwrap();
+        program.memory_barrier(MemoryBarrier::BufferUpdate);
+
+        let expected: Vec<_> = (0..128)
+            .map(|i| if i < 64 { i as f32 } else { 0.0 })
+            .collect();
+        assert_eq!(values.read_back().unwrap(), expected);
+    }
+
+    #[test]
+    fn dispatches_over_the_limit_are_rejected() {
+        let context = HeadlessContext::for_tests();
+        let program = sequence(context.gl());
+        let [x, _, _] = program.max_work_groups;
+        assert!(matches!(
+            program.dispatch([x.saturating_add(1), 1, 1]),
+            Err(GlError::Limit(_))
+        ));
+
+        let vertex = ShaderDescriptor::new(
+            ShaderKind::Vertex,
+            ShaderFrom::Source("#version 330 core\nvoid main() {}".into()),
+        );
+        assert!(ComputeProgram::new(context.gl(), vertex, "NotCompute").is_err());
+    }
+}
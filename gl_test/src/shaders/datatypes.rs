@@ -4,6 +4,6 @@ mod rectangle;
 mod triangle;
 mod vertex;
 
-pub use rectangle::Rectangle;
+pub use rectangle::{Rectangle, TexturedRectangle};
 pub use triangle::Triangle;
-pub use vertex::{Vertex, Vertex3};
+pub use vertex::{TexturedVertex3, Vertex, Vertex3};
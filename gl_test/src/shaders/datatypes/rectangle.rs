@@ -2,7 +2,7 @@ use std::mem::size_of;
 
 use crate::{
     memory::{GpuData, GpuDataIndices, GpuDataVerts, Layout},
-    shaders::datatypes::Vertex3,
+    shaders::datatypes::{TexturedVertex3, Vertex3},
 };
 
 /// Indices for element array buffers.
@@ -71,16 +71,17 @@ impl GpuDataVerts<2> for Rectangle {
     }
 
     fn memory_layout(&self) -> [Layout; 2] {
+        // Position and color are both three f32
         [
             Layout {
                 index: 0,
-                size: 4,
+                size: 3,
                 stride: self.stride(),
                 start: 0,
             },
             Layout {
                 index: 1,
-                size: 4,
+                size: 3,
                 stride: self.stride(),
                 start: Vertex3::size_position(),
             },
@@ -95,3 +96,60 @@ impl GpuDataIndices<RectangleIndices, 2> for Rectangle {
         }
     }
 }
+
+/// Rectangle with texture coordinates that map the full texture onto it.
+#[derive(Clone, Copy, Debug)]
+#[repr(C, packed)]
+pub struct TexturedRectangle {
+    vertices: [TexturedVertex3; 4],
+}
+
+impl Default for TexturedRectangle {
+    fn default() -> Self {
+        // White so the texture's colors are unchanged
+        let white = [1.0, 1.0, 1.0];
+        Self {
+            vertices: [
+                // Top right
+                TexturedVertex3::new([0.5, 0.5, 0.0], white, [1.0, 1.0]),
+                // Bottom right (hypotenuse)
+                TexturedVertex3::new([0.5, -0.5, 0.0], white, [1.0, 0.0]),
+                // Top left (hypotenuse)
+                TexturedVertex3::new([-0.5, 0.5, 0.0], white, [0.0, 1.0]),
+                // Bottom left
+                TexturedVertex3::new([-0.5, -0.5, 0.0], white, [0.0, 0.0]),
+            ],
+        }
+    }
+}
+
+impl GpuData for TexturedRectangle {
+    type Data = [TexturedVertex3; 4];
+
+    fn as_ptr(&self) -> *const Self::Data {
+        std::ptr::addr_of!(self.vertices)
+    }
+
+    fn size_total(&self) -> usize {
+        size_of::<Self::Data>()
+    }
+}
+
+impl GpuDataVerts<3> for TexturedRectangle {
+    fn stride(&self) -> usize {
+        self.vertices[0].stride()
+    }
+
+    fn memory_layout(&self) -> [Layout; 3] {
+        // Every vertex has the same layout
+        self.vertices[0].memory_layout()
+    }
+}
+
+impl GpuDataIndices<RectangleIndices, 3> for TexturedRectangle {
+    fn indices(&self) -> RectangleIndices {
+        RectangleIndices {
+            indices: [0, 1, 2, 1, 2, 3],
+        }
+    }
+}
@@ -66,16 +66,93 @@ impl<const P: usize, const C: usize> GpuDataVerts<2> for Vertex<P, C> {
         [
             Layout {
                 index: 0,
-                size: 3,
+                size: P,
                 stride: self.stride(),
                 start: 0,
             },
             Layout {
                 index: 1,
-                size: 3,
+                size: C,
                 stride: self.stride(),
                 start: Self::size_position(),
             },
         ]
     }
 }
+
+/// Colored vertex with texture coordinates.
+#[derive(Clone, Copy, Debug)]
+#[repr(C, packed)]
+pub struct TexturedVertex<const P: usize, const C: usize, const T: usize> {
+    position: [f32; P],
+    color: [f32; C],
+    uv: [f32; T],
+}
+
+/// Convenience type for a 3D vertex with RGB colors and 2D texture coordinates.
+pub type TexturedVertex3 = TexturedVertex<3, 3, 2>;
+
+impl<const P: usize, const C: usize, const T: usize> TexturedVertex<P, C, T> {
+    pub fn new(position: [f32; P], color: [f32; C], uv: [f32; T]) -> Self {
+        Self {
+            position,
+            color,
+            uv,
+        }
+    }
+
+    /// Size of the position component
+    #[inline]
+    pub const fn size_position() -> usize {
+        size_of::<f32>() * P
+    }
+
+    /// Size of the color component
+    #[inline]
+    pub const fn size_color() -> usize {
+        size_of::<f32>() * C
+    }
+}
+
+impl<const P: usize, const C: usize, const T: usize> GpuData for TexturedVertex<P, C, T> {
+    type Data = f32;
+
+    fn as_ptr(&self) -> *const Self::Data {
+        std::ptr::addr_of!(*self) as _
+    }
+
+    fn size_total(&self) -> usize {
+        self.stride()
+    }
+}
+
+impl<const P: usize, const C: usize, const T: usize> GpuDataVerts<3> for TexturedVertex<P, C, T> {
+    /// Stride value of all components
+    #[inline]
+    fn stride(&self) -> usize {
+        size_of::<f32>() * (P + C + T)
+    }
+
+    fn memory_layout(&self) -> [Layout; 3] {
+        [
+            Layout {
+                index: 0,
+                size: P,
+                stride: self.stride(),
+                start: 0,
+            },
+            Layout {
+                index: 1,
+                size: C,
+                stride: self.stride(),
+                start: Self::size_position(),
+            },
+            Layout {
+                index: 2,
+                size: T,
+                stride: self.stride(),
+                start: Self::size_position() + Self::size_color(),
+            },
+        ]
+    }
+}
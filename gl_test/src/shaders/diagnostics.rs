@@ -0,0 +1,452 @@
+use std::fmt;
+
+/// How serious a [ShaderDiagnostic] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for DiagnosticSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DiagnosticSeverity::Error => write!(f, "error"),
+            DiagnosticSeverity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Error or warning from a shader's info log.
+///
+/// Lines are mapped back to the file they came from with the `#line` directives of the
+/// preprocessor. Columns are reported by some drivers only and count from the start of the line
+/// after macros are expanded, so they're approximate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderDiagnostic {
+    pub severity: DiagnosticSeverity,
+    /// File or source string the diagnostic points at, if the driver said
+    pub file: Option<String>,
+    /// Line starting at 1
+    pub line: Option<u32>,
+    /// Column starting at 1
+    pub column: Option<u32>,
+    pub message: String,
+    /// Source code of the line
+    pub snippet: Option<String>,
+}
+
+impl fmt::Display for ShaderDiagnostic {
+    /// Render like rustc with the offending line underneath.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)?;
+
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let (line, location) = match (self.line, self.column) {
+            (Some(line), Some(column)) => (line, format!("{file}:{line}:{column}")),
+            (Some(line), None) => (line, format!("{file}:{line}")),
+            _ => return write!(f, "\n --> {file}"),
+        };
+        let Some(snippet) = &self.snippet else {
+            return write!(f, "\n --> {location}");
+        };
+
+        let gutter = line.to_string().len();
+        let blank = "";
+        write!(f, "\n{blank:gutter$}--> {location}")?;
+        write!(f, "\n{blank:gutter$} |")?;
+        write!(f, "\n{line} | {snippet}")?;
+        if let Some(column) = self.column {
+            // Leave out carets that would point past the end of the line.
+            let offset = column.saturating_sub(1) as usize;
+            if offset <= snippet.chars().count() {
+                let padding: String = snippet
+                    .chars()
+                    .take(offset)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                write!(f, "\n{blank:gutter$} | {padding}^")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a shader info log. `names` and `contents` are the names and text of each source string
+/// number used by `#line` directives.
+///
+/// Understands the formats of Mesa (`0:3(9): error: ...`), NVIDIA (`0(3) : error C1008: ...`)
+/// and AMD (`ERROR: 0:3: ...`). Lines that don't match continue the previous diagnostic or are
+/// kept as errors without a location.
+pub(super) fn parse(log: &str, names: &[String], contents: &[String]) -> Vec<ShaderDiagnostic> {
+    let mut diagnostics: Vec<ShaderDiagnostic> = Vec::new();
+
+    for line in log.lines() {
+        // Logs may be padded with NULs or spaces up to their reported length.
+        let line = line.trim_end_matches(|c: char| c == '\0' || c.is_whitespace());
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some(entry) = parse_mesa(line)
+            .or_else(|| parse_nvidia(line))
+            .or_else(|| parse_amd(line))
+        else {
+            match diagnostics.last_mut() {
+                Some(previous) if line.starts_with(char::is_whitespace) => {
+                    previous.message.push('\n');
+                    previous.message.push_str(line.trim());
+                }
+                _ => diagnostics.push(unlocated(line)),
+            }
+            continue;
+        };
+
+        let string = entry.string as usize;
+        let snippet = contents.get(string).and_then(|text| {
+            let line = (entry.line as usize).checked_sub(1)?;
+            text.lines().nth(line).map(str::to_owned)
+        });
+        diagnostics.push(ShaderDiagnostic {
+            severity: entry.severity,
+            file: Some(
+                names
+                    .get(string)
+                    .cloned()
+                    .unwrap_or_else(|| format!("<string {string}>")),
+            ),
+            line: Some(entry.line),
+            column: entry.column,
+            message: entry.message.into(),
+            snippet,
+        });
+    }
+    diagnostics
+}
+
+/// Located diagnostic before it's resolved against the source strings.
+struct Entry<'a> {
+    severity: DiagnosticSeverity,
+    string: u32,
+    line: u32,
+    column: Option<u32>,
+    message: &'a str,
+}
+
+/// `0:3(9): error: message`
+fn parse_mesa(line: &str) -> Option<Entry<'_>> {
+    let (string, rest) = line.split_once(':')?;
+    let (line_number, rest) = rest.split_once('(')?;
+    let (column, rest) = rest.split_once("): ")?;
+    let (severity, message) = rest.split_once(": ")?;
+    Some(Entry {
+        severity: severity_of(severity)?,
+        string: string.parse().ok()?,
+        line: line_number.parse().ok()?,
+        column: Some(column.parse().ok()?),
+        message,
+    })
+}
+
+/// `0(3) : error C1008: message`
+fn parse_nvidia(line: &str) -> Option<Entry<'_>> {
+    let (string, rest) = line.split_once('(')?;
+    let (line_number, rest) = rest.split_once(") : ")?;
+    let (severity, message) = rest.split_once(' ')?;
+    Some(Entry {
+        severity: severity_of(severity)?,
+        string: string.parse().ok()?,
+        line: line_number.parse().ok()?,
+        column: None,
+        message,
+    })
+}
+
+/// `ERROR: 0:3: message`
+fn parse_amd(line: &str) -> Option<Entry<'_>> {
+    let (severity, rest) = line.split_once(": ")?;
+    let (string, rest) = rest.split_once(':')?;
+    let (line_number, message) = rest.split_once(':')?;
+    Some(Entry {
+        severity: severity_of(severity)?,
+        string: string.parse().ok()?,
+        line: line_number.parse().ok()?,
+        column: None,
+        message: message.trim_start(),
+    })
+}
+
+/// Diagnostic without a location, e.g. `error: message` or a driver summary line.
+fn unlocated(line: &str) -> ShaderDiagnostic {
+    let (severity, message) = line
+        .split_once(": ")
+        .and_then(|(severity, message)| Some((severity_of(severity)?, message)))
+        .unwrap_or((DiagnosticSeverity::Error, line));
+    ShaderDiagnostic {
+        severity,
+        file: None,
+        line: None,
+        column: None,
+        message: message.into(),
+        snippet: None,
+    }
+}
+
+/// Severity from e.g. `error`, `ERROR` or Mesa's `preprocessor error`.
+fn severity_of(words: &str) -> Option<DiagnosticSeverity> {
+    let word = words.rsplit(' ').next()?;
+    match word.to_ascii_lowercase().as_str() {
+        "error" => Some(DiagnosticSeverity::Error),
+        "warning" => Some(DiagnosticSeverity::Warning),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn located(
+        severity: DiagnosticSeverity,
+        file: &str,
+        line: u32,
+        column: Option<u32>,
+        message: &str,
+        snippet: Option<&str>,
+    ) -> ShaderDiagnostic {
+        ShaderDiagnostic {
+            severity,
+            file: Some(file.into()),
+            line: Some(line),
+            column,
+            message: message.into(),
+            snippet: snippet.map(Into::into),
+        }
+    }
+
+    fn sources() -> (Vec<String>, Vec<String>) {
+        (
+            vec!["main.frag".into(), "common.glsl".into()],
+            vec![
+                "#version 330 core\nout vec4 FragColor;\nvoid main() { FragColor = x; }".into(),
+                "float half(float x) { return x / 2.0 }".into(),
+            ],
+        )
+    }
+
+    #[test]
+    fn driver_formats_are_parsed() {
+        let (names, contents) = sources();
+        let main = "void main() { FragColor = x; }";
+
+        let mesa = "0:3(27): error: `x' undeclared\n\
+                    1:1(39): preprocessor error: syntax error, unexpected '}'\n\
+                    0:2(1): warning: unused output";
+        assert_eq!(
+            parse(mesa, &names, &contents),
+            [
+                located(
+                    DiagnosticSeverity::Error,
+                    "main.frag",
+                    3,
+                    Some(27),
+                    "`x' undeclared",
+                    Some(main)
+                ),
+                located(
+                    DiagnosticSeverity::Error,
+                    "common.glsl",
+                    1,
+                    Some(39),
+                    "syntax error, unexpected '}'",
+                    Some("float half(float x) { return x / 2.0 }")
+                ),
+                located(
+                    DiagnosticSeverity::Warning,
+                    "main.frag",
+                    2,
+                    Some(1),
+                    "unused output",
+                    Some("out vec4 FragColor;")
+                ),
+            ]
+        );
+
+        let nvidia = "0(3) : error C1008: undefined variable \"x\"";
+        assert_eq!(
+            parse(nvidia, &names, &contents),
+            [located(
+                DiagnosticSeverity::Error,
+                "main.frag",
+                3,
+                None,
+                "C1008: undefined variable \"x\"",
+                Some(main)
+            )]
+        );
+
+        let amd = "ERROR: 0:3: 'x' : undeclared identifier\n\
+                   WARNING: 1:1: 'half' : function is unused\n\
+                   ERROR: 1 compilation errors.  No code generated.";
+        assert_eq!(
+            parse(amd, &names, &contents),
+            [
+                located(
+                    DiagnosticSeverity::Error,
+                    "main.frag",
+                    3,
+                    None,
+                    "'x' : undeclared identifier",
+                    Some(main)
+                ),
+                located(
+                    DiagnosticSeverity::Warning,
+                    "common.glsl",
+                    1,
+                    None,
+                    "'half' : function is unused",
+                    Some("float half(float x) { return x / 2.0 }")
+                ),
+                unlocated("ERROR: 1 compilation errors.  No code generated."),
+            ]
+        );
+        assert_eq!(
+            parse(amd, &names, &contents)[2].message,
+            "1 compilation errors.  No code generated."
+        );
+    }
+
+    #[test]
+    fn unmatched_lines_continue_or_stand_alone() {
+        let (names, contents) = sources();
+        let log = "0:3(27): error: `x' undeclared\n\
+                   \x20   did you mean `y'?\n\
+                   \tdeclared in an outer scope\n\
+                   linking failed\n\
+                   warning: extension not supported";
+        let diagnostics = parse(log, &names, &contents);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(
+            diagnostics[0].message,
+            "`x' undeclared\ndid you mean `y'?\ndeclared in an outer scope"
+        );
+
+        let standalone = |severity, message: &str| ShaderDiagnostic {
+            severity,
+            file: None,
+            line: None,
+            column: None,
+            message: message.into(),
+            snippet: None,
+        };
+        // Lines without a severity are errors.
+        assert_eq!(
+            diagnostics[1],
+            standalone(DiagnosticSeverity::Error, "linking failed")
+        );
+        assert_eq!(
+            diagnostics[2],
+            standalone(DiagnosticSeverity::Warning, "extension not supported")
+        );
+
+        // Indented lines without a previous diagnostic aren't dropped.
+        assert_eq!(parse("  stray", &names, &contents).len(), 1);
+    }
+
+    #[test]
+    fn padding_and_unknown_strings_are_handled() {
+        let (names, contents) = sources();
+        // Logs are padded with NULs up to the length the driver reported.
+        let padded = "0:2(1): error: padded\0\0\0\n\0\0\0\0\n   \n";
+        assert_eq!(
+            parse(padded, &names, &contents),
+            [located(
+                DiagnosticSeverity::Error,
+                "main.frag",
+                2,
+                Some(1),
+                "padded",
+                Some("out vec4 FragColor;")
+            )]
+        );
+
+        // Source strings past the end are named by number and have no snippet, like lines past
+        // the end of the source or line 0.
+        let log = "5:1(1): error: unknown string\n0:9(1): error: past the end\n0:0(1): error: zero";
+        let diagnostics = parse(log, &names, &contents);
+        assert_eq!(diagnostics[0].file.as_deref(), Some("<string 5>"));
+        assert!(diagnostics.iter().all(|d| d.snippet.is_none()));
+        assert_eq!(diagnostics[2].line, Some(0));
+        assert!(parse("", &names, &contents).is_empty());
+    }
+
+    #[test]
+    fn severities_are_case_insensitive_last_words() {
+        assert_eq!(severity_of("error"), Some(DiagnosticSeverity::Error));
+        assert_eq!(severity_of("ERROR"), Some(DiagnosticSeverity::Error));
+        assert_eq!(
+            severity_of("preprocessor error"),
+            Some(DiagnosticSeverity::Error)
+        );
+        assert_eq!(severity_of("Warning"), Some(DiagnosticSeverity::Warning));
+        assert_eq!(severity_of("note"), None);
+        assert_eq!(severity_of("error C1008"), None);
+    }
+
+    #[test]
+    fn carets_point_at_the_column() {
+        let diagnostic = |column, snippet: &str| {
+            located(
+                DiagnosticSeverity::Error,
+                "main.frag",
+                12,
+                column,
+                "`x' undeclared",
+                Some(snippet),
+            )
+        };
+
+        assert_eq!(
+            diagnostic(Some(5), "x = y;").to_string(),
+            "error: `x' undeclared\n  --> main.frag:12:5\n   |\n12 | x = y;\n   |     ^"
+        );
+        // Tabs are kept so the caret lines up with the snippet.
+        assert_eq!(
+            diagnostic(Some(3), "\tx = y;").to_string(),
+            "error: `x' undeclared\n  --> main.frag:12:3\n   |\n12 | \tx = y;\n   | \t ^"
+        );
+        // Columns just past the end point after the last character, further ones are left out.
+        assert!(diagnostic(Some(7), "x = y;")
+            .to_string()
+            .ends_with("   |       ^"));
+        assert!(diagnostic(Some(8), "x = y;")
+            .to_string()
+            .ends_with("12 | x = y;"));
+        // Without a column the snippet is shown alone.
+        assert_eq!(
+            diagnostic(None, "x = y;").to_string(),
+            "error: `x' undeclared\n  --> main.frag:12\n   |\n12 | x = y;"
+        );
+    }
+
+    #[test]
+    fn missing_locations_are_left_out() {
+        let mut diagnostic = located(
+            DiagnosticSeverity::Warning,
+            "main.frag",
+            2,
+            Some(1),
+            "unused",
+            None,
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "warning: unused\n --> main.frag:2:1"
+        );
+        diagnostic.line = None;
+        assert_eq!(diagnostic.to_string(), "warning: unused\n --> main.frag");
+        diagnostic.file = None;
+        assert_eq!(diagnostic.to_string(), "warning: unused");
+    }
+}
@@ -0,0 +1,273 @@
+use super::{Shader, ShaderDescriptor, ShaderFrom, ShaderProgram, SpirvBinary};
+use crate::{context::Gl, glerror::GlError, label::Label};
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+use log::{error, info, warn};
+use std::{
+    collections::{HashMap, HashSet},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// Recompiles programs whose shader files changed on disk.
+///
+/// Files are watched with inotify. Changes are collected with [poll](#method.poll) and applied
+/// with [reload](#method.reload), usually once per frame. A program that fails to compile or link
+/// keeps running its previous version.
+///
+/// Directories are watched rather than files because many editors save by replacing the file.
+pub struct ShaderReloader {
+    gl: Rc<Gl>,
+    inotify: Inotify,
+    // Watched directories
+    dirs: HashMap<WatchDescriptor, PathBuf>,
+    // Labels of the programs that use each file
+    files: HashMap<PathBuf, HashSet<Rc<str>>>,
+    // Shaders of each watched program
+    programs: HashMap<Rc<str>, Vec<ShaderDescriptor>>,
+    // Programs with changed files that haven't been reloaded yet
+    pending: HashSet<Rc<str>>,
+}
+
+impl ShaderReloader {
+    pub fn new(gl: Rc<Gl>) -> Result<Self, GlError> {
+        let inotify = Inotify::init()
+            .map_err(|e| GlError::Resource(format!("Failed to initialize inotify: {e}")))?;
+
+        Ok(Self {
+            gl,
+            inotify,
+            dirs: HashMap::new(),
+            files: HashMap::new(),
+            programs: HashMap::new(),
+            pending: HashSet::new(),
+        })
+    }
+
+    /// Reload `program` from `raw_shaders` whenever one of their files changes.
+    ///
+    /// Programs are identified by their label, so `raw_shaders` should be the descriptors that the
+    /// program was created from. Shaders from source strings are reused as is, but files they
+    /// include are watched.
+    pub fn watch<I>(&mut self, program: &ShaderProgram, raw_shaders: I) -> Result<(), GlError>
+    where
+        I: IntoIterator<Item = ShaderDescriptor>,
+    {
+        let label = program.label();
+        let descriptors: Vec<_> = raw_shaders.into_iter().collect();
+        self.watch_files(&label, &descriptors)?;
+        self.programs.insert(label, descriptors);
+        Ok(())
+    }
+
+    /// Collect file changes without blocking.
+    ///
+    /// Returns true if any watched program needs to be reloaded.
+    pub fn poll(&mut self) -> bool {
+        let mut buffer = [0; 4096];
+        loop {
+            let events = match self.inotify.read_events(&mut buffer) {
+                Ok(events) => events,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    error!("Failed to read shader file changes: {e}");
+                    break;
+                }
+            };
+
+            for event in events {
+                // Events were dropped so any file may have changed.
+                if event.mask.contains(EventMask::Q_OVERFLOW) {
+                    warn!("Missed shader file changes, reloading every program");
+                    self.pending.extend(self.programs.keys().cloned());
+                    continue;
+                }
+
+                let (Some(dir), Some(name)) = (self.dirs.get(&event.wd), event.name) else {
+                    continue;
+                };
+                if let Some(labels) = self.files.get(&dir.join(name)) {
+                    self.pending.extend(labels.iter().cloned());
+                }
+            }
+        }
+
+        !self.pending.is_empty()
+    }
+
+    /// Recompile and relink `program` in place if one of its files changed.
+    ///
+    /// The program's id changes, so uniform values must be set again. Uniform block bindings are
+    /// carried over. Returns true if the program was replaced.
+    pub fn reload(&mut self, program: &mut ShaderProgram) -> bool {
+        let label = program.label();
+        if !self.pending.remove(&label) {
+            return false;
+        }
+        let Some(descriptors) = self.programs.get(&label) else {
+            return false;
+        };
+
+        info!("Reloading shader program '{label}'");
+        match ShaderProgram::from_raw(self.gl.clone(), descriptors.clone(), label.clone()) {
+            Ok(reloaded) => {
+                program.replace(reloaded);
+                // Includes may have been added or removed.
+                let descriptors = descriptors.clone();
+                if let Err(e) = self.watch_files(&label, &descriptors) {
+                    warn!("Failed to watch the files of '{label}': {e}");
+                }
+                true
+            }
+            Err(e) => {
+                error!("Keeping the previous version of '{label}': {e}");
+                false
+            }
+        }
+    }
+
+    /// Watch the files that `descriptors` read, including the files that they include, for the
+    /// program labelled `label`.
+    fn watch_files(
+        &mut self,
+        label: &Rc<str>,
+        descriptors: &[ShaderDescriptor],
+    ) -> Result<(), GlError> {
+        let mut paths = Vec::new();
+        for descriptor in descriptors {
+            match &descriptor.from {
+                ShaderFrom::Spirv(module) => {
+                    if let SpirvBinary::FilePath(path) = &module.binary {
+                        paths.push(path.clone());
+                    }
+                }
+                _ => {
+                    if let Some(preprocessed) = Shader::preprocess(descriptor)? {
+                        paths.extend(preprocessed.files);
+                    }
+                }
+            }
+        }
+
+        for labels in self.files.values_mut() {
+            labels.remove(label);
+        }
+        for path in paths {
+            let path = self.watch_file(&path)?;
+            if self
+                .files
+                .entry(path.clone())
+                .or_default()
+                .insert(label.clone())
+            {
+                info!("Watching {} for '{label}'", path.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// Watch the directory of `path` and return the file's absolute path.
+    fn watch_file(&mut self, path: &Path) -> Result<PathBuf, GlError> {
+        let path = path
+            .canonicalize()
+            .map_err(|e| GlError::Resource(format!("{e}\nPath: {}", path.to_string_lossy())))?;
+        let dir = path
+            .parent()
+            .expect("Canonical file paths have a parent")
+            .to_path_buf();
+
+        if !self.dirs.values().any(|watched| *watched == dir) {
+            let wd = self
+                .inotify
+                .watches()
+                .add(&dir, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)
+                .map_err(|e| {
+                    GlError::Resource(format!("Failed to watch {}: {e}", dir.to_string_lossy()))
+                })?;
+            self.dirs.insert(wd, dir);
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        context::{gl, headless::HeadlessContext},
+        shaders::ShaderKind,
+    };
+    use std::fs;
+
+    /// Vertex shader whose `Camera` block scales the position by `scale`.
+    fn vertex(scale: &str) -> String {
+        format!(
+            "#version 330 core
+            layout (std140) uniform Camera {{ mat4 ViewProjection; }};
+            void main() {{ gl_Position = ViewProjection * vec4({scale}); }}"
+        )
+    }
+
+    /// Binding point of the `Camera` block.
+    fn camera_binding(gl: &Gl, program: &ShaderProgram) -> i32 {
+        let mut binding = -1;
+        unsafe {
+            let index = gl.GetUniformBlockIndex(program.id(), b"Camera\0".as_ptr() as *const _);
+            gl.GetActiveUniformBlockiv(
+                program.id(),
+                index,
+                gl::UNIFORM_BLOCK_BINDING,
+                &mut binding,
+            );
+        }
+        binding
+    }
+
+    #[test]
+    fn changed_files_are_reloaded() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let dir = std::env::temp_dir().join(format!("gl_test-hotreload-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("reloaded.vert");
+        fs::write(&path, vertex("1.0")).unwrap();
+
+        let descriptors = [ShaderDescriptor::new(
+            ShaderKind::Vertex,
+            ShaderFrom::FilePath(path.clone()),
+        )];
+        let mut program =
+            ShaderProgram::from_raw(gl.clone(), descriptors.clone(), "Reloaded").unwrap();
+        program.bind_uniform_block("Camera", 3).unwrap();
+        let mut reloader = ShaderReloader::new(gl.clone()).unwrap();
+        reloader.watch(&program, descriptors).unwrap();
+        assert!(!reloader.poll());
+        assert!(!reloader.reload(&mut program));
+
+        fs::write(&path, vertex("2.0")).unwrap();
+        assert!(reloader.poll());
+        let id = program.id();
+        assert!(reloader.reload(&mut program));
+        assert_ne!(program.id(), id);
+        // The block binding carries over to the new program object.
+        assert_eq!(camera_binding(&gl, &program), 3);
+        // Changes are only applied once.
+        assert!(!reloader.poll());
+        assert!(!reloader.reload(&mut program));
+
+        // A broken version keeps the previous program.
+        fs::write(&path, "#version 330 core\nvoid main() { gl_Position = }").unwrap();
+        assert!(reloader.poll());
+        let id = program.id();
+        assert!(!reloader.reload(&mut program));
+        assert_eq!(program.id(), id);
+        assert_eq!(camera_binding(&gl, &program), 3);
+
+        // Fixing it reloads the program again.
+        fs::write(&path, vertex("3.0")).unwrap();
+        assert!(reloader.poll());
+        assert!(reloader.reload(&mut program));
+        assert_ne!(program.id(), id);
+    }
+}
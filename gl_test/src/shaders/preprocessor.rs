@@ -0,0 +1,373 @@
+use crate::glerror::GlError;
+use std::{
+    borrow::Cow,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Preprocessing applied to GLSL sources before they're compiled.
+///
+/// SPIR-V modules are already compiled so these are ignored for them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShaderOptions {
+    /// Replaces the source's `#version` line, e.g. `450 core`
+    pub version: Option<Cow<'static, str>>,
+    /// Macro names and values defined right after the `#version` line
+    pub defines: Vec<(Cow<'static, str>, Cow<'static, str>)>,
+    /// Directories searched for includes that aren't next to the including file
+    pub include_paths: Vec<PathBuf>,
+}
+
+/// GLSL source with includes resolved and options applied.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Preprocessed {
+    pub source: String,
+    /// File the main source was read from
+    pub origin: Option<PathBuf>,
+    /// Name of each source string number used by `#line`, starting with the main source
+    pub names: Vec<String>,
+    /// Original text of each source string number
+    pub contents: Vec<String>,
+    /// Every file that was read
+    pub files: Vec<PathBuf>,
+}
+
+/// Resolve `#include "file"` directives and apply `options` to `source`.
+///
+/// `path` is the file that `source` was read from, if any, which is where relative includes
+/// are looked up first. Each file is given its own source string number in `#line` directives so
+/// that the driver's errors point at the original file and line. Directives are handled line by
+/// line, so includes inside comments or disabled `#if` blocks are still resolved.
+pub(super) fn preprocess(
+    source: &str,
+    path: Option<&Path>,
+    options: &ShaderOptions,
+) -> Result<Preprocessed, GlError> {
+    let mut preprocessor = Preprocessor {
+        options,
+        out: String::with_capacity(source.len()),
+        names: Vec::new(),
+        contents: Vec::new(),
+        files: Vec::new(),
+        stack: Vec::new(),
+    };
+
+    match path {
+        Some(path) => {
+            let canonical = canonicalize(path)?;
+            preprocessor.stack.push(canonical.clone());
+            preprocessor.number(path, canonical, source);
+        }
+        None => {
+            preprocessor.names.push("<source>".into());
+            preprocessor.contents.push(source.into());
+            preprocessor.files.push(None);
+        }
+    }
+
+    let dir = path.and_then(Path::parent);
+    let has_version = source
+        .lines()
+        .any(|line| directive(line, "version").is_some());
+    if !has_version {
+        preprocessor.header(None, 1);
+    }
+    preprocessor.process(source, 0, dir)?;
+
+    Ok(Preprocessed {
+        source: preprocessor.out,
+        origin: path.map(Path::to_path_buf),
+        names: preprocessor.names,
+        contents: preprocessor.contents,
+        files: preprocessor.files.into_iter().flatten().collect(),
+    })
+}
+
+struct Preprocessor<'a> {
+    options: &'a ShaderOptions,
+    out: String,
+    names: Vec<String>,
+    contents: Vec<String>,
+    // Canonical path of each source string number if it was read from a file
+    files: Vec<Option<PathBuf>>,
+    // Files currently being included to detect cycles
+    stack: Vec<PathBuf>,
+}
+
+impl Preprocessor<'_> {
+    /// Copy `source`, which is source string number `index`, into the output.
+    fn process(&mut self, source: &str, index: usize, dir: Option<&Path>) -> Result<(), GlError> {
+        for (line_index, line) in source.lines().enumerate() {
+            let line_number = line_index + 1;
+
+            if let Some(version) = directive(line, "version") {
+                if index != 0 {
+                    return Err(self.error(index, line_number, "#version in an included file"));
+                }
+                self.header(Some(version), line_number + 1);
+            } else if let Some(include) = directive(line, "include") {
+                self.include(include, index, line_number, dir)?;
+                // Resume numbering the including file after the directive.
+                self.line(line_number + 1, index);
+            } else {
+                self.out.push_str(line);
+                self.out.push('\n');
+            }
+        }
+        Ok(())
+    }
+
+    /// Emit the version line and defines, then continue at `next_line` of the main source.
+    ///
+    /// `source_version` is the main source's own `#version` which the emitted line replaces.
+    fn header(&mut self, source_version: Option<&str>, next_line: usize) {
+        let version = self.options.version.as_deref().or(source_version);
+        if let Some(version) = version {
+            writeln!(self.out, "#version {version}").expect("Writing to a String can't fail");
+        }
+        for (name, value) in &self.options.defines {
+            writeln!(self.out, "#define {name} {value}").expect("Writing to a String can't fail");
+        }
+
+        // Only a version line that replaces the original keeps the numbering unchanged.
+        let inserted = source_version.is_none() && version.is_some();
+        if inserted || !self.options.defines.is_empty() {
+            self.line(next_line, 0);
+        }
+    }
+
+    /// Insert the contents of an included file.
+    fn include(
+        &mut self,
+        argument: &str,
+        index: usize,
+        line_number: usize,
+        dir: Option<&Path>,
+    ) -> Result<(), GlError> {
+        let name = argument
+            .strip_prefix('"')
+            .and_then(|argument| argument.strip_suffix('"'))
+            .ok_or_else(|| self.error(index, line_number, "expected #include \"file\""))?;
+
+        let path = dir
+            .into_iter()
+            .chain(self.options.include_paths.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                self.error(
+                    index,
+                    line_number,
+                    &format!("can't find included file \"{name}\""),
+                )
+            })?;
+        let canonical = canonicalize(&path)?;
+
+        if let Some(start) = self.stack.iter().position(|file| *file == canonical) {
+            let cycle: Vec<_> = self.stack[start..]
+                .iter()
+                .chain([&canonical])
+                .map(|file| file.to_string_lossy())
+                .collect();
+            return Err(self.error(
+                index,
+                line_number,
+                &format!("include cycle {}", cycle.join(" -> ")),
+            ));
+        }
+
+        let source = fs::read_to_string(&path)
+            .map_err(|e| GlError::Shader(format!("{e}\nPath: {}", path.to_string_lossy())))?;
+
+        let included = self.number(&path, canonical.clone(), &source);
+        self.stack.push(canonical);
+        self.line(1, included);
+        self.process(&source, included, path.parent())?;
+        self.stack.pop();
+        Ok(())
+    }
+
+    /// Source string number of a file. Files included more than once keep their first number.
+    fn number(&mut self, path: &Path, canonical: PathBuf, source: &str) -> usize {
+        let file = Some(canonical);
+        if let Some(number) = self.files.iter().position(|known| *known == file) {
+            return number;
+        }
+        self.names.push(path.to_string_lossy().into_owned());
+        self.contents.push(source.into());
+        self.files.push(file);
+        self.names.len() - 1
+    }
+
+    /// Make the next line `line` of source string `index`.
+    fn line(&mut self, line: usize, index: usize) {
+        writeln!(self.out, "#line {line} {index}").expect("Writing to a String can't fail");
+    }
+
+    fn error(&self, index: usize, line: usize, message: &str) -> GlError {
+        GlError::Shader(format!("{}:{line}: {message}", self.names[index]))
+    }
+}
+
+/// Argument of a preprocessor directive such as `#include "file"` if `line` is that directive.
+fn directive<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let argument = rest.strip_prefix(name)?;
+    // Don't match longer names, e.g. #include_next
+    if argument.is_empty() || argument.starts_with(char::is_whitespace) {
+        Some(argument.trim())
+    } else {
+        None
+    }
+}
+
+fn canonicalize(path: &Path) -> Result<PathBuf, GlError> {
+    path.canonicalize()
+        .map_err(|e| GlError::Shader(format!("{e}\nPath: {}", path.to_string_lossy())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty directory for a test's shader files.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("gl_test-preprocessor-{}", std::process::id()))
+            .join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn options(
+        version: Option<&'static str>,
+        defines: &[(&'static str, &'static str)],
+    ) -> ShaderOptions {
+        ShaderOptions {
+            version: version.map(Into::into),
+            defines: defines
+                .iter()
+                .map(|&(name, value)| (name.into(), value.into()))
+                .collect(),
+            include_paths: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn inserted_versions_keep_line_numbers() {
+        let source = "void main() {}\n";
+        let preprocessed = preprocess(source, None, &options(Some("450 core"), &[])).unwrap();
+        assert_eq!(
+            preprocessed.source,
+            "#version 450 core\n#line 1 0\nvoid main() {}\n"
+        );
+
+        let preprocessed =
+            preprocess(source, None, &options(Some("450 core"), &[("A", "1")])).unwrap();
+        assert_eq!(
+            preprocessed.source,
+            "#version 450 core\n#define A 1\n#line 1 0\nvoid main() {}\n"
+        );
+
+        // Nothing is inserted without options.
+        let preprocessed = preprocess(source, None, &options(None, &[])).unwrap();
+        assert_eq!(preprocessed.source, source);
+    }
+
+    #[test]
+    fn replaced_versions_keep_line_numbers() {
+        let source = "// Comment\n#version 330 core\nvoid main() {}\n";
+        let preprocessed = preprocess(source, None, &options(Some("450 core"), &[])).unwrap();
+        assert_eq!(
+            preprocessed.source,
+            "// Comment\n#version 450 core\nvoid main() {}\n"
+        );
+
+        let preprocessed = preprocess(source, None, &options(None, &[("A", "1")])).unwrap();
+        assert_eq!(
+            preprocessed.source,
+            "// Comment\n#version 330 core\n#define A 1\n#line 3 0\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn includes_are_resolved_and_numbered() {
+        let dir = test_dir("includes");
+        let shared = dir.join("shared");
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(dir.join("common.glsl"), "float common() { return 1.0; }\n").unwrap();
+        fs::write(
+            shared.join("lighting.glsl"),
+            "#include \"../common.glsl\"\nfloat light() { return common(); }\n",
+        )
+        .unwrap();
+
+        let main = dir.join("main.vert");
+        let source = "#version 330 core\n#include \"common.glsl\"\n#include \"lighting.glsl\"\nvoid main() {}\n";
+        let options = ShaderOptions {
+            include_paths: vec![shared.clone()],
+            ..Default::default()
+        };
+        fs::write(&main, source).unwrap();
+        let preprocessed = preprocess(source, Some(&main), &options).unwrap();
+
+        // lighting.glsl is found in the include path and its own include is relative to it.
+        // common.glsl keeps its source string number when it's included again.
+        assert_eq!(
+            preprocessed.source,
+            "#version 330 core\n\
+             #line 1 1\nfloat common() { return 1.0; }\n#line 3 0\n\
+             #line 1 2\n#line 1 1\nfloat common() { return 1.0; }\n#line 2 2\nfloat light() { return common(); }\n#line 4 0\n\
+             void main() {}\n"
+        );
+        assert_eq!(preprocessed.names.len(), 3);
+        assert_eq!(preprocessed.names[0], main.to_string_lossy());
+        assert_eq!(
+            preprocessed.contents[2],
+            fs::read_to_string(shared.join("lighting.glsl")).unwrap()
+        );
+        assert_eq!(preprocessed.files.len(), 3);
+    }
+
+    #[test]
+    fn include_errors_point_at_the_directive() {
+        let dir = test_dir("errors");
+        fs::write(dir.join("a.glsl"), "\n#include \"b.glsl\"\n").unwrap();
+        fs::write(dir.join("b.glsl"), "#include \"a.glsl\"\n").unwrap();
+        fs::write(dir.join("versioned.glsl"), "#version 330 core\n").unwrap();
+        let main = dir.join("main.vert");
+        fs::write(&main, "").unwrap();
+        let error = |source: &str| match preprocess(source, Some(&main), &Default::default()) {
+            Err(GlError::Shader(message)) => message,
+            result => panic!("Expected an error, got {result:?}"),
+        };
+
+        let cycle = error("#include \"a.glsl\"\n");
+        assert!(cycle.contains("b.glsl:1: include cycle"), "{cycle}");
+        let files: Vec<_> = cycle
+            .rsplit("include cycle ")
+            .next()
+            .unwrap()
+            .split(" -> ")
+            .map(|file| Path::new(file).file_name().unwrap().to_string_lossy())
+            .collect();
+        assert_eq!(files, ["a.glsl", "b.glsl", "a.glsl"]);
+
+        let missing = error("\n\n#include \"missing.glsl\"\n");
+        assert!(
+            missing.contains("main.vert:3: can't find included file"),
+            "{missing}"
+        );
+
+        let version = error("#include \"versioned.glsl\"\n");
+        assert!(
+            version.contains("versioned.glsl:1: #version in an included file"),
+            "{version}"
+        );
+
+        let syntax = error("#include <common.glsl>\n");
+        assert!(syntax.contains("expected #include"), "{syntax}");
+    }
+}
@@ -0,0 +1,470 @@
+use super::{
+    preprocessor::Preprocessed, Shader, ShaderDescriptor, ShaderFrom, ShaderProgram, SpirvBinary,
+};
+use crate::{
+    context::{
+        gl::{self, types::GLenum},
+        info::ContextInfo,
+        Gl,
+    },
+    glenums::{GetString, ShaderStages},
+    glerror::GlError,
+};
+use log::{info, warn};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// Marks cache files written by [ProgramCache].
+const CACHE_MAGIC: &[u8; 4] = b"GLPB";
+
+/// On-disk cache of linked program binaries.
+///
+/// Binaries are stored with [glGetProgramBinary](https://docs.gl/gl4/glGetProgramBinary) and
+/// keyed by a hash of the shaders and the driver that compiled them. Drivers may still reject a
+/// cached binary, e.g. after an update that kept the version string, in which case the program is
+/// compiled from scratch and the cache entry is replaced.
+///
+/// Keys are [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/) hashes of an explicit encoding
+/// of the shaders so they stay valid across builds. Files are named after the program's label as
+/// well, and storing a program removes its stale entries.
+#[derive(Debug, Clone)]
+pub struct ProgramCache {
+    dir: PathBuf,
+    // Identifies the driver that produced the binaries
+    driver: String,
+    // False if the driver has no binary formats, which makes the cache a no-op
+    enabled: bool,
+}
+
+impl ProgramCache {
+    /// Cache binaries in `dir` which is created when the first binary is stored.
+    pub fn new<P>(gl: &Rc<Gl>, dir: P, context_info: &ContextInfo) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        let mut formats = 0;
+        unsafe { gl.GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats) }
+        let enabled = formats > 0;
+        if !enabled {
+            info!("Program binaries aren't supported so programs won't be cached");
+        }
+
+        // The full version string includes the driver's release, e.g. "4.5 (Core Profile) Mesa 23.0"
+        let driver_version = gl.get_string(GetString::Version, None).unwrap_or_default();
+
+        Self {
+            dir: dir.into(),
+            driver: format!(
+                "{}\n{}\n{}\n{}",
+                context_info.vendor, context_info.renderer, driver_version, context_info.glsl
+            ),
+            enabled,
+        }
+    }
+
+    /// Cache that doesn't store anything so every program is compiled.
+    pub fn disabled() -> Self {
+        Self {
+            dir: PathBuf::new(),
+            driver: String::new(),
+            enabled: false,
+        }
+    }
+
+    /// Load a program from the cache or compile and link it like
+    /// [ShaderProgram::from_raw], then store its binary for the next run.
+    pub fn program<S, I>(
+        &self,
+        gl: Rc<Gl>,
+        raw_shaders: I,
+        label: S,
+    ) -> Result<ShaderProgram, GlError>
+    where
+        S: Into<Rc<str>>,
+        I: IntoIterator<Item = ShaderDescriptor>,
+    {
+        let label = label.into();
+        // Shaders are read once for both the key and the compilation.
+        let resolved = raw_shaders
+            .into_iter()
+            .map(Self::resolve)
+            .collect::<Result<Vec<_>, _>>()?;
+        let descriptors: Vec<_> = resolved.iter().map(|(key, _)| key.clone()).collect();
+        let compile = |gl: Rc<Gl>| {
+            resolved
+                .into_iter()
+                .map(|(descriptor, preprocessed)| match preprocessed {
+                    Some(preprocessed) => {
+                        Shader::compile(gl.clone(), descriptor.kind, preprocessed)
+                    }
+                    None => Shader::new(gl.clone(), descriptor),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        if !self.enabled {
+            return ShaderProgram::from_shaders(gl.clone(), &compile(gl)?, label);
+        }
+
+        let prefix = format!("{:016x}-", fnv1a(label.as_bytes()));
+        let name = format!("{prefix}{:016x}.bin", self.key(&descriptors));
+        let path = self.dir.join(&name);
+        if let Some((format, binary)) = Self::read(&path) {
+            let stages = descriptors
+                .iter()
+                .fold(ShaderStages::empty(), |stages, descriptor| {
+                    stages | descriptor.kind.into()
+                });
+            match ShaderProgram::from_binary(gl.clone(), format, &binary, stages, label.clone())? {
+                Some(program) => {
+                    info!("Loaded shader program '{label}' from the program cache");
+                    return Ok(program);
+                }
+                None => info!("Driver rejected the cached binary of '{label}', recompiling"),
+            }
+        }
+
+        let shaders = compile(gl.clone())?;
+        let program = ShaderProgram::link(gl, &shaders, label.clone(), true)?;
+
+        match program.binary() {
+            Some((format, binary)) => {
+                if let Err(e) = Self::write(&path, format, &binary) {
+                    warn!("Failed to cache shader program '{label}': {e}");
+                }
+                self.prune(&prefix, &name);
+            }
+            None => warn!("Driver didn't return a binary for shader program '{label}'"),
+        }
+        Ok(program)
+    }
+
+    /// Replace files with their preprocessed contents so the key covers what's actually
+    /// compiled, including included files. GLSL sources are returned preprocessed as well so that
+    /// compile errors still point at the original files.
+    fn resolve(
+        descriptor: ShaderDescriptor,
+    ) -> Result<(ShaderDescriptor, Option<Preprocessed>), GlError> {
+        if let Some(preprocessed) = Shader::preprocess(&descriptor)? {
+            let from = ShaderFrom::Source(preprocessed.source.clone().into());
+            // The options were applied by preprocessing
+            return Ok((
+                ShaderDescriptor::new(descriptor.kind, from),
+                Some(preprocessed),
+            ));
+        }
+
+        let from = match descriptor.from {
+            ShaderFrom::Spirv(mut module) => {
+                module.binary = SpirvBinary::Bytes(module.load()?);
+                ShaderFrom::Spirv(module)
+            }
+            from => from,
+        };
+        Ok((ShaderDescriptor::new(descriptor.kind, from), None))
+    }
+
+    /// Hash of the driver and every shader of a program.
+    ///
+    /// Descriptors are [resolved](Self::resolve) so only sources and SPIR-V bytes are left.
+    /// Variable-length fields are prefixed with their length so adjacent fields can't run into
+    /// each other.
+    fn key(&self, descriptors: &[ShaderDescriptor]) -> u64 {
+        fn field(encoded: &mut Vec<u8>, bytes: &[u8]) {
+            encoded.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            encoded.extend_from_slice(bytes);
+        }
+
+        let mut encoded = Vec::new();
+        field(&mut encoded, self.driver.as_bytes());
+        for descriptor in descriptors {
+            encoded.extend_from_slice(&(descriptor.kind as u32).to_le_bytes());
+            match &descriptor.from {
+                ShaderFrom::Source(source) => {
+                    encoded.push(0);
+                    field(&mut encoded, source.as_bytes());
+                }
+                ShaderFrom::FilePath(path) => {
+                    encoded.push(1);
+                    field(&mut encoded, path.to_string_lossy().as_bytes());
+                }
+                ShaderFrom::Spirv(module) => {
+                    encoded.push(2);
+                    match &module.binary {
+                        SpirvBinary::Bytes(bytes) => field(&mut encoded, bytes),
+                        SpirvBinary::FilePath(path) => {
+                            field(&mut encoded, path.to_string_lossy().as_bytes())
+                        }
+                    }
+                    field(&mut encoded, module.entry_point.as_bytes());
+                    encoded.extend_from_slice(&(module.constants.len() as u64).to_le_bytes());
+                    for (id, value) in &module.constants {
+                        encoded.extend_from_slice(&id.to_le_bytes());
+                        encoded.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+        }
+        fnv1a(&encoded)
+    }
+
+    /// Remove the other entries of the program named by `prefix` and files left by older
+    /// versions of the cache, keeping `current`.
+    fn prune(&self, prefix: &str, current: &str) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            let Some(stem) = name.strip_suffix(".bin") else {
+                continue;
+            };
+            // Current entries are named "{label hash}-{key}.bin"
+            let current_scheme = stem.len() == 33
+                && stem.as_bytes()[16] == b'-'
+                && stem
+                    .bytes()
+                    .enumerate()
+                    .all(|(i, b)| i == 16 || b.is_ascii_hexdigit());
+            if name != current && (name.starts_with(prefix) || !current_scheme) {
+                info!("Removing stale program cache file {name}");
+                if let Err(e) = fs::remove_file(entry.path()) {
+                    warn!("Failed to remove program cache file {name}: {e}");
+                }
+            }
+        }
+    }
+
+    /// Read a cache file. Missing or malformed files are misses.
+    fn read(path: &Path) -> Option<(GLenum, Vec<u8>)> {
+        let mut file = fs::read(path).ok()?;
+        if file.len() <= 8 || &file[..4] != CACHE_MAGIC {
+            warn!("Ignoring malformed program cache file {}", path.display());
+            return None;
+        }
+        let format = GLenum::from_le_bytes(file[4..8].try_into().expect("Four bytes"));
+        Some((format, file.split_off(8)))
+    }
+
+    /// Store a binary with its format.
+    fn write(path: &Path, format: GLenum, binary: &[u8]) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = Vec::with_capacity(8 + binary.len());
+        file.extend_from_slice(CACHE_MAGIC);
+        file.extend_from_slice(&format.to_le_bytes());
+        file.extend_from_slice(binary);
+        fs::write(path, file)
+    }
+}
+
+/// 64-bit [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/) hash, which unlike
+/// [DefaultHasher](std::collections::hash_map::DefaultHasher) is the same for every build.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shaders::{ShaderKind, SpirvModule};
+
+    fn source(kind: ShaderKind, source: &'static str) -> ShaderDescriptor {
+        ShaderDescriptor::new(kind, ShaderFrom::Source(source.into()))
+    }
+
+    #[test]
+    fn keys_are_stable() {
+        // Reference values of the FNV-1a specification
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        let cache = ProgramCache {
+            driver: "Mesa".into(),
+            ..ProgramCache::disabled()
+        };
+        let vertex = source(ShaderKind::Vertex, "void main() {}");
+        let key = cache.key(&[vertex.clone()]);
+        // Changing the encoding invalidates every cache, so it should be deliberate.
+        assert_eq!(key, 0xc620_361e_8d9b_6a17);
+
+        // Every part of the key matters.
+        let fragment = source(ShaderKind::Fragment, "void main() {}");
+        assert_ne!(cache.key(&[fragment]), key);
+        assert_ne!(
+            cache.key(&[source(ShaderKind::Vertex, "void main() { }")]),
+            key
+        );
+        assert_ne!(ProgramCache::disabled().key(&[vertex.clone()]), key);
+        let spirv = |entry_point: &'static str, constants: Vec<(u32, u32)>| {
+            let module = SpirvModule {
+                binary: SpirvBinary::Bytes(b"void main() {}".as_slice().into()),
+                entry_point: entry_point.into(),
+                constants,
+            };
+            cache.key(&[ShaderDescriptor::new(
+                ShaderKind::Vertex,
+                ShaderFrom::Spirv(module),
+            )])
+        };
+        assert_ne!(spirv("main", vec![]), key);
+        assert_ne!(spirv("main", vec![]), spirv("other", vec![]));
+        assert_ne!(spirv("main", vec![]), spirv("main", vec![(0, 1)]));
+    }
+
+    #[cfg(unix)]
+    mod headless {
+        use super::*;
+        use crate::context::headless::HeadlessContext;
+
+        const VERTEX: &str = "#version 330 core
+            void main() { gl_Position = vec4(0.0); }";
+
+        /// Empty directory for a test's cache.
+        fn test_dir(name: &str) -> PathBuf {
+            let dir = std::env::temp_dir()
+                .join(format!("gl_test-programcache-{}", std::process::id()))
+                .join(name);
+            let _ = fs::remove_dir_all(&dir);
+            dir
+        }
+
+        /// Program with a single active uniform called `uniform`.
+        fn shaders(uniform: &str) -> Vec<ShaderDescriptor> {
+            let fragment = format!(
+                "#version 330 core
+                uniform vec4 {uniform};
+                out vec4 FragColor;
+                void main() {{ FragColor = {uniform}; }}"
+            );
+            vec![
+                source(ShaderKind::Vertex, VERTEX),
+                ShaderDescriptor::new(ShaderKind::Fragment, ShaderFrom::Source(fragment.into())),
+            ]
+        }
+
+        fn files(dir: &Path) -> Vec<PathBuf> {
+            let mut files: Vec<_> = fs::read_dir(dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect();
+            files.sort();
+            files
+        }
+
+        fn cache(context: &HeadlessContext, dir: &Path) -> ProgramCache {
+            let gl = context.gl();
+            let cache = ProgramCache::new(&gl, dir, gl.info());
+            assert!(cache.enabled, "llvmpipe supports program binaries");
+            cache
+        }
+
+        #[test]
+        fn misses_are_stored_and_hits_are_loaded() {
+            let context = HeadlessContext::for_tests();
+            let dir = test_dir("hit");
+            let cache = cache(&context, &dir);
+
+            let a = cache.program(context.gl(), shaders("A"), "A").unwrap();
+            assert!(a.uniform_location("A").is_some());
+            let stored = files(&dir);
+            assert_eq!(stored.len(), 1);
+            let file = fs::read(&stored[0]).unwrap();
+            assert_eq!(&file[..4], CACHE_MAGIC);
+
+            // Store the binary of a program with another uniform as the entry of "A". Loading
+            // "A" returns that program, which it only can if it's loaded from the cache.
+            cache.program(context.gl(), shaders("B"), "B").unwrap();
+            let entries = files(&dir);
+            assert_eq!(entries.len(), 2);
+            let b = entries.iter().find(|path| **path != stored[0]).unwrap();
+            fs::copy(b, &stored[0]).unwrap();
+
+            let loaded = cache.program(context.gl(), shaders("A"), "A").unwrap();
+            assert!(loaded.uniform_location("B").is_some());
+            assert!(loaded.uniform_location("A").is_none());
+            assert_eq!(
+                loaded.stages(),
+                ShaderStages::Vertex | ShaderStages::Fragment
+            );
+        }
+
+        #[test]
+        fn broken_entries_are_recompiled_and_overwritten() {
+            let context = HeadlessContext::for_tests();
+            let dir = test_dir("broken");
+            let cache = cache(&context, &dir);
+            cache.program(context.gl(), shaders("A"), "A").unwrap();
+            let path = files(&dir).remove(0);
+            let valid = fs::read(&path).unwrap();
+
+            let truncated = valid[..6].to_vec();
+            let mut corrupted = valid.clone();
+            corrupted[8..].fill(0xAB);
+            for broken in [truncated, corrupted] {
+                fs::write(&path, &broken).unwrap();
+                let program = cache.program(context.gl(), shaders("A"), "A").unwrap();
+                assert!(program.uniform_location("A").is_some());
+                assert_eq!(files(&dir), [path.clone()]);
+                // Binaries aren't byte-for-byte reproducible, so check that the driver accepts it.
+                let (format, binary) = ProgramCache::read(&path).unwrap();
+                let stages = ShaderStages::Vertex | ShaderStages::Fragment;
+                assert!(
+                    ShaderProgram::from_binary(context.gl(), format, &binary, stages, "A")
+                        .unwrap()
+                        .is_some()
+                );
+            }
+        }
+
+        #[test]
+        fn stale_entries_are_pruned() {
+            let context = HeadlessContext::for_tests();
+            let dir = test_dir("prune");
+            let cache = cache(&context, &dir);
+            let other = cache.program(context.gl(), shaders("B"), "Other").unwrap();
+            drop(other);
+            let kept = files(&dir);
+            // Left by the previous naming scheme
+            fs::write(dir.join("0123456789abcdef.bin"), b"GLPB").unwrap();
+            fs::write(dir.join("notes.txt"), b"").unwrap();
+
+            cache.program(context.gl(), shaders("A"), "A").unwrap();
+            let first = files(&dir);
+            assert_eq!(first.len(), 3);
+            // The program changed, so its previous entry is replaced.
+            cache.program(context.gl(), shaders("C"), "A").unwrap();
+            let second = files(&dir);
+            assert_eq!(second.len(), 3);
+            assert!(kept.iter().all(|path| second.contains(path)));
+            assert!(second.contains(&dir.join("notes.txt")));
+            assert_ne!(first, second);
+        }
+
+        #[test]
+        fn disabled_caches_stay_off_disk() {
+            let context = HeadlessContext::for_tests();
+            let dir = test_dir("disabled");
+            let disabled = ProgramCache::disabled();
+            assert!(!disabled.enabled);
+            let program = disabled.program(context.gl(), shaders("A"), "A").unwrap();
+            assert!(program.uniform_location("A").is_some());
+
+            // The same cache with a directory doesn't create it either.
+            let cache = ProgramCache {
+                enabled: false,
+                ..cache(&context, &dir)
+            };
+            cache.program(context.gl(), shaders("A"), "A").unwrap();
+            assert!(!dir.exists());
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use super::ShaderProgram;
+use crate::{
+    context::{
+        gl::{
+            self,
+            types::{GLint, GLuint},
+        },
+        Gl,
+    },
+    glenums::ShaderStages,
+    glerror::GlError,
+    label::Label,
+};
+use log::{error, info};
+use std::rc::Rc;
+
+/// Stages from [separable](ShaderProgram::separable) programs combined into one pipeline.
+///
+/// Stages can be swapped individually, e.g. to switch fragment shaders without relinking the
+/// vertex stage. Interfaces between stages are matched at draw time, so use
+/// [validate](#method.validate) while developing to catch mismatches. Requires OpenGL 4.1 or
+/// [ARB_separate_shader_objects](https://registry.khronos.org/OpenGL/extensions/ARB/ARB_separate_shader_objects.txt).
+#[allow(dead_code)]
+pub struct ProgramPipeline {
+    gl: Rc<Gl>,
+    id: GLuint,
+    // Programs kept alive while their stages are used
+    programs: Vec<(ShaderStages, Rc<ShaderProgram>)>,
+    label: Rc<str>,
+}
+
+#[allow(dead_code)]
+impl ProgramPipeline {
+    /// Create an empty pipeline.
+    pub fn new<S>(gl: Rc<Gl>, label: S) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let label = label.into();
+        info!("Creating program pipeline '{}'", label);
+
+        let mut id = 0;
+        unsafe { gl.GenProgramPipelines(1, &mut id) }
+        if id == 0 {
+            error!("GenProgramPipelines failed to generate an object id");
+            return Err(GlError::ShaderProgram(
+                "GenProgramPipelines returned an object id of 0".into(),
+            ));
+        }
+
+        Ok(Self {
+            gl,
+            id,
+            programs: Vec::new(),
+            label,
+        })
+    }
+
+    /// Use the `stages` of `program` in this pipeline, replacing the programs that provided them
+    /// before.
+    ///
+    /// Stages the program doesn't contain are left empty and aren't attributed to it.
+    pub fn use_stages(
+        &mut self,
+        stages: ShaderStages,
+        program: Rc<ShaderProgram>,
+    ) -> Result<(), GlError> {
+        if !program.is_separable() {
+            return Err(GlError::ShaderProgram(format!(
+                "Program '{}' can't be used in pipeline '{}' because it isn't separable",
+                program.label(),
+                self.label
+            )));
+        }
+
+        unsafe {
+            self.gl
+                .UseProgramStages(self.id, stages.bits(), program.id())
+        }
+        self.release(stages);
+        let provided = stages & program.stages();
+        if !provided.is_empty() {
+            self.programs.push((provided, program));
+        }
+        Ok(())
+    }
+
+    /// Empty `stages` of the pipeline.
+    pub fn clear_stages(&mut self, stages: ShaderStages) {
+        unsafe { self.gl.UseProgramStages(self.id, stages.bits(), 0) }
+        self.release(stages);
+    }
+
+    /// Program that provides `stage`, if any.
+    pub fn program(&self, stage: ShaderStages) -> Option<&Rc<ShaderProgram>> {
+        self.programs
+            .iter()
+            .find(|(stages, _)| stages.intersects(stage))
+            .map(|(_, program)| program)
+    }
+
+    /// Make this the pipeline used by draws and dispatches.
+    ///
+    /// Pipelines are only used while no program is bound with
+    /// [ShaderProgram::set_used], so the current program is unbound.
+    pub fn bind(&self) {
+        unsafe {
+            self.gl.UseProgram(0);
+            self.gl.BindProgramPipeline(self.id);
+        }
+    }
+
+    /// Check whether the pipeline can run in the current state with
+    /// [glValidateProgramPipeline](https://docs.gl/gl4/glValidateProgramPipeline), e.g. whether
+    /// the outputs of each stage match the inputs of the next.
+    pub fn validate(&self) -> Result<(), GlError> {
+        let mut status = gl::FALSE as _;
+        unsafe {
+            self.gl.ValidateProgramPipeline(self.id);
+            self.gl
+                .GetProgramPipelineiv(self.id, gl::VALIDATE_STATUS, &mut status);
+        }
+        if status != gl::FALSE as GLint {
+            return Ok(());
+        }
+
+        let mut len = 0;
+        unsafe {
+            self.gl
+                .GetProgramPipelineiv(self.id, gl::INFO_LOG_LENGTH, &mut len)
+        }
+        let log = Gl::create_whitespace_cstring(len.max(0) as usize);
+        unsafe {
+            self.gl.GetProgramPipelineInfoLog(
+                self.id,
+                len,
+                std::ptr::null_mut(),
+                log.as_ptr() as *mut gl::types::GLchar,
+            )
+        }
+        Err(GlError::ShaderProgram(format!(
+            "Pipeline '{}' is invalid: {}",
+            self.label,
+            log.to_string_lossy().trim_end_matches('\0').trim_end()
+        )))
+    }
+
+    /// Forget the programs of `stages`, dropping programs that no longer provide any stage.
+    fn release(&mut self, stages: ShaderStages) {
+        for (used, _) in &mut self.programs {
+            used.remove(stages);
+        }
+        self.programs.retain(|(used, _)| !used.is_empty());
+    }
+}
+
+impl Label for ProgramPipeline {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.label.clone()
+    }
+}
+
+impl Drop for ProgramPipeline {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteProgramPipelines(1, &self.id) }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        context::headless::HeadlessContext,
+        shaders::{Shader, ShaderDescriptor, ShaderFrom, ShaderKind},
+    };
+
+    fn separable(
+        gl: Rc<Gl>,
+        kind: ShaderKind,
+        source: &'static str,
+        label: &str,
+    ) -> Rc<ShaderProgram> {
+        let descriptor = ShaderDescriptor::new(kind, ShaderFrom::Source(source.into()));
+        Rc::new(ShaderProgram::separable(gl, descriptor, label).unwrap())
+    }
+
+    #[test]
+    fn fragment_stages_are_swapped() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let vertex = separable(
+            gl.clone(),
+            ShaderKind::Vertex,
+            "#version 410 core
+            out gl_PerVertex { vec4 gl_Position; };
+            layout (location = 0) out vec3 Color;
+            void main() { Color = vec3(1.0); gl_Position = vec4(0.0); }",
+            "Vertex",
+        );
+        let red = separable(
+            gl.clone(),
+            ShaderKind::Fragment,
+            "#version 410 core
+            layout (location = 0) in vec3 Color;
+            out vec4 FragColor;
+            void main() { FragColor = vec4(Color.r, 0.0, 0.0, 1.0); }",
+            "Red",
+        );
+        let green = separable(
+            gl.clone(),
+            ShaderKind::Fragment,
+            "#version 410 core
+            layout (location = 0) in vec3 Color;
+            out vec4 FragColor;
+            void main() { FragColor = vec4(0.0, Color.g, 0.0, 1.0); }",
+            "Green",
+        );
+        assert_eq!(vertex.stages(), ShaderStages::Vertex);
+
+        let mut pipeline = ProgramPipeline::new(gl, "Pipeline").unwrap();
+        // The vertex program only provides its own stage.
+        pipeline
+            .use_stages(ShaderStages::all(), vertex.clone())
+            .unwrap();
+        assert!(Rc::ptr_eq(
+            pipeline.program(ShaderStages::Vertex).unwrap(),
+            &vertex
+        ));
+        assert!(pipeline.program(ShaderStages::Fragment).is_none());
+        let recorded: Vec<_> = pipeline
+            .programs
+            .iter()
+            .map(|(stages, _)| *stages)
+            .collect();
+        assert_eq!(recorded, [ShaderStages::Vertex]);
+
+        pipeline.use_stages(ShaderStages::Fragment, red).unwrap();
+        pipeline.bind();
+        pipeline.validate().unwrap();
+
+        pipeline
+            .use_stages(ShaderStages::Fragment, green.clone())
+            .unwrap();
+        pipeline.validate().unwrap();
+        assert!(Rc::ptr_eq(
+            pipeline.program(ShaderStages::Fragment).unwrap(),
+            &green
+        ));
+        assert!(Rc::ptr_eq(
+            pipeline.program(ShaderStages::Vertex).unwrap(),
+            &vertex
+        ));
+        assert_eq!(pipeline.programs.len(), 2);
+
+        // Programs that weren't linked as separable are rejected.
+        let descriptor = ShaderDescriptor::new(
+            ShaderKind::Fragment,
+            ShaderFrom::Source("#version 410 core\nvoid main() {}".into()),
+        );
+        let shader = Shader::new(context.gl(), descriptor).unwrap();
+        let linked = ShaderProgram::from_shaders(context.gl(), &[shader], "Linked").unwrap();
+        assert!(pipeline
+            .use_stages(ShaderStages::Fragment, Rc::new(linked))
+            .is_err());
+    }
+}
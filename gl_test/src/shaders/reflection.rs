@@ -0,0 +1,538 @@
+use crate::{
+    context::{
+        gl::{
+            self,
+            types::{GLchar, GLenum, GLint, GLsizei, GLuint},
+        },
+        Gl,
+    },
+    glenums::GlslType,
+};
+use std::fmt::{self, Display, Formatter};
+
+/// Vertex shader input or fragment shader output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProgramVariable {
+    pub name: String,
+    pub kind: GlslType,
+    /// None for built-in variables such as `gl_VertexID`
+    pub location: Option<u32>,
+    /// One if the variable isn't an array
+    pub array_size: u32,
+}
+
+/// Uniform in the default block which is set with glProgramUniform.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProgramUniform {
+    pub name: String,
+    pub kind: GlslType,
+    pub location: u32,
+    /// One if the uniform isn't an array
+    pub array_size: u32,
+}
+
+/// Variable stored in a uniform or shader storage block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BlockMember {
+    pub name: String,
+    pub kind: GlslType,
+    /// Zero for runtime sized arrays at the end of a storage block
+    pub array_size: u32,
+    /// Bytes from the start of the block
+    pub offset: u32,
+    /// Bytes between array elements or zero if the member isn't an array
+    pub array_stride: u32,
+    /// Bytes between columns of a matrix or zero if the member isn't a matrix
+    pub matrix_stride: u32,
+}
+
+/// Uniform or shader storage block backed by a buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProgramBlock {
+    pub name: String,
+    /// Indexed buffer binding point that the block reads from
+    pub binding: u32,
+    /// Minimum size of the buffer backing the block
+    pub data_size: usize,
+    /// Members sorted by offset
+    pub members: Vec<BlockMember>,
+}
+
+/// Every active resource of a linked program.
+///
+/// Inactive resources, such as uniforms that don't contribute to the output, are optimized out
+/// by the driver and aren't listed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProgramInterface {
+    pub inputs: Vec<ProgramVariable>,
+    pub outputs: Vec<ProgramVariable>,
+    pub uniforms: Vec<ProgramUniform>,
+    pub uniform_blocks: Vec<ProgramBlock>,
+    pub storage_blocks: Vec<ProgramBlock>,
+}
+
+impl ProgramInterface {
+    /// Query the active resources of `program` with
+    /// [glGetProgramResource](https://docs.gl/gl4/glGetProgramResource).
+    ///
+    /// Requires OpenGL 4.3.
+    pub(super) fn query(gl: &Gl, program: GLuint) -> Self {
+        let inputs = Resources::new(gl, program, gl::PROGRAM_INPUT).variables();
+        let outputs = Resources::new(gl, program, gl::PROGRAM_OUTPUT).variables();
+
+        // Block members are listed with the default block's uniforms.
+        let uniform_blocks = Resources::new(gl, program, gl::UNIFORM_BLOCK)
+            .blocks(&Resources::new(gl, program, gl::UNIFORM));
+        let storage_blocks = Resources::new(gl, program, gl::SHADER_STORAGE_BLOCK)
+            .blocks(&Resources::new(gl, program, gl::BUFFER_VARIABLE));
+
+        let uniforms = Resources::new(gl, program, gl::UNIFORM).uniforms();
+
+        Self {
+            inputs,
+            outputs,
+            uniforms,
+            uniform_blocks,
+            storage_blocks,
+        }
+    }
+}
+
+impl Display for ProgramInterface {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let array = |size: u32| {
+            if size > 1 {
+                format!("[{size}]")
+            } else {
+                String::new()
+            }
+        };
+        let location = |location: Option<u32>| match location {
+            Some(location) => location.to_string(),
+            None => "-".into(),
+        };
+
+        for input in &self.inputs {
+            writeln!(
+                f,
+                "in {} {}{} (location = {})",
+                input.kind,
+                input.name,
+                array(input.array_size),
+                location(input.location)
+            )?;
+        }
+        for output in &self.outputs {
+            writeln!(
+                f,
+                "out {} {}{} (location = {})",
+                output.kind,
+                output.name,
+                array(output.array_size),
+                location(output.location)
+            )?;
+        }
+        for uniform in &self.uniforms {
+            writeln!(
+                f,
+                "uniform {} {}{} (location = {})",
+                uniform.kind,
+                uniform.name,
+                array(uniform.array_size),
+                uniform.location
+            )?;
+        }
+
+        let blocks = self
+            .uniform_blocks
+            .iter()
+            .map(|block| ("uniform", block))
+            .chain(self.storage_blocks.iter().map(|block| ("buffer", block)));
+        for (qualifier, block) in blocks {
+            writeln!(
+                f,
+                "{qualifier} {} (binding = {}, {} bytes)",
+                block.name, block.binding, block.data_size
+            )?;
+            for member in &block.members {
+                writeln!(
+                    f,
+                    "    {} {}{} (offset = {})",
+                    member.kind,
+                    member.name,
+                    array(member.array_size),
+                    member.offset
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Resources of one program interface, e.g. every uniform.
+struct Resources<'gl> {
+    gl: &'gl Gl,
+    program: GLuint,
+    interface: GLenum,
+}
+
+impl<'gl> Resources<'gl> {
+    fn new(gl: &'gl Gl, program: GLuint, interface: GLenum) -> Self {
+        Self {
+            gl,
+            program,
+            interface,
+        }
+    }
+
+    /// Number of active resources.
+    fn count(&self) -> GLuint {
+        let mut count: GLint = 0;
+        unsafe {
+            self.gl.GetProgramInterfaceiv(
+                self.program,
+                self.interface,
+                gl::ACTIVE_RESOURCES,
+                &mut count,
+            )
+        }
+        count.max(0) as GLuint
+    }
+
+    /// Query properties of the resource at `index`.
+    fn properties<const N: usize>(&self, index: GLuint, props: [GLenum; N]) -> [GLint; N] {
+        let mut values = [0; N];
+        unsafe {
+            self.gl.GetProgramResourceiv(
+                self.program,
+                self.interface,
+                index,
+                N as GLsizei,
+                props.as_ptr(),
+                N as GLsizei,
+                std::ptr::null_mut(),
+                values.as_mut_ptr(),
+            )
+        }
+        values
+    }
+
+    /// Indices into another interface of a block's active variables.
+    fn active_variables(&self, index: GLuint) -> Vec<GLuint> {
+        let [count] = self.properties(index, [gl::NUM_ACTIVE_VARIABLES]);
+        let mut variables = vec![0; count.max(0) as usize];
+        if !variables.is_empty() {
+            let prop = gl::ACTIVE_VARIABLES;
+            unsafe {
+                self.gl.GetProgramResourceiv(
+                    self.program,
+                    self.interface,
+                    index,
+                    1,
+                    &prop,
+                    count,
+                    std::ptr::null_mut(),
+                    variables.as_mut_ptr(),
+                )
+            }
+        }
+        variables.into_iter().map(|index| index as GLuint).collect()
+    }
+
+    /// Name of the resource at `index`. `len` includes the null terminator.
+    fn name(&self, index: GLuint, len: GLint) -> String {
+        let name = Gl::create_whitespace_cstring(len.max(1) as usize);
+        let mut written: GLsizei = 0;
+        unsafe {
+            self.gl.GetProgramResourceName(
+                self.program,
+                self.interface,
+                index,
+                len,
+                &mut written,
+                name.as_ptr() as *mut GLchar,
+            )
+        }
+        let mut name = name.into_bytes();
+        name.truncate(written.max(0) as usize);
+        let mut name = String::from_utf8_lossy(&name).into_owned();
+
+        // Arrays are reported by their first element, e.g. "lights[0]".
+        if name.ends_with("[0]") {
+            name.truncate(name.len() - 3);
+        }
+        name
+    }
+
+    /// Program inputs or outputs.
+    fn variables(&self) -> Vec<ProgramVariable> {
+        (0..self.count())
+            .map(|index| {
+                let [name_len, kind, array_size, location] = self.properties(
+                    index,
+                    [gl::NAME_LENGTH, gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION],
+                );
+                ProgramVariable {
+                    name: self.name(index, name_len),
+                    kind: glsl_type(kind),
+                    location: (location >= 0).then_some(location as u32),
+                    array_size: array_size as u32,
+                }
+            })
+            .collect()
+    }
+
+    /// Uniforms in the default block.
+    fn uniforms(&self) -> Vec<ProgramUniform> {
+        (0..self.count())
+            .filter_map(|index| {
+                let [name_len, kind, array_size, location, block] = self.properties(
+                    index,
+                    [
+                        gl::NAME_LENGTH,
+                        gl::TYPE,
+                        gl::ARRAY_SIZE,
+                        gl::LOCATION,
+                        gl::BLOCK_INDEX,
+                    ],
+                );
+                // Atomic counters and block members don't have locations.
+                (block == -1 && location >= 0).then(|| ProgramUniform {
+                    name: self.name(index, name_len),
+                    kind: glsl_type(kind),
+                    location: location as u32,
+                    array_size: array_size as u32,
+                })
+            })
+            .collect()
+    }
+
+    /// Uniform or storage blocks whose members are in `members`.
+    fn blocks(&self, members: &Resources) -> Vec<ProgramBlock> {
+        (0..self.count())
+            .map(|index| {
+                let [name_len, binding, data_size] = self.properties(
+                    index,
+                    [gl::NAME_LENGTH, gl::BUFFER_BINDING, gl::BUFFER_DATA_SIZE],
+                );
+                let mut block_members: Vec<_> = self
+                    .active_variables(index)
+                    .into_iter()
+                    .map(|member| members.block_member(member))
+                    .collect();
+                block_members.sort_by_key(|member| member.offset);
+
+                ProgramBlock {
+                    name: self.name(index, name_len),
+                    binding: binding as u32,
+                    data_size: data_size as usize,
+                    members: block_members,
+                }
+            })
+            .collect()
+    }
+
+    /// Uniform or buffer variable that's stored in a block.
+    fn block_member(&self, index: GLuint) -> BlockMember {
+        let [name_len, kind, array_size, offset, array_stride, matrix_stride] = self.properties(
+            index,
+            [
+                gl::NAME_LENGTH,
+                gl::TYPE,
+                gl::ARRAY_SIZE,
+                gl::OFFSET,
+                gl::ARRAY_STRIDE,
+                gl::MATRIX_STRIDE,
+            ],
+        );
+        BlockMember {
+            name: self.name(index, name_len),
+            kind: glsl_type(kind),
+            array_size: array_size as u32,
+            offset: offset as u32,
+            array_stride: array_stride as u32,
+            matrix_stride: matrix_stride as u32,
+        }
+    }
+}
+
+/// Wrap a type reported by the driver, including types that [GlslType] doesn't name.
+fn glsl_type(kind: GLint) -> GlslType {
+    GlslType::from(kind as GLenum)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        context::headless::HeadlessContext,
+        shaders::{ShaderDescriptor, ShaderFrom, ShaderKind, ShaderProgram},
+    };
+
+    fn reflected() -> ProgramInterface {
+        let context = HeadlessContext::for_tests();
+        let program = ShaderProgram::from_raw(
+            context.gl(),
+            [
+                ShaderDescriptor::new(
+                    ShaderKind::Vertex,
+                    ShaderFrom::Source(
+                        "#version 430 core
+                        layout (location = 0) in vec3 Position;
+                        layout (location = 2) in vec2 Uv;
+                        layout (location = 5) uniform mat4 Transform;
+                        layout (std140, binding = 3) uniform Camera {
+                            mat4 View;
+                            vec3 Eye;
+                            float Exposure;
+                        };
+                        out vec2 FragUv;
+                        void main() {
+                            FragUv = Uv * Exposure;
+                            gl_Position = View * Transform * vec4(Position + Eye, 1.0);
+                        }"
+                        .into(),
+                    ),
+                ),
+                ShaderDescriptor::new(
+                    ShaderKind::Fragment,
+                    ShaderFrom::Source(
+                        "#version 430 core
+                        in vec2 FragUv;
+                        layout (location = 7) uniform float Weights[4];
+                        layout (std430, binding = 1) buffer Lights {
+                            vec4 Color;
+                            float Intensity[];
+                        };
+                        layout (location = 0) out vec4 FragColor;
+                        void main() {
+                            float weight = Weights[0] + Weights[3];
+                            FragColor = Color * Intensity[int(FragUv.x)] * weight;
+                        }"
+                        .into(),
+                    ),
+                ),
+            ],
+            "Reflected",
+        )
+        .unwrap();
+        program.interface()
+    }
+
+    fn member(name: &str, kind: GlslType, offset: u32) -> BlockMember {
+        BlockMember {
+            name: name.into(),
+            kind,
+            array_size: 1,
+            offset,
+            array_stride: 0,
+            matrix_stride: 0,
+        }
+    }
+
+    #[test]
+    fn interface_is_reflected() {
+        let interface = reflected();
+
+        let inputs: Vec<_> = interface
+            .inputs
+            .iter()
+            .map(|input| (input.name.as_str(), input.kind, input.location))
+            .collect();
+        assert_eq!(
+            inputs,
+            [
+                ("Position", GlslType::Vec3, Some(0)),
+                ("Uv", GlslType::Vec2, Some(2))
+            ]
+        );
+        assert_eq!(
+            interface.outputs,
+            [ProgramVariable {
+                name: "FragColor".into(),
+                kind: GlslType::Vec4,
+                location: Some(0),
+                array_size: 1,
+            }]
+        );
+
+        let uniform = |name: &str| {
+            interface
+                .uniforms
+                .iter()
+                .find(|uniform| uniform.name == name)
+                .unwrap_or_else(|| panic!("{name} is reflected"))
+        };
+        assert_eq!(
+            (uniform("Transform").kind, uniform("Transform").location),
+            (GlslType::Mat4, 5)
+        );
+        assert_eq!(
+            uniform("Weights"),
+            &ProgramUniform {
+                name: "Weights".into(),
+                kind: GlslType::Float,
+                location: 7,
+                array_size: 4,
+            }
+        );
+        // Block members aren't listed with the default block's uniforms.
+        assert_eq!(interface.uniforms.len(), 2);
+
+        // std140 packs the float after the vec3.
+        let mut view = member("View", GlslType::Mat4, 0);
+        view.matrix_stride = 16;
+        assert_eq!(
+            interface.uniform_blocks,
+            [ProgramBlock {
+                name: "Camera".into(),
+                binding: 3,
+                data_size: 80,
+                members: vec![
+                    view,
+                    member("Eye", GlslType::Vec3, 64),
+                    member("Exposure", GlslType::Float, 76),
+                ],
+            }]
+        );
+
+        let [lights] = interface.storage_blocks.as_slice() else {
+            panic!("One storage block is reflected");
+        };
+        assert_eq!((lights.name.as_str(), lights.binding), ("Lights", 1));
+        // The runtime sized array counts as one element, and drivers may pad it.
+        assert!(lights.data_size >= 20, "{}", lights.data_size);
+        let mut intensity = member("Intensity", GlslType::Float, 16);
+        intensity.array_size = 0;
+        intensity.array_stride = 4;
+        assert_eq!(
+            lights.members,
+            [member("Color", GlslType::Vec4, 0), intensity]
+        );
+
+        let printed = interface.to_string();
+        assert!(
+            printed.contains("uniform float Weights[4] (location = 7)"),
+            "{printed}"
+        );
+        assert!(printed.contains("    vec3 Eye (offset = 64)"), "{printed}");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn interface_is_serialized() {
+        let json = serde_json::to_value(reflected()).unwrap();
+        assert_eq!(json["inputs"][1]["name"], "Uv");
+        assert_eq!(json["inputs"][1]["location"], 2);
+        assert_eq!(json["uniforms"][1]["kind"], "Float");
+        assert_eq!(json["uniforms"][1]["array_size"], 4);
+        assert_eq!(json["uniform_blocks"][0]["members"][1]["offset"], 64);
+        assert_eq!(json["storage_blocks"][0]["binding"], 1);
+    }
+}
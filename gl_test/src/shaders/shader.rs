@@ -10,21 +10,32 @@ use std::{
 
 use log::{error, info};
 
+use super::{
+    diagnostics,
+    preprocessor::{self, Preprocessed},
+    DiagnosticSeverity, ShaderDiagnostic, ShaderOptions, SpirvBinary, SpirvModule,
+};
 use crate::{
     context::{
-        gl::{self, types::GLuint},
+        gl::{
+            self,
+            types::{GLint, GLsizei, GLuint, GLvoid},
+        },
         Gl,
     },
+    glenums::ObjectName,
     glerror::GlError,
 };
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(u32)]
 pub enum ShaderKind {
     Vertex = gl::VERTEX_SHADER,
     Fragment = gl::FRAGMENT_SHADER,
+    TessControl = gl::TESS_CONTROL_SHADER,
+    TessEvaluation = gl::TESS_EVALUATION_SHADER,
     Geometry = gl::GEOMETRY_SHADER,
-    Spirv = gl::SHADER_BINARY_FORMAT_SPIR_V,
+    Compute = gl::COMPUTE_SHADER,
 }
 
 impl fmt::Display for ShaderKind {
@@ -33,24 +44,40 @@ impl fmt::Display for ShaderKind {
         match *self {
             Vertex => write!(f, "Vertex"),
             Fragment => write!(f, "Fragment"),
+            TessControl => write!(f, "Tessellation control"),
+            TessEvaluation => write!(f, "Tessellation evaluation"),
             Geometry => write!(f, "Geometry"),
-            Spirv => write!(f, "SPIR-V"),
+            Compute => write!(f, "Compute"),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ShaderFrom {
     // Shader source code
     Source(Cow<'static, str>),
     // File containing shader source
     FilePath(PathBuf),
+    // Precompiled SPIR-V module
+    Spirv(SpirvModule),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ShaderDescriptor {
     pub kind: ShaderKind,
     pub from: ShaderFrom,
+    pub options: ShaderOptions,
+}
+
+impl ShaderDescriptor {
+    /// Descriptor with default [ShaderOptions].
+    pub fn new(kind: ShaderKind, from: ShaderFrom) -> Self {
+        Self {
+            kind,
+            from,
+            options: ShaderOptions::default(),
+        }
+    }
 }
 
 pub struct Shader {
@@ -61,7 +88,7 @@ pub struct Shader {
 
 impl Shader {
     /// Helper function to load shader source code
-    fn from_file<P: AsRef<Path>>(path: P) -> Result<Cow<'static, str>, GlError> {
+    pub(super) fn from_file<P: AsRef<Path>>(path: P) -> Result<Cow<'static, str>, GlError> {
         let path = path.as_ref();
         let path_name = path.to_string_lossy();
         info!("Loading shader from file: {}", path_name);
@@ -93,25 +120,48 @@ impl Shader {
         Ok(source.into())
     }
 
-    /// Compile a shader from source.
+    /// Load GLSL source code and run the [preprocessor](super::ShaderOptions) over it.
+    ///
+    /// Returns None for SPIR-V modules which aren't preprocessed.
+    pub(super) fn preprocess(
+        descriptor: &ShaderDescriptor,
+    ) -> Result<Option<Preprocessed>, GlError> {
+        match &descriptor.from {
+            ShaderFrom::FilePath(path) => {
+                let source = Shader::from_file(path)?;
+                preprocessor::preprocess(&source, Some(path), &descriptor.options).map(Some)
+            }
+            ShaderFrom::Source(source) => {
+                preprocessor::preprocess(source, None, &descriptor.options).map(Some)
+            }
+            ShaderFrom::Spirv(_) => Ok(None),
+        }
+    }
+
+    /// Compile a shader from GLSL source or specialize a SPIR-V module.
     pub(super) fn new(gl: Rc<Gl>, descriptor: ShaderDescriptor) -> Result<Self, GlError> {
-        // Load shader source code from file if necessary.
-        let source = match descriptor.from {
-            ShaderFrom::FilePath(path) => Shader::from_file(path)?,
-            ShaderFrom::Source(source) => source,
+        if let ShaderFrom::Spirv(module) = &descriptor.from {
+            return Shader::from_spirv(gl, descriptor.kind, module);
+        }
+        // Load shader source code from file if necessary and resolve includes.
+        let Some(preprocessed) = Shader::preprocess(&descriptor)? else {
+            unreachable!("GLSL sources are always preprocessed");
         };
+        Shader::compile(gl, descriptor.kind, preprocessed)
+    }
+
+    /// Compile preprocessed GLSL source.
+    pub(super) fn compile(
+        gl: Rc<Gl>,
+        kind: ShaderKind,
+        preprocessed: Preprocessed,
+    ) -> Result<Self, GlError> {
         // Convert source to a CString for FFI
-        let source = CString::new(&*source)
+        let source = CString::new(preprocessed.source.as_str())
             .map_err(|_| GlError::Shader("Invalid CString from shader source".to_string()))?;
 
         // Create shader object and compile source
-        let id = unsafe { gl.CreateShader(descriptor.kind as _) };
-        if id == 0 {
-            error!("CreateShader failed to generate an object id");
-            return Err(GlError::Shader(
-                "CreateShader returned an object id of 0".into(),
-            ));
-        }
+        let shader = Shader::create(gl, kind, preprocessed.origin.as_deref())?;
 
         // Compile sauce
         unsafe {
@@ -119,36 +169,138 @@ impl Shader {
             // Dropping the CString that stores the source is entirely safe.
             // Length: ShaderSource doesn't need the actual length because CStrings are null terminated.
             // https://docs.gl/gl4/glShaderSource
-            gl.ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
-            gl.CompileShader(id);
+            shader
+                .gl
+                .ShaderSource(shader.id, 1, &source.as_ptr(), std::ptr::null());
+            shader.gl.CompileShader(shader.id);
         }
 
+        shader.check_compile_status(&preprocessed)
+    }
+
+    /// Load a SPIR-V module with [glShaderBinary](https://docs.gl/gl4/glShaderBinary) and
+    /// specialize its entry point for `kind`.
+    fn from_spirv(gl: Rc<Gl>, kind: ShaderKind, module: &SpirvModule) -> Result<Self, GlError> {
+        // The entry points may be loaded even if the context doesn't support SPIR-V.
+        if !gl.info().supports_spirv() {
+            return Err(GlError::Shader(
+                "SPIR-V shaders require OpenGL 4.6 or GL_ARB_gl_spirv".into(),
+            ));
+        }
+
+        let binary = module.load()?;
+        let entry_point = CString::new(&*module.entry_point)
+            .map_err(|_| GlError::Shader("Invalid CString from SPIR-V entry point".to_string()))?;
+        let (indices, values): (Vec<GLuint>, Vec<GLuint>) =
+            module.constants.iter().copied().unzip();
+
+        let origin = match &module.binary {
+            SpirvBinary::FilePath(path) => Some(path.clone()),
+            SpirvBinary::Bytes(_) => None,
+        };
+
+        let shader = Shader::create(gl, kind, origin.as_deref())?;
+        unsafe {
+            shader.gl.ShaderBinary(
+                1,
+                &shader.id,
+                gl::SHADER_BINARY_FORMAT_SPIR_V,
+                binary.as_ptr() as *const GLvoid,
+                binary.len() as GLsizei,
+            );
+            // https://docs.gl/gl4/glSpecializeShader
+            shader.gl.SpecializeShader(
+                shader.id,
+                entry_point.as_ptr(),
+                indices.len() as GLuint,
+                indices.as_ptr(),
+                values.as_ptr(),
+            );
+        }
+
+        // Specialization sets the compile status and info log like compiling GLSL does, but
+        // drivers may leave the log empty, e.g. for a missing entry point.
+        shader
+            .check_compile_status(&Preprocessed {
+                origin,
+                ..Preprocessed::default()
+            })
+            .map_err(|e| match e {
+                GlError::ShaderCompile {
+                    kind,
+                    origin,
+                    diagnostics,
+                } if diagnostics.is_empty() => GlError::ShaderCompile {
+                    kind,
+                    origin,
+                    diagnostics: vec![ShaderDiagnostic {
+                        severity: DiagnosticSeverity::Error,
+                        file: None,
+                        line: None,
+                        column: None,
+                        message: format!(
+                            "entry point '{}' of the SPIR-V module couldn't be specialized",
+                            module.entry_point
+                        ),
+                        snippet: None,
+                    }],
+                },
+                e => e,
+            })
+    }
+
+    /// Create an empty shader object which is deleted when dropped.
+    ///
+    /// The object is labelled with the file it's loaded from or its kind for inline sources.
+    fn create(gl: Rc<Gl>, kind: ShaderKind, origin: Option<&Path>) -> Result<Self, GlError> {
+        let id = unsafe { gl.CreateShader(kind as _) };
+        if id == 0 {
+            error!("CreateShader failed to generate an object id");
+            return Err(GlError::Shader(
+                "CreateShader returned an object id of 0".into(),
+            ));
+        }
+
+        let label = match origin {
+            Some(path) => path.to_string_lossy(),
+            None => format!("{kind} shader").into(),
+        };
+        gl.object_label(ObjectName::Shader, id, &label);
+        Ok(Self { gl, id, kind })
+    }
+
+    /// Return the shader if it compiled or the diagnostics from the info log if it didn't.
+    ///
+    /// `preprocessed` maps the source string numbers in the log to files.
+    fn check_compile_status(self, preprocessed: &Preprocessed) -> Result<Self, GlError> {
         // Check for compile status errors
         let mut success = gl::TRUE as _;
         unsafe {
-            gl.GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+            self.gl
+                .GetShaderiv(self.id, gl::COMPILE_STATUS, &mut success);
         }
 
-        if success != gl::FALSE as _ {
-            Ok(Self {
-                gl,
-                id,
-                kind: descriptor.kind,
-            })
+        if success != gl::FALSE as GLint {
+            Ok(self)
         } else {
             // Retrieve error string from OpenGL if compilation failed
             let mut len: gl::types::GLint = 0;
-            unsafe { gl.GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len) };
-            let error = Gl::create_whitespace_cstring(len as usize);
+            unsafe { self.gl.GetShaderiv(self.id, gl::INFO_LOG_LENGTH, &mut len) };
+            let error = Gl::create_whitespace_cstring(len.max(0) as usize);
             unsafe {
-                gl.GetShaderInfoLog(
-                    id,
+                self.gl.GetShaderInfoLog(
+                    self.id,
                     len,
                     std::ptr::null_mut(),
                     error.as_ptr() as *mut gl::types::GLchar,
                 )
             };
-            Err(GlError::Shader(error.to_string_lossy().into_owned()))
+            let log = error.to_string_lossy();
+            Err(GlError::ShaderCompile {
+                kind: self.kind,
+                origin: preprocessed.origin.clone(),
+                diagnostics: diagnostics::parse(&log, &preprocessed.names, &preprocessed.contents),
+            })
         }
     }
 
@@ -169,6 +321,7 @@ impl Drop for Shader {
         // Signals that a shader may be deleted but does not delete if attached to a program.
         // Shaders do not need to stay attached to a program after linking.
         // https://www.khronos.org/registry/OpenGL-Refpages/gl4/html/glDeleteShader.xhtml
+        self.gl.log_deletion(ObjectName::Shader, self.id);
         unsafe { self.gl.DeleteShader(self.id) }
     }
 }
@@ -1,68 +1,243 @@
-use super::{Shader, ShaderDescriptor};
+use super::{ProgramInterface, Shader, ShaderDescriptor, Uniform};
 use crate::{
     context::{
         gl::{
             self,
-            types::{GLint, GLuint},
+            types::{GLenum, GLint, GLsizei, GLuint, GLvoid},
         },
         Gl,
     },
+    glenums::{ObjectName, ShaderStages},
     glerror::GlError,
     label::Label,
 };
-use log::{error, info};
-use std::rc::Rc;
+use log::{error, info, warn};
+use std::{cell::RefCell, collections::HashMap, ffi::CString, rc::Rc};
 
 pub struct ShaderProgram {
     gl: Rc<Gl>,
     id: GLuint,
+    // Uniform locations by name. Unknown names are stored as -1 so they're only reported once.
+    uniforms: RefCell<HashMap<Box<str>, GLint>>,
+    // Uniform block bindings by block name, reapplied when the program is replaced
+    block_bindings: RefCell<HashMap<Box<str>, u32>>,
+    // Stages of the shaders that were linked
+    stages: ShaderStages,
     label: Rc<str>,
 }
 
 impl ShaderProgram {
     fn new<S>(gl: Rc<Gl>, shaders: &[Shader], label: S) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        ShaderProgram::link(gl, shaders, label, false)
+    }
+
+    /// Link `shaders` into a new program.
+    ///
+    /// `retrievable` hints to the driver that the program's binary will be read back with
+    /// [binary](#method.binary).
+    pub(super) fn link<S>(
+        gl: Rc<Gl>,
+        shaders: &[Shader],
+        label: S,
+        retrievable: bool,
+    ) -> Result<Self, GlError>
     where
         S: Into<Rc<str>>,
     {
         let label = label.into();
         info!("Creating shader program '{}'", label);
+        let program = ShaderProgram::create(gl, label)?;
 
-        // Create shader program
-        let program = unsafe { gl.CreateProgram() };
+        // Must be set before linking to take effect.
+        if retrievable {
+            unsafe {
+                program.gl.ProgramParameteri(
+                    program.id,
+                    gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                    gl::TRUE as _,
+                )
+            }
+        }
 
-        // CreateProgram returns 0 on errors, but errors only occur if something is really broken (like a Context error).
-        if program == 0 {
-            error!("CreateProgram failed to generate an object id");
-            Err(GlError::ShaderProgram(
-                "CreateProgram returned an object id of 0".into(),
-            ))?
+        program.attach_and_link(shaders)
+    }
+
+    /// Compile and link a single shader into a separable program whose stage can be combined
+    /// with other programs' stages in a [ProgramPipeline](super::ProgramPipeline).
+    ///
+    /// Requires OpenGL 4.1 or
+    /// [ARB_separate_shader_objects](https://registry.khronos.org/OpenGL/extensions/ARB/ARB_separate_shader_objects.txt).
+    pub fn separable<S>(gl: Rc<Gl>, descriptor: ShaderDescriptor, label: S) -> Result<Self, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let label = label.into();
+        info!("Creating separable shader program '{}'", label);
+        let shader = Shader::new(gl.clone(), descriptor)?;
+        let program = ShaderProgram::create(gl, label)?;
+
+        // Must be set before linking to take effect.
+        unsafe {
+            program
+                .gl
+                .ProgramParameteri(program.id, gl::PROGRAM_SEPARABLE, gl::TRUE as _)
+        }
+
+        program.attach_and_link(&[shader])
+    }
+
+    /// Whether the program was linked as [separable](#method.separable).
+    pub fn is_separable(&self) -> bool {
+        let mut separable = gl::FALSE as GLint;
+        unsafe {
+            self.gl
+                .GetProgramiv(self.id, gl::PROGRAM_SEPARABLE, &mut separable)
         }
+        separable != gl::FALSE as GLint
+    }
 
+    /// Link the program from `shaders` which are detached again afterwards.
+    fn attach_and_link(mut self, shaders: &[Shader]) -> Result<Self, GlError> {
         // Attach each shader to program
         for shader in shaders {
             info!("Attaching {} shader", shader.kind());
-            unsafe { gl.AttachShader(program, shader.id()) }
+            unsafe { self.gl.AttachShader(self.id, shader.id()) }
         }
 
         // Link shader program
-        unsafe { gl.LinkProgram(program) }
+        unsafe { self.gl.LinkProgram(self.id) }
+        self.check_link_status()?;
+
+        // Detach shaders so they may be deleted later when dropped.
+        for shader in shaders {
+            unsafe { self.gl.DetachShader(self.id, shader.id()) }
+            self.stages |= shader.kind().into();
+        }
+        Ok(self)
+    }
+
+    /// Load a program from a binary returned by [binary](#method.binary) with
+    /// [glProgramBinary](https://docs.gl/gl4/glProgramBinary).
+    ///
+    /// Binaries don't record their stages, so `stages` are the stages it was linked from.
+    /// Returns None if the driver rejects the binary, e.g. because the driver was updated since
+    /// the binary was saved.
+    pub(super) fn from_binary<S>(
+        gl: Rc<Gl>,
+        format: GLenum,
+        binary: &[u8],
+        stages: ShaderStages,
+        label: S,
+    ) -> Result<Option<Self>, GlError>
+    where
+        S: Into<Rc<str>>,
+    {
+        let mut program = ShaderProgram::create(gl, label.into())?;
+        program.stages = stages;
+        unsafe {
+            program.gl.ProgramBinary(
+                program.id,
+                format,
+                binary.as_ptr() as *const GLvoid,
+                binary.len() as GLsizei,
+            )
+        }
+        Ok(program.check_link_status().is_ok().then_some(program))
+    }
 
+    /// Binary format and contents of the linked program which may be cached and loaded with
+    /// [from_binary](#method.from_binary).
+    ///
+    /// Returns None if the driver can't provide a binary.
+    pub(super) fn binary(&self) -> Option<(GLenum, Vec<u8>)> {
+        let mut len: GLint = 0;
+        unsafe {
+            self.gl
+                .GetProgramiv(self.id, gl::PROGRAM_BINARY_LENGTH, &mut len)
+        }
+        if len <= 0 {
+            return None;
+        }
+
+        let mut format: GLenum = 0;
+        let mut written: GLsizei = 0;
+        let mut binary = vec![0u8; len as usize];
+        unsafe {
+            self.gl.GetProgramBinary(
+                self.id,
+                len,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr() as *mut GLvoid,
+            )
+        }
+        binary.truncate(written.max(0) as usize);
+        (!binary.is_empty()).then_some((format, binary))
+    }
+
+    /// Swap in a new version of this program, e.g. after its shaders were edited.
+    ///
+    /// The previous program object is deleted and cached uniform locations are forgotten. Block
+    /// bindings set with [bind_uniform_block](#method.bind_uniform_block) are applied to the new
+    /// version, but uniform values must be set again.
+    pub(super) fn replace(&mut self, mut program: ShaderProgram) {
+        std::mem::swap(&mut self.id, &mut program.id);
+        self.stages = program.stages;
+        self.uniforms.get_mut().clear();
+
+        let bindings = std::mem::take(self.block_bindings.get_mut());
+        for (name, binding) in bindings.iter() {
+            if let Err(e) = self.bind_uniform_block(name, *binding) {
+                warn!("Dropping the binding of block '{name}' after replacing it: {e}");
+            }
+        }
+    }
+
+    /// Create an empty program object which is deleted when dropped.
+    fn create(gl: Rc<Gl>, label: Rc<str>) -> Result<Self, GlError> {
+        // Create shader program
+        let id = unsafe { gl.CreateProgram() };
+
+        // CreateProgram returns 0 on errors, but errors only occur if something is really broken (like a Context error).
+        if id == 0 {
+            error!("CreateProgram failed to generate an object id");
+            Err(GlError::ShaderProgram(
+                "CreateProgram returned an object id of 0".into(),
+            ))?
+        }
+
+        gl.object_label(ObjectName::Program, id, &label);
+        Ok(Self {
+            gl,
+            id,
+            uniforms: Default::default(),
+            block_bindings: Default::default(),
+            stages: ShaderStages::empty(),
+            label,
+        })
+    }
+
+    /// Return the info log if the program failed to link.
+    fn check_link_status(&self) -> Result<(), GlError> {
         // Handle LinkProgram errors.
         let mut success = gl::TRUE as _;
         unsafe {
-            gl.GetProgramiv(program, gl::LINK_STATUS, &mut success);
+            self.gl.GetProgramiv(self.id, gl::LINK_STATUS, &mut success);
         }
-        if success == gl::FALSE as _ {
+        if success == gl::FALSE as GLint {
             let mut len: GLint = 0;
             unsafe {
-                gl.GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
+                self.gl.GetProgramiv(self.id, gl::INFO_LOG_LENGTH, &mut len);
             }
 
             // Retrieve error string from OpenGL
-            let error = Gl::create_whitespace_cstring(len as _);
+            let error = Gl::create_whitespace_cstring(len.max(0) as _);
             unsafe {
-                gl.GetProgramInfoLog(
-                    program,
+                self.gl.GetProgramInfoLog(
+                    self.id,
                     len,
                     std::ptr::null_mut(),
                     error.as_ptr() as *mut gl::types::GLchar,
@@ -71,16 +246,7 @@ impl ShaderProgram {
             // And return the error
             Err(GlError::ShaderProgram(error.to_string_lossy().to_string()))
         } else {
-            // Detach shaders so they may be deleted later when dropped.
-            for shader in shaders {
-                unsafe { gl.DetachShader(program, shader.id()) }
-            }
-
-            Ok(Self {
-                gl,
-                id: program,
-                label,
-            })
+            Ok(())
         }
     }
 
@@ -108,9 +274,85 @@ impl ShaderProgram {
         Ok(program)
     }
 
+    /// Return OpenGL object id.
+    pub(super) fn id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Stages of the shaders that the program was linked from.
+    pub fn stages(&self) -> ShaderStages {
+        self.stages
+    }
+
     pub fn set_used(&self) {
         unsafe { self.gl.UseProgram(self.id) }
     }
+
+    /// List the program's active inputs, outputs, uniforms and blocks.
+    ///
+    /// Requires OpenGL 4.3 or
+    /// [ARB_program_interface_query](https://registry.khronos.org/OpenGL/extensions/ARB/ARB_program_interface_query.txt).
+    pub fn interface(&self) -> ProgramInterface {
+        ProgramInterface::query(&self.gl, self.id)
+    }
+
+    /// Assign the uniform block called `name` to the indexed uniform buffer `binding` point.
+    ///
+    /// Buffers bound to the same point with
+    /// [UniformBuffer::bind](crate::memory::UniformBuffer::bind) back the block. The binding is
+    /// kept when shader hot reloading replaces the program.
+    pub fn bind_uniform_block(&self, name: &str, binding: u32) -> Result<(), GlError> {
+        let c_name = CString::new(name).map_err(|_| {
+            GlError::ShaderProgram(format!("Uniform block name '{name}' contains a null byte"))
+        })?;
+
+        let index = unsafe { self.gl.GetUniformBlockIndex(self.id, c_name.as_ptr()) };
+        if index == gl::INVALID_INDEX {
+            return Err(GlError::ShaderProgram(format!(
+                "'{}' has no active uniform block named '{name}'",
+                self.label
+            )));
+        }
+
+        unsafe { self.gl.UniformBlockBinding(self.id, index, binding) }
+        self.block_bindings
+            .borrow_mut()
+            .insert(name.into(), binding);
+        Ok(())
+    }
+
+    /// Set the uniform called `name` to `value` without binding the program.
+    ///
+    /// Unknown names, including uniforms that were optimized away, are logged the first time
+    /// they're used and ignored.
+    pub fn set_uniform<U>(&self, name: &str, value: &U)
+    where
+        U: Uniform + ?Sized,
+    {
+        if let Some(location) = self.uniform_location(name) {
+            value.set_uniform(&self.gl, self.id, location)
+        }
+    }
+
+    /// Look up the location of the uniform called `name`.
+    ///
+    /// Locations are cached since they don't change after linking.
+    pub fn uniform_location(&self, name: &str) -> Option<GLint> {
+        if let Some(&location) = self.uniforms.borrow().get(name) {
+            return (location != -1).then_some(location);
+        }
+
+        let location = match CString::new(name) {
+            Ok(c_name) => unsafe { self.gl.GetUniformLocation(self.id, c_name.as_ptr()) },
+            Err(_) => -1,
+        };
+        if location == -1 {
+            warn!("'{}' has no active uniform named '{name}'", self.label);
+        }
+
+        self.uniforms.borrow_mut().insert(name.into(), location);
+        (location != -1).then_some(location)
+    }
 }
 
 impl Label for ShaderProgram {
@@ -123,6 +365,89 @@ impl Label for ShaderProgram {
 
 impl Drop for ShaderProgram {
     fn drop(&mut self) {
+        self.gl.log_deletion(ObjectName::Program, self.id);
         unsafe { self.gl.DeleteProgram(self.id) }
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        context::headless::HeadlessContext,
+        shaders::{ShaderFrom, ShaderKind},
+    };
+
+    #[test]
+    fn unknown_uniforms_are_cached() {
+        let context = HeadlessContext::for_tests();
+        let program = ShaderProgram::from_raw(
+            context.gl(),
+            [ShaderDescriptor::new(
+                ShaderKind::Vertex,
+                ShaderFrom::Source(
+                    "#version 330 core
+                    uniform float Scale;
+                    void main() { gl_Position = vec4(Scale); }"
+                        .into(),
+                ),
+            )],
+            "Cached",
+        )
+        .unwrap();
+
+        assert!(program.uniform_location("Scale").is_some());
+        assert_eq!(program.uniform_location("Missing"), None);
+        // The second lookup is answered from the cache so the warning isn't repeated.
+        assert_eq!(program.uniform_location("Missing"), None);
+        assert_eq!(program.uniforms.borrow().get("Missing"), Some(&-1));
+        assert_eq!(program.uniforms.borrow().len(), 2);
+
+        // Setting an unknown uniform is ignored.
+        program.set_uniform("Missing", &1.0f32);
+        assert_eq!(program.uniforms.borrow().len(), 2);
+    }
+
+    #[test]
+    fn block_bindings_are_reapplied_after_replacing() {
+        let context = HeadlessContext::for_tests();
+        let program = |scale: &'static str, block: &'static str| {
+            let source = format!(
+                "#version 330 core
+                layout (std140) uniform {block} {{ vec4 Offset; }};
+                void main() {{ gl_Position = Offset * {scale}; }}"
+            );
+            let descriptor =
+                ShaderDescriptor::new(ShaderKind::Vertex, ShaderFrom::Source(source.into()));
+            ShaderProgram::from_raw(context.gl(), [descriptor], "Replaced").unwrap()
+        };
+        let binding = |program: &ShaderProgram, name: &str| {
+            let name = CString::new(name).unwrap();
+            let mut binding = -1;
+            unsafe {
+                let index = program.gl.GetUniformBlockIndex(program.id, name.as_ptr());
+                program.gl.GetActiveUniformBlockiv(
+                    program.id,
+                    index,
+                    gl::UNIFORM_BLOCK_BINDING,
+                    &mut binding,
+                );
+            }
+            binding
+        };
+
+        let mut replaced = program("1.0", "Camera");
+        replaced.bind_uniform_block("Camera", 3).unwrap();
+        replaced.uniform_location("Missing");
+        let id = replaced.id;
+        replaced.replace(program("2.0", "Camera"));
+        assert_ne!(replaced.id, id);
+        assert_eq!(binding(&replaced, "Camera"), 3);
+        assert!(replaced.uniforms.borrow().is_empty());
+
+        // Bindings of blocks that the new version doesn't have are dropped.
+        replaced.replace(program("3.0", "Lights"));
+        assert_eq!(binding(&replaced, "Lights"), 0);
+        assert!(replaced.block_bindings.borrow().is_empty());
+    }
+}
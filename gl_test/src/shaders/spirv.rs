@@ -0,0 +1,236 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use log::info;
+
+use crate::glerror::GlError;
+
+/// First word of every SPIR-V module.
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+/// Where a SPIR-V binary is loaded from.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpirvBinary {
+    // Compiled module, e.g. from include_bytes!
+    Bytes(Cow<'static, [u8]>),
+    // File containing a compiled module
+    FilePath(PathBuf),
+}
+
+/// Precompiled SPIR-V module and how to specialize it into a shader.
+///
+/// The same module may contain entry points for several stages, so the stage is still picked by
+/// [ShaderDescriptor::kind](super::ShaderDescriptor::kind). Requires OpenGL 4.6 or
+/// `GL_ARB_gl_spirv`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SpirvModule {
+    pub binary: SpirvBinary,
+    /// Name of the entry point function, usually `main`
+    pub entry_point: Cow<'static, str>,
+    /// Specialization constant ids with the bits of their values, e.g. from `f32::to_bits`.
+    /// Constants that aren't listed keep their default values.
+    pub constants: Vec<(u32, u32)>,
+}
+
+impl SpirvModule {
+    /// Module with a `main` entry point and default constants.
+    pub fn new(binary: SpirvBinary) -> Self {
+        Self {
+            binary,
+            entry_point: "main".into(),
+            constants: Vec::new(),
+        }
+    }
+
+    /// Read the binary and check that it looks like a SPIR-V module.
+    pub(super) fn load(&self) -> Result<Cow<'static, [u8]>, GlError> {
+        let (bytes, name) = match &self.binary {
+            SpirvBinary::Bytes(bytes) => (bytes.clone(), Cow::Borrowed("bytes")),
+            SpirvBinary::FilePath(path) => {
+                let path_name = path.to_string_lossy();
+                info!("Loading SPIR-V module from file: {}", path_name);
+                let bytes = std::fs::read(path)
+                    .map_err(|e| GlError::Shader(format!("{e}\nPath: {}", path_name)))?;
+                (Cow::Owned(bytes), path_name.into_owned().into())
+            }
+        };
+
+        // Modules are streams of 32 bit words in the host's byte order.
+        let magic = bytes
+            .get(..4)
+            .map(|word| u32::from_ne_bytes(word.try_into().expect("Four bytes")));
+        if !bytes.len().is_multiple_of(4) || magic != Some(SPIRV_MAGIC) {
+            return Err(GlError::Shader(format!(
+                "SPIR-V module from {name} isn't a valid binary"
+            )));
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compute shader that stores a specialization constant in a storage buffer. Equivalent to:
+    ///
+    /// ```glsl
+    /// layout (local_size_x = 1) in;
+    /// layout (constant_id = 0) const uint Value = 42;
+    /// layout (std430, binding = 0) buffer Out { uint value; };
+    /// void main() { value = Value; }
+    /// ```
+    const STORE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/shaders/store.spv");
+
+    #[test]
+    fn binaries_are_loaded() {
+        let module = SpirvModule::new(SpirvBinary::FilePath(STORE.into()));
+        let bytes = module.load().unwrap();
+        assert_eq!(bytes, std::fs::read(STORE).unwrap());
+
+        let module = SpirvModule::new(SpirvBinary::Bytes(bytes));
+        assert!(module.load().is_ok());
+    }
+
+    #[test]
+    fn invalid_binaries_are_rejected() {
+        let bytes = std::fs::read(STORE).unwrap();
+        let load = |bytes: Vec<u8>| SpirvModule::new(SpirvBinary::Bytes(bytes.into())).load();
+
+        // GLSL source, a truncated word and a missing magic number
+        assert!(load(b"#version 460 core\n".to_vec()).is_err());
+        assert!(load(bytes[..bytes.len() - 1].to_vec()).is_err());
+        assert!(load(bytes[4..].to_vec()).is_err());
+        assert!(load(Vec::new()).is_err());
+
+        let missing = SpirvModule::new(SpirvBinary::FilePath("missing.spv".into()));
+        assert!(missing.load().is_err());
+    }
+
+    /// Opcodes and operands of the instructions after the five word header.
+    fn instructions(words: &[u32]) -> Vec<(u32, &[u32])> {
+        let mut instructions = Vec::new();
+        let mut rest = &words[5..];
+        while let Some(&first) = rest.first() {
+            let (instruction, next) = rest.split_at((first >> 16) as usize);
+            instructions.push((first & 0xffff, &instruction[1..]));
+            rest = next;
+        }
+        instructions
+    }
+
+    #[test]
+    fn fixture_declares_a_specializable_entry_point() {
+        let bytes = SpirvModule::new(SpirvBinary::FilePath(STORE.into()))
+            .load()
+            .unwrap();
+        let words: Vec<_> = bytes
+            .chunks_exact(4)
+            .map(|word| u32::from_ne_bytes(word.try_into().unwrap()))
+            .collect();
+        // Magic number, version 1.0, generator, id bound and reserved schema
+        assert_eq!(words[..2], [SPIRV_MAGIC, 0x0001_0000]);
+        assert_eq!(words[4], 0);
+        let bound = words[3];
+
+        let instructions = instructions(&words);
+        // OpEntryPoint GLCompute %main "main"
+        let (_, entry_point) = instructions
+            .iter()
+            .find(|(opcode, _)| *opcode == 15)
+            .expect("An entry point");
+        assert_eq!(entry_point[0], 5);
+        let name: Vec<u8> = entry_point[2..]
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .take_while(|&byte| byte != 0)
+            .collect();
+        assert_eq!(name, b"main");
+        assert!(entry_point[1] < bound);
+
+        // OpDecorate %value SpecId 0 on an OpSpecConstant with a default of 42
+        let (_, spec_id) = instructions
+            .iter()
+            .find(|(opcode, operands)| *opcode == 71 && operands[1] == 1)
+            .expect("A specialization constant");
+        assert_eq!(spec_id[2], 0);
+        let constant = instructions
+            .iter()
+            .find(|(opcode, operands)| *opcode == 50 && operands[1] == spec_id[0]);
+        assert_eq!(constant.map(|(_, operands)| operands[2]), Some(42));
+    }
+
+    #[cfg(unix)]
+    mod headless {
+        use super::*;
+        use crate::{
+            context::{headless::HeadlessContext, Gl},
+            glenums::MemoryBarrier,
+            memory::{BufferBackend, StorageBuffer},
+            shaders::{ComputeProgram, ShaderDescriptor, ShaderFrom, ShaderKind},
+        };
+        use std::rc::Rc;
+
+        /// Context for a test that needs SPIR-V, or None after noting that the test is skipped.
+        fn spirv_context(test: &str) -> Option<HeadlessContext> {
+            let context = HeadlessContext::for_tests();
+            let info = context.gl().info().clone();
+            if info.supports_spirv() {
+                Some(context)
+            } else {
+                eprintln!("Skipping {test}: {} doesn't support SPIR-V", info.renderer);
+                None
+            }
+        }
+
+        fn store(gl: Rc<Gl>, module: SpirvModule) -> Result<u32, GlError> {
+            let program = ComputeProgram::new(
+                gl.clone(),
+                ShaderDescriptor::new(ShaderKind::Compute, ShaderFrom::Spirv(module)),
+                "Store",
+            )?;
+            let out = StorageBuffer::<u32>::zeroed(gl, BufferBackend::Dsa, 1, "Out")?;
+            out.bind(0);
+            program.dispatch([1, 1, 1])?;
+            program.memory_barrier(MemoryBarrier::BufferUpdate);
+            Ok(out.read_back()?[0])
+        }
+
+        #[test]
+        fn modules_are_specialized() {
+            let Some(context) = spirv_context("modules_are_specialized") else {
+                return;
+            };
+            let gl = context.gl();
+
+            let module = SpirvModule::new(SpirvBinary::FilePath(STORE.into()));
+            assert_eq!(store(gl.clone(), module.clone()).unwrap(), 42);
+
+            let specialized = SpirvModule {
+                constants: vec![(0, 7)],
+                ..module
+            };
+            assert_eq!(store(gl, specialized).unwrap(), 7);
+        }
+
+        #[test]
+        fn missing_entry_points_are_reported() {
+            let Some(context) = spirv_context("missing_entry_points_are_reported") else {
+                return;
+            };
+            let gl = context.gl();
+
+            let module = SpirvModule {
+                entry_point: "missing".into(),
+                ..SpirvModule::new(SpirvBinary::FilePath(STORE.into()))
+            };
+            match store(gl, module) {
+                Err(GlError::ShaderCompile { diagnostics, .. }) => {
+                    assert!(diagnostics
+                        .iter()
+                        .any(|diagnostic| diagnostic.message.contains("missing")));
+                }
+                result => panic!("Expected a compile error, got {result:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use crate::context::{
+    gl::{
+        self,
+        types::{GLint, GLuint},
+    },
+    Gl,
+};
+use nalgebra_glm as glm;
+
+/// Value that can be uploaded to a uniform with
+/// [glProgramUniform](https://docs.gl/gl4/glProgramUniform).
+///
+/// Matrices are uploaded in column major order which is the layout used by both `nalgebra-glm`
+/// and `cgmath`.
+pub trait Uniform {
+    /// Set the uniform at `location` of `program` to this value.
+    ///
+    /// The program doesn't need to be bound.
+    fn set_uniform(&self, gl: &Gl, program: GLuint, location: GLint);
+}
+
+/// Implement [Uniform] for types that can be viewed as a slice of scalars.
+macro_rules! impl_uniform {
+    ($func:ident, $scalar:ty, $($t:ty => $as_ptr:expr),+ $(,)?) => {
+        $(
+            impl Uniform for $t {
+                fn set_uniform(&self, gl: &Gl, program: GLuint, location: GLint) {
+                    let ptr: *const $scalar = $as_ptr(self);
+                    unsafe { gl.$func(program, location, 1, ptr) }
+                }
+            }
+        )+
+    };
+}
+
+/// Implement [Uniform] for matrices that can be viewed as a slice of floats.
+macro_rules! impl_uniform_matrix {
+    ($func:ident, $($t:ty => $as_ptr:expr),+ $(,)?) => {
+        $(
+            impl Uniform for $t {
+                fn set_uniform(&self, gl: &Gl, program: GLuint, location: GLint) {
+                    let ptr: *const f32 = $as_ptr(self);
+                    unsafe { gl.$func(program, location, 1, gl::FALSE, ptr) }
+                }
+            }
+        )+
+    };
+}
+
+impl Uniform for f32 {
+    fn set_uniform(&self, gl: &Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform1f(program, location, *self) }
+    }
+}
+
+impl Uniform for i32 {
+    fn set_uniform(&self, gl: &Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform1i(program, location, *self) }
+    }
+}
+
+impl Uniform for u32 {
+    fn set_uniform(&self, gl: &Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform1ui(program, location, *self) }
+    }
+}
+
+impl Uniform for bool {
+    fn set_uniform(&self, gl: &Gl, program: GLuint, location: GLint) {
+        // Booleans are set with the integer functions.
+        unsafe { gl.ProgramUniform1i(program, location, *self as GLint) }
+    }
+}
+
+// Arrays
+impl_uniform!(ProgramUniform2fv, f32, [f32; 2] => <[f32]>::as_ptr);
+impl_uniform!(ProgramUniform3fv, f32, [f32; 3] => <[f32]>::as_ptr);
+impl_uniform!(ProgramUniform4fv, f32, [f32; 4] => <[f32]>::as_ptr);
+impl_uniform!(ProgramUniform2iv, i32, [i32; 2] => <[i32]>::as_ptr);
+impl_uniform!(ProgramUniform3iv, i32, [i32; 3] => <[i32]>::as_ptr);
+impl_uniform!(ProgramUniform4iv, i32, [i32; 4] => <[i32]>::as_ptr);
+impl_uniform!(ProgramUniform2uiv, u32, [u32; 2] => <[u32]>::as_ptr);
+impl_uniform!(ProgramUniform3uiv, u32, [u32; 3] => <[u32]>::as_ptr);
+impl_uniform!(ProgramUniform4uiv, u32, [u32; 4] => <[u32]>::as_ptr);
+
+// nalgebra-glm
+impl_uniform!(ProgramUniform2fv, f32, glm::Vec2 => glm::Vec2::as_ptr);
+impl_uniform!(ProgramUniform3fv, f32, glm::Vec3 => glm::Vec3::as_ptr);
+impl_uniform!(ProgramUniform4fv, f32, glm::Vec4 => glm::Vec4::as_ptr);
+impl_uniform!(ProgramUniform2iv, i32, glm::IVec2 => glm::IVec2::as_ptr);
+impl_uniform!(ProgramUniform3iv, i32, glm::IVec3 => glm::IVec3::as_ptr);
+impl_uniform!(ProgramUniform4iv, i32, glm::IVec4 => glm::IVec4::as_ptr);
+impl_uniform!(ProgramUniform2uiv, u32, glm::UVec2 => glm::UVec2::as_ptr);
+impl_uniform!(ProgramUniform3uiv, u32, glm::UVec3 => glm::UVec3::as_ptr);
+impl_uniform!(ProgramUniform4uiv, u32, glm::UVec4 => glm::UVec4::as_ptr);
+impl_uniform_matrix!(
+    ProgramUniformMatrix2fv,
+    glm::Mat2 => glm::Mat2::as_ptr,
+);
+impl_uniform_matrix!(
+    ProgramUniformMatrix3fv,
+    glm::Mat3 => glm::Mat3::as_ptr,
+);
+impl_uniform_matrix!(
+    ProgramUniformMatrix4fv,
+    glm::Mat4 => glm::Mat4::as_ptr,
+);
+
+// cgmath
+impl_uniform!(
+    ProgramUniform2fv,
+    f32,
+    cgmath::Vector2<f32> => |v: &cgmath::Vector2<f32>| AsRef::<[f32; 2]>::as_ref(v).as_ptr(),
+);
+impl_uniform!(
+    ProgramUniform3fv,
+    f32,
+    cgmath::Vector3<f32> => |v: &cgmath::Vector3<f32>| AsRef::<[f32; 3]>::as_ref(v).as_ptr(),
+    cgmath::Point3<f32> => |p: &cgmath::Point3<f32>| AsRef::<[f32; 3]>::as_ref(p).as_ptr(),
+);
+impl_uniform!(
+    ProgramUniform4fv,
+    f32,
+    cgmath::Vector4<f32> => |v: &cgmath::Vector4<f32>| AsRef::<[f32; 4]>::as_ref(v).as_ptr(),
+);
+impl_uniform_matrix!(
+    ProgramUniformMatrix2fv,
+    cgmath::Matrix2<f32> => |m: &cgmath::Matrix2<f32>| AsRef::<[f32; 4]>::as_ref(m).as_ptr(),
+);
+impl_uniform_matrix!(
+    ProgramUniformMatrix3fv,
+    cgmath::Matrix3<f32> => |m: &cgmath::Matrix3<f32>| AsRef::<[f32; 9]>::as_ref(m).as_ptr(),
+);
+impl_uniform_matrix!(
+    ProgramUniformMatrix4fv,
+    cgmath::Matrix4<f32> => |m: &cgmath::Matrix4<f32>| AsRef::<[f32; 16]>::as_ref(m).as_ptr(),
+);
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{
+        context::headless::HeadlessContext,
+        shaders::{ShaderDescriptor, ShaderFrom, ShaderKind, ShaderProgram},
+    };
+    use cgmath::SquareMatrix;
+    use std::rc::Rc;
+
+    /// Every uniform contributes to the output so that none of them are optimized away.
+    const VERTEX: &str = "#version 330 core
+        uniform float Scalar;
+        uniform ivec3 Integers;
+        uniform vec4 Vector;
+        uniform mat3 Rotation;
+        uniform mat4 Transform;
+
+        void main() {
+            vec3 position = Rotation * vec3(Integers) * Scalar;
+            gl_Position = Transform * vec4(position, 1.0) + Vector;
+        }";
+    const FRAGMENT: &str = "#version 330 core
+        out vec4 Color;
+
+        void main() {
+            Color = vec4(1.0);
+        }";
+
+    fn program(gl: Rc<Gl>) -> ShaderProgram {
+        ShaderProgram::from_raw(
+            gl,
+            [
+                ShaderDescriptor::new(ShaderKind::Vertex, ShaderFrom::Source(VERTEX.into())),
+                ShaderDescriptor::new(ShaderKind::Fragment, ShaderFrom::Source(FRAGMENT.into())),
+            ],
+            "Uniforms",
+        )
+        .unwrap()
+    }
+
+    /// Read back `N` floats of the uniform called `name`.
+    fn floats<const N: usize>(gl: &Gl, program: &ShaderProgram, name: &str) -> [f32; N] {
+        let location = program.uniform_location(name).unwrap();
+        let mut values = [0.0; N];
+        unsafe { gl.GetUniformfv(program.id(), location, values.as_mut_ptr()) }
+        values
+    }
+
+    #[test]
+    fn scalars_and_arrays_are_set() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let program = program(gl.clone());
+
+        program.set_uniform("Scalar", &2.5f32);
+        program.set_uniform("Integers", &[1, -2, 3]);
+        program.set_uniform("Vector", &[0.25f32, 0.5, 0.75, 1.0]);
+
+        assert_eq!(floats::<1>(&gl, &program, "Scalar"), [2.5]);
+        assert_eq!(floats::<4>(&gl, &program, "Vector"), [0.25, 0.5, 0.75, 1.0]);
+        let mut integers = [0; 3];
+        let location = program.uniform_location("Integers").unwrap();
+        unsafe { gl.GetUniformiv(program.id(), location, integers.as_mut_ptr()) }
+        assert_eq!(integers, [1, -2, 3]);
+    }
+
+    #[test]
+    fn glm_values_are_column_major() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let program = program(gl.clone());
+
+        let transform = glm::translation(&glm::vec3(1.0, 2.0, 3.0));
+        program.set_uniform("Transform", &transform);
+        program.set_uniform("Rotation", &glm::Mat3::identity());
+        program.set_uniform("Vector", &glm::vec4(1.0, 2.0, 3.0, 4.0));
+
+        let transform = floats::<16>(&gl, &program, "Transform");
+        assert_eq!(transform[12..15], [1.0, 2.0, 3.0]);
+        assert_eq!(
+            floats::<9>(&gl, &program, "Rotation"),
+            [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]
+        );
+        assert_eq!(floats::<4>(&gl, &program, "Vector"), [1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn cgmath_values_are_column_major() {
+        let context = HeadlessContext::for_tests();
+        let gl = context.gl();
+        let program = program(gl.clone());
+
+        let transform = cgmath::Matrix4::from_translation(cgmath::vec3(1.0, 2.0, 3.0));
+        program.set_uniform("Transform", &transform);
+        program.set_uniform("Rotation", &cgmath::Matrix3::identity());
+        program.set_uniform("Vector", &cgmath::vec4(1.0, 2.0, 3.0, 4.0));
+
+        let transform = floats::<16>(&gl, &program, "Transform");
+        assert_eq!(transform[12..15], [1.0, 2.0, 3.0]);
+        assert_eq!(
+            floats::<9>(&gl, &program, "Rotation"),
+            [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]
+        );
+        assert_eq!(floats::<4>(&gl, &program, "Vector"), [1.0, 2.0, 3.0, 4.0]);
+    }
+}
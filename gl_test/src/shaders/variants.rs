@@ -0,0 +1,171 @@
+use super::{ProgramCache, ShaderDescriptor, ShaderProgram};
+use crate::{context::Gl, glerror::GlError, label::Label};
+use log::info;
+use std::{borrow::Cow, collections::HashMap, rc::Rc};
+
+/// Permutations of one set of shaders compiled with different feature keywords, e.g. textured
+/// vs colored or skinned vs static.
+///
+/// Every keyword of a variant is defined to `1` after the `#version` line of each shader so the
+/// sources can test for it with `#ifdef`. Variants are compiled on first use and kept until the
+/// set is dropped. Each is labelled with the set's label and its keywords, e.g.
+/// `Mesh[SKINNED TEXTURED]`. The label isn't part of the [ProgramCache] key, which hashes the
+/// driver and the descriptors with the keywords' defines.
+#[allow(dead_code)]
+pub struct ShaderVariants {
+    gl: Rc<Gl>,
+    label: Rc<str>,
+    descriptors: Vec<ShaderDescriptor>,
+    cache: ProgramCache,
+    // Compiled variants by their sorted keywords
+    programs: HashMap<Vec<Cow<'static, str>>, ShaderProgram>,
+}
+
+#[allow(dead_code)]
+impl ShaderVariants {
+    /// Variants of the program made from `raw_shaders`. Nothing is compiled until a variant is
+    /// requested.
+    pub fn new<S, I>(gl: Rc<Gl>, raw_shaders: I, cache: &ProgramCache, label: S) -> Self
+    where
+        S: Into<Rc<str>>,
+        I: IntoIterator<Item = ShaderDescriptor>,
+    {
+        Self {
+            gl,
+            label: label.into(),
+            descriptors: raw_shaders.into_iter().collect(),
+            cache: cache.clone(),
+            programs: HashMap::new(),
+        }
+    }
+
+    /// Program with `keywords` defined, compiling it if it's the first use.
+    ///
+    /// The order of the keywords and duplicates don't matter. Keywords must be valid GLSL
+    /// identifiers.
+    pub fn variant<K>(&mut self, keywords: K) -> Result<&ShaderProgram, GlError>
+    where
+        K: IntoIterator,
+        K::Item: Into<Cow<'static, str>>,
+    {
+        let mut keywords: Vec<_> = keywords.into_iter().map(Into::into).collect();
+        keywords.sort();
+        keywords.dedup();
+
+        if !self.programs.contains_key(&keywords) {
+            let program = self.compile(&keywords)?;
+            self.programs.insert(keywords.clone(), program);
+        }
+        Ok(&self.programs[&keywords])
+    }
+
+    /// Descriptors of the variant with `keywords` as they're compiled, e.g. to
+    /// [watch](super::ShaderReloader::watch) the variant for changes.
+    pub fn descriptors(&self, keywords: &[Cow<'static, str>]) -> Vec<ShaderDescriptor> {
+        self.descriptors
+            .iter()
+            .cloned()
+            .map(|mut descriptor| {
+                let defines = keywords.iter().map(|keyword| (keyword.clone(), "1".into()));
+                descriptor.options.defines.extend(defines);
+                descriptor
+            })
+            .collect()
+    }
+
+    /// Number of variants compiled so far.
+    pub fn compiled(&self) -> usize {
+        self.programs.len()
+    }
+
+    /// Label of the variant with sorted and deduplicated `keywords`.
+    fn variant_label(&self, keywords: &[Cow<'static, str>]) -> String {
+        format!("{}[{}]", self.label, keywords.join(" "))
+    }
+
+    fn compile(&self, keywords: &[Cow<'static, str>]) -> Result<ShaderProgram, GlError> {
+        let label = self.variant_label(keywords);
+        if let Some(keyword) = keywords.iter().find(|keyword| !is_identifier(keyword)) {
+            return Err(GlError::Shader(format!(
+                "Keyword '{keyword}' of shader variant '{label}' isn't a valid identifier"
+            )));
+        }
+
+        info!("Compiling shader variant '{label}'");
+        self.cache
+            .program(self.gl.clone(), self.descriptors(keywords), label)
+    }
+}
+
+impl Label for ShaderVariants {
+    type Output = Rc<str>;
+
+    fn label(&self) -> Self::Output {
+        self.label.clone()
+    }
+}
+
+/// Letters, digits and underscores not starting with a digit.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifiers_are_validated() {
+        for name in ["A", "_", "TEXTURED", "use_fog", "_2D", "x86_64"] {
+            assert!(is_identifier(name), "{name}");
+        }
+        for name in ["", "2D", "USE-FOG", "USE FOG", "A=1", "Ä", "#define"] {
+            assert!(!is_identifier(name), "{name}");
+        }
+    }
+
+    #[cfg(unix)]
+    mod headless {
+        use super::*;
+        use crate::{
+            context::headless::HeadlessContext,
+            shaders::{ShaderFrom, ShaderKind},
+        };
+
+        /// Vertex shader that only compiles if both keywords are defined.
+        fn variants(gl: Rc<Gl>) -> ShaderVariants {
+            let source = "#version 330 core
+                #if defined(A) && defined(B)
+                void main() { gl_Position = vec4(0.0); }
+                #endif";
+            ShaderVariants::new(
+                gl,
+                [ShaderDescriptor::new(
+                    ShaderKind::Vertex,
+                    ShaderFrom::Source(source.into()),
+                )],
+                &ProgramCache::disabled(),
+                "Variants",
+            )
+        }
+
+        #[test]
+        fn equivalent_keywords_compile_once() {
+            let context = HeadlessContext::for_tests();
+            let mut variants = variants(context.gl());
+
+            let label = variants.variant(["B", "A", "A"]).unwrap().label();
+            assert_eq!(&*label, "Variants[A B]");
+            variants.variant(["A", "B"]).unwrap();
+            assert_eq!(variants.compiled(), 1);
+
+            assert!(variants.variant(["A"]).is_err());
+            assert!(variants.variant(["A", "B", "C-D"]).is_err());
+            assert_eq!(variants.compiled(), 1);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Golden image regression tests.
+//!
+//! Each test renders a resource offscreen and compares it against a reference PNG in
+//! `tests/golden`. Channels may differ by up to [TOLERANCE] to allow for rasterizer differences.
+//! On failure the rendered frame and a diff image, where mismatched pixels are red, are written to
+//! Cargo's temporary directory for integration tests.
+//!
+//! Run with `GOLDEN_UPDATE=1` to overwrite the references with the current output.
+
+#![cfg(unix)]
+
+use gl_test::GlHeadless;
+use image::{Rgba, RgbaImage};
+use std::{env, fs, path::PathBuf};
+
+/// Maximum difference allowed per channel.
+const TOLERANCE: u8 = 2;
+
+/// Size of every rendered frame.
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 96;
+
+/// Result of comparing a frame against its reference.
+struct Comparison {
+    mismatched: usize,
+    diff: RgbaImage,
+}
+
+/// Compare two images of the same size channel by channel.
+fn compare(actual: &RgbaImage, reference: &RgbaImage, tolerance: u8) -> Comparison {
+    let mut mismatched = 0;
+    let diff = RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let Rgba(a) = *actual.get_pixel(x, y);
+        let Rgba(r) = *reference.get_pixel(x, y);
+
+        if a.iter().zip(r).any(|(a, r)| a.abs_diff(r) > tolerance) {
+            mismatched += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            // Faded copy of the frame so mismatches are easy to place
+            let luma = (a[0] as u32 + a[1] as u32 + a[2] as u32) / 3;
+            let faded = (luma / 4 + 160) as u8;
+            Rgba([faded, faded, faded, 255])
+        }
+    });
+
+    Comparison { mismatched, diff }
+}
+
+/// Create an offscreen context with the test resources loaded.
+fn headless() -> GlHeadless {
+    GlHeadless::new(WIDTH, HEIGHT).expect("Headless context")
+}
+
+/// Render `name` and check it against `tests/golden/{name}.png`.
+fn assert_golden(name: &str) {
+    let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let headless = headless();
+    let actual = headless
+        .render(name)
+        .unwrap_or_else(|e| panic!("Rendering {name} failed: {e}"));
+
+    let reference_path = manifest.join("tests/golden").join(format!("{name}.png"));
+    if env::var_os("GOLDEN_UPDATE").is_some() {
+        actual.save(&reference_path).expect("Writing reference");
+        return;
+    }
+
+    let reference = image::open(&reference_path)
+        .unwrap_or_else(|e| panic!("Loading reference {}: {e}", reference_path.display()))
+        .into_rgba8();
+    assert_eq!(
+        actual.dimensions(),
+        reference.dimensions(),
+        "Frame size of {name} differs from the reference"
+    );
+
+    let comparison = compare(&actual, &reference, TOLERANCE);
+    if comparison.mismatched > 0 {
+        let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        fs::create_dir_all(&out).expect("Creating output directory");
+        let actual_path = out.join(format!("{name}.actual.png"));
+        let diff_path = out.join(format!("{name}.diff.png"));
+        actual.save(&actual_path).expect("Writing frame");
+        comparison.diff.save(&diff_path).expect("Writing diff");
+
+        panic!(
+            "{} pixels of {name} differ from the reference by more than {TOLERANCE}.\nFrame: {}\nDiff: {}",
+            comparison.mismatched,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn triangle() {
+    assert_golden("triangle");
+}
+
+#[test]
+fn rectangle() {
+    assert_golden("rectangle");
+}
+
+#[test]
+fn textured_rectangle() {
+    assert_golden("textured_rectangle");
+}
+
+#[test]
+fn tessellated_rectangle() {
+    assert_golden("tessellated_rectangle");
+}
+
+#[test]
+fn render_to_texture() {
+    // Rendering into a texture and blitting it back must not change the frame.
+    let headless = headless();
+    let direct = headless.render("triangle").expect("Rendering directly");
+    let blitted = headless
+        .render_to_texture("triangle")
+        .expect("Rendering into a texture");
+
+    let comparison = compare(&blitted, &direct, 0);
+    assert_eq!(comparison.mismatched, 0);
+}
+
+#[test]
+fn unknown_resource() {
+    assert!(headless().render("pentagon").is_err());
+}
@@ -0,0 +1,222 @@
+//! Derive macros for [gl_test](../gl_test/index.html).
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields, Index};
+
+/// Derive `Std140` for a struct whose fields all implement `Std140`.
+///
+/// Fields are laid out in declaration order following the std140 rules so the struct can be
+/// copied into a uniform block with the same members. The Rust layout of the struct doesn't
+/// matter since fields are written one by one into a padded byte buffer.
+///
+/// Every field's alignment is checked at compile time, so a hand written `Std140` implementation
+/// with an invalid alignment fails to build rather than producing a misaligned block.
+///
+/// ```ignore
+/// #[derive(Std140)]
+/// struct Camera {
+///     view: glm::Mat4,
+///     eye: glm::Vec3,
+///     // Packed into the padding after eye at offset 76
+///     time: f32,
+/// }
+/// ```
+#[proc_macro_derive(Std140)]
+pub fn derive_std140(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    block_layout(input, BlockLayout::Std140)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derive `Std430` for a struct whose fields all implement `Std430`.
+///
+/// Works like [Std140](derive@Std140) except that structs are only aligned to their largest
+/// field and values can also be read back from a buffer.
+#[proc_macro_derive(Std430)]
+pub fn derive_std430(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    block_layout(input, BlockLayout::Std430)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Memory layout of an interface block.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BlockLayout {
+    /// Uniform blocks
+    Std140,
+    /// Shader storage blocks
+    Std430,
+}
+
+impl BlockLayout {
+    fn name(self) -> &'static str {
+        match self {
+            BlockLayout::Std140 => "Std140",
+            BlockLayout::Std430 => "Std430",
+        }
+    }
+
+    fn trait_path(self) -> TokenStream2 {
+        match self {
+            BlockLayout::Std140 => quote!(::gl_test::memory::Std140),
+            BlockLayout::Std430 => quote!(::gl_test::memory::Std430),
+        }
+    }
+
+    /// Minimum alignment of a struct. std140 aligns structs like a vec4.
+    fn struct_align(self) -> usize {
+        match self {
+            BlockLayout::Std140 => 16,
+            BlockLayout::Std430 => 4,
+        }
+    }
+}
+
+fn block_layout(input: DeriveInput, layout: BlockLayout) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let derive = layout.name();
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            format!("{derive} can't be derived for generic structs"),
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                format!("{derive} can only be derived for structs"),
+            ))
+        }
+    };
+    if fields.is_empty() {
+        return Err(Error::new(
+            fields.span(),
+            format!("{derive} structs need at least one field"),
+        ));
+    }
+
+    // Accessors work for both named and tuple fields.
+    let members: Vec<_> = match fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|field| {
+                let ident = field.ident.as_ref().expect("Named fields have identifiers");
+                quote!(#ident)
+            })
+            .collect(),
+        _ => (0..fields.len())
+            .map(|i| {
+                let index = Index::from(i);
+                quote!(#index)
+            })
+            .collect(),
+    };
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let count = types.len();
+    let last = &types[count - 1];
+    let indices: Vec<_> = (0..count).map(Index::from).collect();
+
+    let trait_path = layout.trait_path();
+    let align_to = quote!(::gl_test::memory::std140_align_to);
+
+    // Compile time checks of each field's alignment.
+    let checks = types.iter().map(|ty| {
+        quote_spanned! {ty.span()=>
+            ::std::assert!(
+                <#ty as #trait_path>::ALIGN.is_power_of_two()
+                    && <#ty as #trait_path>::ALIGN >= 4
+                    && <#ty as #trait_path>::SIZE % 4 == 0,
+                "Fields must be aligned to a power of two of at least four bytes"
+            );
+        }
+    });
+
+    let offsets = format_ident!("__{}_OFFSETS", derive.to_uppercase());
+    let struct_align = layout.struct_align();
+    let (write, read) = match layout {
+        BlockLayout::Std140 => (format_ident!("write_std140"), None),
+        BlockLayout::Std430 => (
+            format_ident!("write_std430"),
+            Some(format_ident!("read_std430")),
+        ),
+    };
+
+    // Only std430 values are read back.
+    let read = read.map(|read| {
+        let fields = members
+            .iter()
+            .zip(&types)
+            .zip(&indices)
+            .map(|((member, ty), index)| {
+                quote! {
+                    #member: <#ty as #trait_path>::#read({
+                        let offset = Self::#offsets[#index];
+                        &bytes[offset..offset + <#ty as #trait_path>::SIZE]
+                    })
+                }
+            });
+        quote! {
+            fn #read(bytes: &[u8]) -> Self {
+                Self { #(#fields),* }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #name {
+            /// Offset of each field in the block layout.
+            #[doc(hidden)]
+            const #offsets: [usize; #count] = {
+                let mut offsets = [0; #count];
+                let mut end = 0;
+                #(
+                    offsets[#indices] = #align_to(end, <#types as #trait_path>::ALIGN);
+                    end = offsets[#indices] + <#types as #trait_path>::SIZE;
+                )*
+                let _ = end;
+                offsets
+            };
+        }
+
+        const _: () = {
+            #(#checks)*
+        };
+
+        impl #trait_path for #name {
+            const ALIGN: usize = {
+                let mut align = #struct_align;
+                #(
+                    if <#types as #trait_path>::ALIGN > align {
+                        align = <#types as #trait_path>::ALIGN;
+                    }
+                )*
+                align
+            };
+            const SIZE: usize = #align_to(
+                Self::#offsets[#count - 1] + <#last as #trait_path>::SIZE,
+                <Self as #trait_path>::ALIGN,
+            );
+
+            fn #write(&self, out: &mut [u8]) {
+                #(
+                    let offset = Self::#offsets[#indices];
+                    #trait_path::#write(
+                        &self.#members,
+                        &mut out[offset..offset + <#types as #trait_path>::SIZE],
+                    );
+                )*
+            }
+
+            #read
+        }
+    })
+}